@@ -0,0 +1,4 @@
+ALTER TABLE newsletter_issues
+    ADD COLUMN recipients_count INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN delivered_count INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN failed_count INTEGER NOT NULL DEFAULT 0;
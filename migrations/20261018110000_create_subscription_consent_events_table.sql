@@ -0,0 +1,24 @@
+CREATE TABLE subscription_consent_events (
+    event_id uuid NOT NULL,
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id),
+    event_type TEXT NOT NULL,
+    source TEXT NOT NULL,
+    ip_address TEXT NULL,
+    user_agent TEXT NULL,
+    consent_text TEXT NULL,
+    recorded_at timestamptz NOT NULL,
+    PRIMARY KEY (event_id)
+);
+
+-- Consent evidence is append-only: rows can be erased together with
+-- the subscriber, but they can never be rewritten.
+CREATE FUNCTION reject_consent_event_update() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'subscription_consent_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER subscription_consent_events_append_only
+    BEFORE UPDATE ON subscription_consent_events
+    FOR EACH ROW EXECUTE FUNCTION reject_consent_event_update();
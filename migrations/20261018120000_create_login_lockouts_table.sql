@@ -0,0 +1,12 @@
+CREATE TABLE login_lockouts (
+    lockout_id uuid PRIMARY KEY,
+    -- Either `username` or `ip_address`
+    scope TEXT NOT NULL,
+    -- The username or the IP address that was locked out
+    locked_key TEXT NOT NULL,
+    ip_address TEXT,
+    failed_attempts INTEGER NOT NULL,
+    locked_at timestamptz NOT NULL,
+    locked_until timestamptz NOT NULL
+);
+CREATE INDEX login_lockouts_locked_at_idx ON login_lockouts (locked_at DESC);
@@ -0,0 +1,9 @@
+-- `NULL` when two-factor authentication is disabled
+ALTER TABLE users ADD COLUMN totp_secret TEXT NULL;
+
+CREATE TABLE recovery_codes (
+    user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    used_at timestamptz NULL,
+    PRIMARY KEY (user_id, code_hash)
+);
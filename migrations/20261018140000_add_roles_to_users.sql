@@ -0,0 +1,15 @@
+-- The existing users could do everything so far
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'owner';
+ALTER TABLE users ALTER COLUMN role DROP DEFAULT;
+ALTER TABLE users ADD COLUMN disabled_at timestamptz NULL;
+
+CREATE TABLE user_invitations (
+    invitation_id uuid PRIMARY KEY,
+    token_hash TEXT NOT NULL UNIQUE,
+    email TEXT NOT NULL,
+    role TEXT NOT NULL,
+    invited_by uuid NULL REFERENCES users (user_id) ON DELETE SET NULL,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL,
+    accepted_at timestamptz NULL
+);
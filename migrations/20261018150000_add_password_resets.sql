@@ -0,0 +1,10 @@
+-- Where password reset links are sent
+ALTER TABLE users ADD COLUMN email TEXT NULL UNIQUE;
+
+CREATE TABLE password_reset_tokens (
+    token_hash TEXT PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL,
+    expires_at timestamptz NOT NULL,
+    used_at timestamptz NULL
+);
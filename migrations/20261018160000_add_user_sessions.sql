@@ -0,0 +1,9 @@
+CREATE TABLE user_sessions (
+    session_id uuid PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users (user_id) ON DELETE CASCADE,
+    created_at timestamptz NOT NULL,
+    last_seen_at timestamptz NOT NULL,
+    ip_address TEXT NULL,
+    user_agent TEXT NULL
+);
+CREATE INDEX user_sessions_user_id_idx ON user_sessions (user_id);
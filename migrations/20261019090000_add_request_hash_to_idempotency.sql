@@ -0,0 +1,2 @@
+-- Keys saved before fingerprinting have no hash and are not checked
+ALTER TABLE idempotency ADD COLUMN request_hash BYTEA;
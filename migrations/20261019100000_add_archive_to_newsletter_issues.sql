@@ -0,0 +1,8 @@
+ALTER TABLE newsletter_issues
+    ADD COLUMN slug TEXT,
+    ADD COLUMN public BOOLEAN NOT NULL DEFAULT false;
+-- Issues published before the archive keep out of it, under a unique slug
+UPDATE newsletter_issues SET slug = newsletter_issue_id::text;
+ALTER TABLE newsletter_issues
+    ALTER COLUMN slug SET NOT NULL,
+    ADD CONSTRAINT newsletter_issues_slug_key UNIQUE (slug);
@@ -0,0 +1,3 @@
+-- `published_at` was always filled with `now()`, so every value parses back
+ALTER TABLE newsletter_issues
+    ALTER COLUMN published_at TYPE timestamptz USING published_at::timestamptz;
@@ -0,0 +1,2 @@
+-- The Markdown source of issues written in Markdown, to edit them later
+ALTER TABLE newsletter_issues ADD COLUMN markdown_content TEXT NULL;
@@ -0,0 +1,3 @@
+-- Deliveries removed when their subscriber's data is erased, so that
+-- recipients_count = delivered_count + failed_count + dropped_count + pending
+ALTER TABLE newsletter_issues ADD COLUMN dropped_count INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- The time step of the last accepted authentication code, which cannot be
+-- used again. `NULL` until a code is used to log in.
+ALTER TABLE users ADD COLUMN totp_last_used_step BIGINT NULL;
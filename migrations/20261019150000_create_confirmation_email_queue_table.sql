@@ -0,0 +1,7 @@
+-- The confirmation emails of imported subscribers, sent by the background
+-- worker instead of while the import request is being answered.
+CREATE TABLE confirmation_email_queue (
+    subscription_token TEXT NOT NULL
+        REFERENCES subscription_tokens (subscription_token) ON DELETE CASCADE,
+    PRIMARY KEY (subscription_token)
+);
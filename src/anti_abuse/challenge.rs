@@ -0,0 +1,159 @@
+use anyhow::Context;
+use secrecy::{ExposeSecret, Secret};
+
+/// Checks the response to a challenge (e.g. a CAPTCHA) solved by the client.
+#[async_trait::async_trait]
+pub trait ChallengeVerifier: Send + Sync {
+    async fn verify(
+        &self,
+        response: Option<&str>,
+        ip_address: Option<&str>,
+    ) -> Result<bool, anyhow::Error>;
+}
+
+/// Used when no challenge is configured: every request passes.
+pub struct NoChallenge;
+
+#[async_trait::async_trait]
+impl ChallengeVerifier for NoChallenge {
+    async fn verify(&self, _: Option<&str>, _: Option<&str>) -> Result<bool, anyhow::Error> {
+        Ok(true)
+    }
+}
+
+/// A verifier for the `siteverify` API shared by hCaptcha, reCAPTCHA
+/// and Cloudflare Turnstile.
+pub struct SiteVerifyChallenge {
+    http_client: reqwest::Client,
+    verify_url: String,
+    secret_key: Secret<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+}
+
+impl SiteVerifyChallenge {
+    pub fn new(
+        verify_url: String,
+        secret_key: Secret<String>,
+        timeout: std::time::Duration,
+    ) -> Self {
+        let http_client = reqwest::Client::builder().timeout(timeout).build().unwrap();
+        Self {
+            http_client,
+            verify_url,
+            secret_key,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ChallengeVerifier for SiteVerifyChallenge {
+    #[tracing::instrument(name = "Verify a challenge response", skip_all)]
+    async fn verify(
+        &self,
+        response: Option<&str>,
+        ip_address: Option<&str>,
+    ) -> Result<bool, anyhow::Error> {
+        let Some(response) = response.filter(|r| !r.is_empty()) else {
+            return Ok(false);
+        };
+        let mut form = vec![
+            ("secret", self.secret_key.expose_secret().as_str()),
+            ("response", response),
+        ];
+        if let Some(ip_address) = ip_address {
+            form.push(("remoteip", ip_address));
+        }
+        let outcome: SiteVerifyResponse = self
+            .http_client
+            .post(&self.verify_url)
+            .form(&form)
+            .send()
+            .await
+            .context("Failed to call the challenge verification API")?
+            .error_for_status()
+            .context("The challenge verification API returned an error")?
+            .json()
+            .await
+            .context("Failed to parse the challenge verification response")?;
+        Ok(outcome.success)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChallengeVerifier, SiteVerifyChallenge};
+    use claims::{assert_err, assert_ok_eq};
+    use secrecy::Secret;
+    use wiremock::matchers::{body_string_contains, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn verifier(base_url: String) -> SiteVerifyChallenge {
+        SiteVerifyChallenge::new(
+            base_url,
+            Secret::new("secret".into()),
+            std::time::Duration::from_millis(200),
+        )
+    }
+
+    #[tokio::test]
+    async fn a_missing_response_fails_without_calling_the_api() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = verifier(mock_server.uri()).verify(None, None).await;
+
+        // Assert
+        assert_ok_eq!(outcome, false);
+    }
+
+    #[tokio::test]
+    async fn the_api_outcome_is_returned() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("response=solved"))
+            .and(body_string_contains("remoteip=127.0.0.1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "success": true
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = verifier(mock_server.uri())
+            .verify(Some("solved"), Some("127.0.0.1"))
+            .await;
+
+        // Assert
+        assert_ok_eq!(outcome, true);
+    }
+
+    #[tokio::test]
+    async fn an_api_error_is_an_error() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = verifier(mock_server.uri())
+            .verify(Some("solved"), None)
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+}
@@ -0,0 +1,305 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::anti_abuse::RateLimiter;
+use crate::configuration::LoginThrottleSettings;
+
+pub enum LoginAttempt {
+    /// The credentials can be checked, after waiting for `delay`.
+    Allowed {
+        delay: Duration,
+        attempt: CountedAttempt,
+    },
+    LockedOut {
+        retry_after: Duration,
+    },
+}
+
+/// An attempt that is counted as failed until it is known to have succeeded,
+/// so that concurrent attempts cannot all get in before the first failure.
+pub struct CountedAttempt {
+    username: String,
+    ip_address: String,
+    /// The failures of the username and of the IP address, this attempt included.
+    failures: [u64; 2],
+}
+
+impl CountedAttempt {
+    fn keys(&self) -> [(LockoutScope, &str, u64); 2] {
+        [
+            (LockoutScope::Username, &self.username, self.failures[0]),
+            (LockoutScope::IpAddress, &self.ip_address, self.failures[1]),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum LockoutScope {
+    Username,
+    IpAddress,
+}
+
+impl LockoutScope {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LockoutScope::Username => "username",
+            LockoutScope::IpAddress => "ip_address",
+        }
+    }
+
+    fn failures_bucket(&self) -> &'static str {
+        match self {
+            LockoutScope::Username => "login_failures_username",
+            LockoutScope::IpAddress => "login_failures_ip",
+        }
+    }
+
+    fn lockout_bucket(&self) -> &'static str {
+        match self {
+            LockoutScope::Username => "login_lockout_username",
+            LockoutScope::IpAddress => "login_lockout_ip",
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct LockoutRecord {
+    pub scope: String,
+    pub locked_key: String,
+    pub ip_address: Option<String>,
+    pub failed_attempts: i32,
+    pub locked_at: DateTime<Utc>,
+    pub locked_until: DateTime<Utc>,
+}
+
+/// Slows down and eventually locks out repeated failed logins,
+/// both for a username and for an IP address.
+///
+/// Counters are keyed by the submitted username whether the user exists
+/// or not, so the throttling does not reveal which usernames are valid.
+pub struct LoginThrottle {
+    rate_limiter: RateLimiter,
+    settings: LoginThrottleSettings,
+}
+
+impl LoginThrottle {
+    pub fn new(rate_limiter: RateLimiter, settings: LoginThrottleSettings) -> Self {
+        Self {
+            rate_limiter,
+            settings,
+        }
+    }
+
+    /// Count an attempt as failed before its credentials are checked.
+    ///
+    /// Attempts beyond the limit are locked out even if the ones that
+    /// reached it are still being checked.
+    #[tracing::instrument(name = "Check login throttling", skip(self, username))]
+    pub async fn begin(
+        &self,
+        username: &str,
+        ip_address: Option<&str>,
+    ) -> Result<LoginAttempt, anyhow::Error> {
+        let ip_address = ip_address.unwrap_or("unknown");
+        let keys = [
+            (LockoutScope::Username, username),
+            (LockoutScope::IpAddress, ip_address),
+        ];
+        for (scope, key) in keys {
+            if let Some(seconds) = self
+                .rate_limiter
+                .blocked_for(scope.lockout_bucket(), key)
+                .await?
+            {
+                return Ok(LoginAttempt::LockedOut {
+                    retry_after: Duration::from_secs(seconds),
+                });
+            }
+        }
+        let mut failures = [0; 2];
+        for (failures, (scope, key)) in failures.iter_mut().zip(keys) {
+            *failures = self
+                .rate_limiter
+                .increment(
+                    scope.failures_bucket(),
+                    key,
+                    self.settings.failure_window_seconds,
+                )
+                .await?;
+        }
+        let max_failures = [
+            self.settings.max_failures_per_username,
+            self.settings.max_failures_per_ip,
+        ];
+        for ((scope, key), (failures, max_failures)) in
+            keys.into_iter().zip(failures.iter().zip(max_failures))
+        {
+            if *failures > max_failures {
+                // The attempt that reached the limit may not have blocked the key yet
+                let retry_after = match self
+                    .rate_limiter
+                    .blocked_for(scope.lockout_bucket(), key)
+                    .await?
+                {
+                    Some(seconds) => seconds,
+                    None => self
+                        .rate_limiter
+                        .blocked_for(scope.failures_bucket(), key)
+                        .await?
+                        .unwrap_or(self.settings.failure_window_seconds),
+                };
+                return Ok(LoginAttempt::LockedOut {
+                    retry_after: Duration::from_secs(retry_after),
+                });
+            }
+        }
+        Ok(LoginAttempt::Allowed {
+            // The delay is imposed by the previous failures
+            delay: self.delay(failures[0].max(failures[1]) - 1),
+            attempt: CountedAttempt {
+                username: username.to_owned(),
+                ip_address: ip_address.to_owned(),
+                failures,
+            },
+        })
+    }
+
+    // The delay doubles with every failed attempt, up to a maximum.
+    fn delay(&self, failures: u64) -> Duration {
+        if failures == 0 {
+            return Duration::ZERO;
+        }
+        let factor = 1u64.checked_shl((failures - 1) as u32).unwrap_or(u64::MAX);
+        let milliseconds = self
+            .settings
+            .base_delay_milliseconds
+            .saturating_mul(factor)
+            .min(self.settings.max_delay_milliseconds);
+        Duration::from_millis(milliseconds)
+    }
+
+    /// The failure was counted by [`LoginThrottle::begin`]: the attempt
+    /// that reached the limit locks the key out.
+    #[tracing::instrument(name = "Record a failed login", skip_all)]
+    pub async fn record_failure(
+        &self,
+        pool: &PgPool,
+        attempt: &CountedAttempt,
+    ) -> Result<(), anyhow::Error> {
+        let ip_address = Some(attempt.ip_address.as_str());
+        for (scope, key, failures) in attempt.keys() {
+            let max_failures = match scope {
+                LockoutScope::Username => self.settings.max_failures_per_username,
+                LockoutScope::IpAddress => self.settings.max_failures_per_ip,
+            };
+            if failures == max_failures {
+                self.rate_limiter
+                    .block(scope.lockout_bucket(), key, self.settings.lockout_seconds)
+                    .await?;
+                tracing::warn!(
+                    scope = scope.as_str(),
+                    failures,
+                    "Locked out after too many failed logins"
+                );
+                record_lockout(
+                    pool,
+                    scope,
+                    key,
+                    ip_address,
+                    failures,
+                    self.settings.lockout_seconds,
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// A successful login clears the failures of the username,
+    /// but only takes back the attempt from those of the IP address.
+    pub async fn record_success(&self, attempt: &CountedAttempt) -> Result<(), anyhow::Error> {
+        self.rate_limiter
+            .reset(LockoutScope::Username.failures_bucket(), &attempt.username)
+            .await?;
+        self.rate_limiter
+            .decrement(
+                LockoutScope::IpAddress.failures_bucket(),
+                &attempt.ip_address,
+            )
+            .await
+    }
+
+    /// Take back an attempt that did not fail, without clearing the
+    /// previous failures, e.g. a password still to be followed by a code.
+    pub async fn withdraw(&self, attempt: &CountedAttempt) -> Result<(), anyhow::Error> {
+        for (scope, key, _) in attempt.keys() {
+            self.rate_limiter
+                .decrement(scope.failures_bucket(), key)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[tracing::instrument(name = "Record a lockout", skip(pool, locked_key, ip_address))]
+async fn record_lockout(
+    pool: &PgPool,
+    scope: LockoutScope,
+    locked_key: &str,
+    ip_address: Option<&str>,
+    failed_attempts: u64,
+    lockout_seconds: u64,
+) -> Result<(), anyhow::Error> {
+    let locked_at = Utc::now();
+    let locked_until = locked_at + chrono::Duration::seconds(lockout_seconds as i64);
+    sqlx::query!(
+        r#"
+        INSERT INTO login_lockouts (
+            lockout_id,
+            scope,
+            locked_key,
+            ip_address,
+            failed_attempts,
+            locked_at,
+            locked_until
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        Uuid::new_v4(),
+        scope.as_str(),
+        locked_key,
+        ip_address,
+        failed_attempts as i32,
+        locked_at,
+        locked_until
+    )
+    .execute(pool)
+    .await
+    .context("Failed to record a lockout.")?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Get the latest lockouts", skip(pool))]
+pub async fn get_latest_lockouts(
+    pool: &PgPool,
+    limit: i64,
+) -> Result<Vec<LockoutRecord>, anyhow::Error> {
+    let lockouts = sqlx::query_as!(
+        LockoutRecord,
+        r#"
+        SELECT scope, locked_key, ip_address, failed_attempts, locked_at, locked_until
+        FROM login_lockouts
+        ORDER BY locked_at DESC
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the lockouts.")?;
+    Ok(lockouts)
+}
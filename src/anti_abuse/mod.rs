@@ -0,0 +1,11 @@
+mod challenge;
+mod login_throttle;
+mod rate_limit;
+mod subscription_guard;
+
+pub use challenge::{ChallengeVerifier, NoChallenge, SiteVerifyChallenge};
+pub use login_throttle::{
+    CountedAttempt, LockoutRecord, LoginAttempt, LoginThrottle, get_latest_lockouts,
+};
+pub use rate_limit::{RateLimit, RateLimiter};
+pub use subscription_guard::{Rejection, SubscriptionGuard};
@@ -0,0 +1,140 @@
+use anyhow::Context;
+use redis::AsyncCommands;
+use redis::aio::ConnectionManager;
+use secrecy::{ExposeSecret, Secret};
+
+#[derive(Clone, Copy, Debug, serde::Deserialize)]
+pub struct RateLimit {
+    pub max_requests: u64,
+    pub window_seconds: u64,
+}
+
+/// Fixed-window counters stored in Redis, shared by every instance
+/// of the application.
+#[derive(Clone)]
+pub struct RateLimiter {
+    connection: ConnectionManager,
+    key_prefix: String,
+}
+
+impl RateLimiter {
+    pub async fn new(
+        redis_uri: &Secret<String>,
+        key_prefix: String,
+    ) -> Result<Self, anyhow::Error> {
+        let client = redis::Client::open(redis_uri.expose_secret().as_str())?;
+        let connection = client
+            .get_connection_manager()
+            .await
+            .context("Failed to connect to Redis")?;
+        Ok(Self {
+            connection,
+            key_prefix,
+        })
+    }
+
+    fn key(&self, bucket: &str, key: &str) -> String {
+        format!("{}:{bucket}:{key}", self.key_prefix)
+    }
+
+    /// Count a hit against `key` in the given `bucket`.
+    ///
+    /// Returns `false` if the limit for the current window has been exceeded.
+    #[tracing::instrument(name = "Check a rate limit", skip(self, key))]
+    pub async fn hit(
+        &self,
+        bucket: &str,
+        key: &str,
+        limit: RateLimit,
+    ) -> Result<bool, anyhow::Error> {
+        let count = self.increment(bucket, key, limit.window_seconds).await?;
+        Ok(count <= limit.max_requests)
+    }
+
+    /// Increment the counter and return its new value.
+    /// The window starts with the first increment.
+    ///
+    /// The counter is created along with its expiration, in a single
+    /// transaction, so that it cannot be left behind without one.
+    pub async fn increment(
+        &self,
+        bucket: &str,
+        key: &str,
+        window_seconds: u64,
+    ) -> Result<u64, anyhow::Error> {
+        let key = self.key(bucket, key);
+        let mut connection = self.connection.clone();
+        let (count,): (u64,) = redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(&key)
+            .arg(0)
+            .arg("EX")
+            .arg(window_seconds)
+            .arg("NX")
+            .ignore()
+            .incr(&key, 1)
+            .query_async(&mut connection)
+            .await
+            .context("Failed to increment a rate limit counter")?;
+        Ok(count)
+    }
+
+    /// Decrement the counter, removing it once it is back to zero.
+    pub async fn decrement(&self, bucket: &str, key: &str) -> Result<(), anyhow::Error> {
+        let key = self.key(bucket, key);
+        let mut connection = self.connection.clone();
+        let count: i64 = connection
+            .decr(&key, 1)
+            .await
+            .context("Failed to decrement a rate limit counter")?;
+        // A counter that expired in the meantime was recreated without
+        // an expiration
+        if count <= 0 {
+            let _: () = connection
+                .del(&key)
+                .await
+                .context("Failed to reset a rate limit counter")?;
+        }
+        Ok(())
+    }
+
+    pub async fn count(&self, bucket: &str, key: &str) -> Result<u64, anyhow::Error> {
+        let mut connection = self.connection.clone();
+        let count: Option<u64> = connection
+            .get(self.key(bucket, key))
+            .await
+            .context("Failed to read a rate limit counter")?;
+        Ok(count.unwrap_or(0))
+    }
+
+    pub async fn reset(&self, bucket: &str, key: &str) -> Result<(), anyhow::Error> {
+        let mut connection = self.connection.clone();
+        let _: () = connection
+            .del(self.key(bucket, key))
+            .await
+            .context("Failed to reset a rate limit counter")?;
+        Ok(())
+    }
+
+    /// Block `key` in the given `bucket` for `seconds`.
+    pub async fn block(&self, bucket: &str, key: &str, seconds: u64) -> Result<(), anyhow::Error> {
+        let mut connection = self.connection.clone();
+        let _: () = connection
+            .set_ex(self.key(bucket, key), 1, seconds)
+            .await
+            .context("Failed to store a block")?;
+        Ok(())
+    }
+
+    /// How many seconds are left before `key` is unblocked, if it is blocked.
+    pub async fn blocked_for(&self, bucket: &str, key: &str) -> Result<Option<u64>, anyhow::Error> {
+        let mut connection = self.connection.clone();
+        let ttl: i64 = connection
+            .ttl(self.key(bucket, key))
+            .await
+            .context("Failed to read a block")?;
+        // Negative values mean that the key does not exist or does not expire
+        Ok((ttl >= 0).then_some(ttl as u64))
+    }
+}
@@ -0,0 +1,87 @@
+use crate::anti_abuse::{ChallengeVerifier, RateLimit, RateLimiter};
+use crate::configuration::AntiAbuseSettings;
+use crate::domain::SubscriberEmail;
+
+#[derive(Debug)]
+pub enum Rejection {
+    Honeypot,
+    TooManyRequestsFromIp,
+    FailedChallenge,
+    TooManyRequestsForEmail,
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            Rejection::Honeypot => "the honeypot field was filled in",
+            Rejection::TooManyRequestsFromIp => "too many requests from the IP address",
+            Rejection::FailedChallenge => "the challenge was not solved",
+            Rejection::TooManyRequestsForEmail => "too many requests for the email address",
+        };
+        f.write_str(reason)
+    }
+}
+
+/// Screens requests to the public subscription endpoint, which would
+/// otherwise let anybody send confirmation emails to any address.
+pub struct SubscriptionGuard {
+    rate_limiter: RateLimiter,
+    challenge_verifier: Box<dyn ChallengeVerifier>,
+    per_ip: RateLimit,
+    per_email: RateLimit,
+}
+
+impl SubscriptionGuard {
+    pub fn new(rate_limiter: RateLimiter, settings: &AntiAbuseSettings) -> Self {
+        Self {
+            rate_limiter,
+            challenge_verifier: settings.challenge_verifier(),
+            per_ip: settings.subscriptions_per_ip,
+            per_email: settings.subscriptions_per_email,
+        }
+    }
+
+    /// The checks that do not depend on the submitted subscriber details.
+    /// They run from the cheapest to the most expensive one.
+    pub async fn check_request(
+        &self,
+        ip_address: Option<&str>,
+        honeypot: &str,
+        challenge_response: Option<&str>,
+    ) -> Result<Option<Rejection>, anyhow::Error> {
+        if !honeypot.is_empty() {
+            return Ok(Some(Rejection::Honeypot));
+        }
+        let ip_key = ip_address.unwrap_or("unknown");
+        if !self
+            .rate_limiter
+            .hit("subscriptions_per_ip", ip_key, self.per_ip)
+            .await?
+        {
+            return Ok(Some(Rejection::TooManyRequestsFromIp));
+        }
+        if !self
+            .challenge_verifier
+            .verify(challenge_response, ip_address)
+            .await?
+        {
+            return Ok(Some(Rejection::FailedChallenge));
+        }
+        Ok(None)
+    }
+
+    pub async fn check_email(
+        &self,
+        email: &SubscriberEmail,
+    ) -> Result<Option<Rejection>, anyhow::Error> {
+        let email_key = email.as_ref().to_lowercase();
+        if !self
+            .rate_limiter
+            .hit("subscriptions_per_email", &email_key, self.per_email)
+            .await?
+        {
+            return Ok(Some(Rejection::TooManyRequestsForEmail));
+        }
+        Ok(None)
+    }
+}
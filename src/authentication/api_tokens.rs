@@ -0,0 +1,265 @@
+use actix_web::HttpMessage;
+use actix_web::HttpResponse;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use actix_web::middleware::Next;
+use actix_web::web;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use super::{generate_token, hash_token};
+use crate::domain::UserRole;
+use crate::utils::e500;
+
+/// Prefix of the API tokens, to make them easy to spot in scripts and logs.
+const API_TOKEN_PREFIX: &str = "z2p_";
+
+/// What an API token is allowed to do, on top of the role of its user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiScope {
+    /// Check newsletter issues and their delivery status.
+    IssuesRead,
+    /// Publish newsletter issues.
+    IssuesWrite,
+    /// List the subscribers.
+    SubscribersRead,
+}
+
+impl ApiScope {
+    pub const ALL: [ApiScope; 3] = [
+        ApiScope::IssuesRead,
+        ApiScope::IssuesWrite,
+        ApiScope::SubscribersRead,
+    ];
+
+    pub fn parse(s: &str) -> Result<ApiScope, String> {
+        Self::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s.trim())
+            .ok_or_else(|| format!("{s} is not a valid API token scope."))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::IssuesRead => "issues:read",
+            ApiScope::IssuesWrite => "issues:write",
+            ApiScope::SubscribersRead => "subscribers:read",
+        }
+    }
+
+    /// The role needed to use the scope, the same as in the admin area.
+    pub fn required_role(&self) -> UserRole {
+        match self {
+            ApiScope::IssuesRead | ApiScope::SubscribersRead => UserRole::Viewer,
+            ApiScope::IssuesWrite => UserRole::Editor,
+        }
+    }
+}
+
+impl std::fmt::Display for ApiScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct ApiTokenRecord {
+    pub api_token_id: Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// The API token of the current request, set by `reject_invalid_api_tokens`.
+#[derive(Clone, Debug)]
+pub struct ApiToken {
+    pub user_id: Uuid,
+    pub role: UserRole,
+    pub scopes: Vec<ApiScope>,
+}
+
+impl ApiToken {
+    /// Both the token and the current role of its user must allow the scope.
+    pub fn allows(&self, scope: ApiScope) -> bool {
+        self.scopes.contains(&scope) && self.role >= scope.required_role()
+    }
+}
+
+/// Store a new API token for the user and return it.
+/// Only its hash is kept: the token cannot be shown again.
+#[tracing::instrument(name = "Create an API token", skip(pool))]
+pub async fn create_api_token(
+    user_id: Uuid,
+    name: &str,
+    scopes: &[ApiScope],
+    pool: &PgPool,
+) -> Result<String, anyhow::Error> {
+    let api_token = format!("{API_TOKEN_PREFIX}{}", generate_token());
+    let scopes: Vec<String> = scopes.iter().map(|s| s.as_str().to_owned()).collect();
+    sqlx::query!(
+        r#"
+        INSERT INTO api_tokens (api_token_id, user_id, name, token_hash, scopes, created_at)
+        VALUES ($1, $2, $3, $4, $5, now())
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        name,
+        hash_token(&api_token),
+        &scopes
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the API token.")?;
+    Ok(api_token)
+}
+
+#[tracing::instrument(name = "Get the API tokens of a user", skip(pool))]
+pub async fn get_user_api_tokens(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Vec<ApiTokenRecord>, anyhow::Error> {
+    sqlx::query_as!(
+        ApiTokenRecord,
+        r#"
+        SELECT api_token_id, name, scopes, created_at, last_used_at
+        FROM api_tokens
+        WHERE user_id = $1 AND revoked_at IS NULL
+        ORDER BY created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the API tokens of the user.")
+}
+
+/// Returns `false` if the user has no such token.
+#[tracing::instrument(name = "Revoke an API token", skip(executor))]
+pub async fn revoke_api_token(
+    user_id: Uuid,
+    api_token_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE api_tokens
+        SET revoked_at = now()
+        WHERE user_id = $1 AND api_token_id = $2 AND revoked_at IS NULL
+        "#,
+        user_id,
+        api_token_id
+    )
+    .execute(executor)
+    .await
+    .context("Failed to revoke the API token.")?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// Authenticate API requests with an `Authorization: Bearer <token>` header.
+/// Unlike the admin area, there is no session and failures are JSON.
+pub async fn reject_invalid_api_tokens(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(api_token) = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(str::trim)
+    else {
+        return Err(unauthorized("Missing API token."));
+    };
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .expect("The database pool is not registered.");
+    let Some(api_token) = authenticate_api_token(api_token, pool)
+        .await
+        .map_err(e500)?
+    else {
+        return Err(unauthorized("Invalid API token."));
+    };
+    req.extensions_mut().insert(api_token);
+    next.call(req).await
+}
+
+fn unauthorized(message: &str) -> actix_web::Error {
+    let response = HttpResponse::Unauthorized()
+        .insert_header((WWW_AUTHENTICATE, "Bearer"))
+        .json(serde_json::json!({ "error": message }));
+    InternalError::from_response(anyhow::anyhow!("{message}"), response).into()
+}
+
+/// Revoked tokens, and the tokens of disabled or deleted users, are rejected.
+#[tracing::instrument(name = "Authenticate an API token", skip_all)]
+async fn authenticate_api_token(
+    api_token: &str,
+    pool: &PgPool,
+) -> Result<Option<ApiToken>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        UPDATE api_tokens t
+        SET last_used_at = now()
+        FROM users u
+        WHERE
+            t.token_hash = $1 AND
+            t.revoked_at IS NULL AND
+            u.user_id = t.user_id AND
+            u.disabled_at IS NULL
+        RETURNING t.user_id, t.scopes, u.role
+        "#,
+        hash_token(api_token)
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to validate the API token.")?;
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let role = UserRole::parse(&row.role).map_err(anyhow::Error::msg)?;
+    // Unknown scopes are ignored, e.g. after a scope has been removed.
+    let scopes = row
+        .scopes
+        .iter()
+        .filter_map(|s| ApiScope::parse(s).ok())
+        .collect();
+    Ok(Some(ApiToken {
+        user_id: row.user_id,
+        role,
+        scopes,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok_eq};
+    use uuid::Uuid;
+
+    use super::{ApiScope, ApiToken};
+    use crate::domain::UserRole;
+
+    #[test]
+    fn scopes_are_parsed_from_their_names() {
+        for scope in ApiScope::ALL {
+            assert_ok_eq!(ApiScope::parse(scope.as_str()), scope);
+        }
+        assert_err!(ApiScope::parse("issues:delete"));
+    }
+
+    #[test]
+    fn a_token_cannot_do_more_than_its_user() {
+        let api_token = ApiToken {
+            user_id: Uuid::new_v4(),
+            role: UserRole::Viewer,
+            scopes: vec![ApiScope::IssuesRead, ApiScope::IssuesWrite],
+        };
+        assert!(api_token.allows(ApiScope::IssuesRead));
+        assert!(!api_token.allows(ApiScope::IssuesWrite));
+        assert!(!api_token.allows(ApiScope::SubscribersRead));
+    }
+}
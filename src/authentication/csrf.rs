@@ -0,0 +1,101 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::REFERER;
+use actix_web::middleware::Next;
+use actix_web::{FromRequest, HttpMessage, web};
+use actix_web_flash_messages::FlashMessage;
+use subtle::ConstantTimeEq;
+
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+const CSRF_HEADER: &str = "X-CSRF-Token";
+/// The largest form, the subscriber import, is limited to 10MB.
+const MAX_FORM_BYTES: usize = 10 * 1024 * 1024;
+
+/// The token of the current session, to be added to every rendered form
+/// as a hidden `csrf_token` field.
+#[derive(Clone, Debug)]
+pub struct CsrfToken(String);
+
+impl AsRef<str> for CsrfToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct CsrfFormData {
+    csrf_token: Option<String>,
+}
+
+/// Reject the unsafe requests that do not carry the token of the session,
+/// either in the `csrf_token` form field or in the `X-CSRF-Token` header.
+/// Must be registered inside `reject_anonymous_users`.
+pub async fn verify_csrf_token(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let session = {
+        let (http_request, payload) = req.parts_mut();
+        TypedSession::from_request(http_request, payload).await
+    }?;
+    let expected_token = session.get_or_insert_csrf_token().map_err(e500)?;
+
+    if !req.method().is_safe() {
+        let header_token = req
+            .headers()
+            .get(CSRF_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .map(String::from);
+        let submitted_token = match header_token {
+            Some(token) => Some(token),
+            None => read_form_token(&mut req).await?,
+        };
+        let is_valid = submitted_token
+            .is_some_and(|token| bool::from(token.as_bytes().ct_eq(expected_token.as_bytes())));
+        if !is_valid {
+            tracing::warn!("Rejected a request with a missing or invalid CSRF token");
+            FlashMessage::error(
+                "Your request could not be verified. Please reload the page and try again.",
+            )
+            .send();
+            let response = see_other(&redirect_target(&req));
+            return Ok(req.into_response(response).map_into_right_body());
+        }
+    }
+
+    req.extensions_mut().insert(CsrfToken(expected_token));
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+// Buffer the body to look for the token, then hand it back to the handler.
+async fn read_form_token(req: &mut ServiceRequest) -> Result<Option<String>, actix_web::Error> {
+    let body = req
+        .extract::<web::Payload>()
+        .await?
+        .to_bytes_limited(MAX_FORM_BYTES)
+        .await
+        .map_err(|_| actix_web::error::ErrorPayloadTooLarge("The form is too large."))??;
+    let token = serde_urlencoded::from_bytes::<CsrfFormData>(&body)
+        .ok()
+        .and_then(|form| form.csrf_token);
+    req.set_payload(Payload::from(body));
+    Ok(token)
+}
+
+// Back to the page the form was submitted from, if it is one of ours.
+fn redirect_target(req: &ServiceRequest) -> String {
+    req.headers()
+        .get(REFERER)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|referer| reqwest::Url::parse(referer).ok())
+        .filter(|url| url.path().starts_with("/admin/"))
+        .map(|url| match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_owned(),
+        })
+        .unwrap_or_else(|| "/admin/dashboard".to_owned())
+}
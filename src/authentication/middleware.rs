@@ -1,12 +1,21 @@
 use actix_web::FromRequest;
 use actix_web::HttpMessage;
+use actix_web::HttpResponse;
 use actix_web::body::MessageBody;
 use actix_web::dev::{ServiceRequest, ServiceResponse};
 use actix_web::error::InternalError;
 use actix_web::middleware::Next;
+use actix_web::web;
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::PgPool;
 use std::ops::Deref;
 use uuid::Uuid;
 
+use super::sessions::{revoke_session, touch_session};
+use crate::configuration::SessionSettings;
+use crate::domain::UserRole;
 use crate::session_state::TypedSession;
 use crate::utils::{e500, see_other};
 
@@ -25,24 +34,135 @@ impl Deref for UserId {
     }
 }
 
+/// The id of the current session in `user_sessions`.
+#[derive(Copy, Clone, Debug)]
+pub struct SessionId(Uuid);
+
+impl Deref for SessionId {
+    type Target = Uuid;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 pub async fn reject_anonymous_users(
     mut req: ServiceRequest,
-    next: Next<impl MessageBody>,
+    next: Next<impl MessageBody + 'static>,
 ) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
     let session = {
         let (http_request, payload) = req.parts_mut();
         TypedSession::from_request(http_request, payload).await
     }?;
 
-    match session.get_user_id().map_err(e500)? {
-        Some(user_id) => {
-            req.extensions_mut().insert(UserId(user_id));
-            next.call(req).await
+    let Some(user_id) = session.get_user_id().map_err(e500)? else {
+        let response = see_other("/login");
+        let e = anyhow::anyhow!("The user has not logged in");
+        return Err(InternalError::from_response(e, response).into());
+    };
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .expect("The database pool is not registered.");
+    let settings = req
+        .app_data::<web::Data<SessionSettings>>()
+        .expect("The session settings are not registered.");
+    let now = Utc::now();
+    let authenticated_at = session.get_authenticated_at().map_err(e500)?;
+    let last_activity = session.get_last_activity().map_err(e500)?;
+    let has_expired = match (authenticated_at, last_activity) {
+        (Some(authenticated_at), Some(last_activity)) => {
+            now - authenticated_at > settings.absolute_lifetime()
+                || now - last_activity > settings.idle_timeout()
         }
-        None => {
-            let response = see_other("/login");
-            let e = anyhow::anyhow!("The user has not logged in");
-            Err(InternalError::from_response(e, response).into())
+        _ => true,
+    };
+    if has_expired {
+        if let Some(session_id) = session.get_session_id().map_err(e500)? {
+            revoke_session(user_id, session_id, pool.get_ref())
+                .await
+                .map_err(e500)?;
         }
+        session.log_out();
+        // Not an error response: the flash message would be lost otherwise.
+        FlashMessage::info("Your session has expired, please log in again.").send();
+        return Ok(req.into_response(see_other("/login")).map_into_right_body());
+    }
+    session.insert_last_activity(now).map_err(e500)?;
+    // Disabling or deleting a user ends their sessions as well.
+    let Some(role) = get_active_user_role(user_id, pool).await.map_err(e500)? else {
+        session.log_out();
+        let response = see_other("/login");
+        let e = anyhow::anyhow!("The user has been disabled or deleted");
+        return Err(InternalError::from_response(e, response).into());
+    };
+    let session_id = session.get_session_id().map_err(e500)?;
+    let is_active = match session_id {
+        Some(session_id) => touch_session(session_id, user_id, pool)
+            .await
+            .map_err(e500)?,
+        None => false,
+    };
+    let Some(session_id) = session_id.filter(|_| is_active) else {
+        session.log_out();
+        let response = see_other("/login");
+        let e = anyhow::anyhow!("The session has been revoked");
+        return Err(InternalError::from_response(e, response).into());
+    };
+    req.extensions_mut().insert(UserId(user_id));
+    req.extensions_mut().insert(SessionId(session_id));
+    req.extensions_mut().insert(role);
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+// Must be registered inside `reject_anonymous_users`, which sets the role.
+async fn require_role(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+    required_role: UserRole,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let role = req
+        .extensions()
+        .get::<UserRole>()
+        .copied()
+        .expect("The user role has not been set.");
+    if role < required_role {
+        let e = anyhow::anyhow!("The {role} role is not allowed to do this");
+        return Err(InternalError::from_response(e, HttpResponse::Forbidden().finish()).into());
     }
+    next.call(req).await
+}
+
+pub async fn require_editor(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    require_role(req, next, UserRole::Editor).await
+}
+
+pub async fn require_owner(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    require_role(req, next, UserRole::Owner).await
+}
+
+#[tracing::instrument(name = "Get active user role", skip(pool))]
+async fn get_active_user_role(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Option<UserRole>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT role
+        FROM users
+        WHERE user_id = $1 AND disabled_at IS NULL
+        "#,
+        user_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve the user role.")?;
+    row.map(|r| UserRole::parse(&r.role).map_err(anyhow::Error::msg))
+        .transpose()
 }
@@ -1,5 +1,28 @@
+mod api_tokens;
+mod csrf;
 mod middleware;
 mod password;
+mod password_policy;
+mod sessions;
+mod token;
+mod two_factor;
 
-pub use middleware::{UserId, reject_anonymous_users};
-pub use password::{AuthError, Credentials, change_password, validate_credentials};
+pub use api_tokens::{
+    ApiScope, ApiToken, ApiTokenRecord, create_api_token, get_user_api_tokens,
+    reject_invalid_api_tokens, revoke_api_token,
+};
+pub use csrf::{CsrfToken, verify_csrf_token};
+pub use middleware::{SessionId, UserId, reject_anonymous_users, require_editor, require_owner};
+pub use password::{
+    AuthError, CreateUserError, Credentials, PasswordHashing, change_password, create_user,
+    validate_credentials,
+};
+pub use password_policy::{BreachedPasswords, PasswordPolicy, PasswordPolicyError};
+pub use sessions::{
+    SessionRecord, get_user_sessions, register_session, revoke_session, revoke_sessions,
+};
+pub use token::{generate_token, hash_token};
+pub use two_factor::{
+    disable_two_factor, enable_two_factor, generate_totp_secret, get_totp_secret,
+    provisioning_qr_code, use_recovery_code, use_totp_step, verify_totp_code,
+};
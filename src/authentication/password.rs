@@ -2,10 +2,50 @@ use anyhow::Context;
 use argon2::password_hash::SaltString;
 use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
 use secrecy::{ExposeSecret, Secret};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgExecutor, PgPool};
 
+use super::generate_token;
+use crate::domain::{SubscriberEmail, UserRole};
 use crate::telemetry::spawn_blocking_with_tracing;
 
+/// The Argon2 parameters of new password hashes, along with a hash
+/// computed with them to verify unknown usernames against:
+/// logging in takes as long whether the user exists or not.
+#[derive(Clone)]
+pub struct PasswordHashing {
+    params: Params,
+    dummy_hash: Secret<String>,
+}
+
+impl PasswordHashing {
+    pub fn new(params: Params) -> Result<Self, anyhow::Error> {
+        let dummy_hash = compute_password_hash(Secret::new(generate_token()), params.clone())
+            .context("Failed to compute the dummy password hash")?;
+        Ok(Self { params, dummy_hash })
+    }
+
+    /// Whether the hash was computed with weaker parameters than the
+    /// current ones, and should be replaced.
+    fn needs_rehash(&self, password_hash: &Secret<String>) -> bool {
+        let Ok(password_hash) = PasswordHash::new(password_hash.expose_secret()) else {
+            return false;
+        };
+        if password_hash.algorithm != Algorithm::Argon2id.ident()
+            || password_hash.version < Some(Version::V0x13.into())
+        {
+            return true;
+        }
+        match Params::try_from(&password_hash) {
+            Ok(params) => {
+                params.m_cost() < self.params.m_cost()
+                    || params.t_cost() < self.params.t_cost()
+                    || params.p_cost() < self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum AuthError {
     #[error("Invalid credentials.")]
@@ -19,18 +59,14 @@ pub struct Credentials {
     pub password: Secret<String>,
 }
 
-#[tracing::instrument(name = "Validate credentials", skip(credentials, pool))]
+#[tracing::instrument(name = "Validate credentials", skip(credentials, hashing, pool))]
 pub async fn validate_credentials(
     credentials: Credentials,
+    hashing: &PasswordHashing,
     pool: &PgPool,
 ) -> Result<uuid::Uuid, AuthError> {
     let mut user_id = None;
-    let mut expected_password_hash = Secret::new(
-        "$argon2id$v=19$m=15000,t=2,p=1$\
-        gZiV/M1gPc22ElAH/Jh1Hw$\
-        CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno"
-            .to_string(),
-    );
+    let mut expected_password_hash = hashing.dummy_hash.clone();
 
     if let Some((stored_user_id, stored_password_hash)) =
         get_stored_credentials(&credentials.username, pool).await?
@@ -39,6 +75,8 @@ pub async fn validate_credentials(
         expected_password_hash = stored_password_hash;
     }
 
+    let stored_password_hash = expected_password_hash.clone();
+    let password = credentials.password.clone();
     spawn_blocking_with_tracing(move || {
         verify_password_hash(expected_password_hash, credentials.password)
     })
@@ -50,9 +88,51 @@ pub async fn validate_credentials(
     // with the provided password,
     // we never authenticate a non-existing user.
     // You can easily add a unit test for that precise scenario.
-    user_id
+    let user_id = user_id
         .ok_or_else(|| anyhow::anyhow!("Unknown username."))
-        .map_err(AuthError::InvalidCredentials)
+        .map_err(AuthError::InvalidCredentials)?;
+
+    // The password is only available in clear text here.
+    if hashing.needs_rehash(&stored_password_hash)
+        && let Err(e) =
+            upgrade_password_hash(user_id, password, stored_password_hash, hashing, pool).await
+    {
+        tracing::warn!(error.cause_chain = ?e, "Failed to upgrade a password hash");
+    }
+    Ok(user_id)
+}
+
+#[tracing::instrument(
+    name = "Upgrade password hash",
+    skip(password, stored_password_hash, hashing, pool)
+)]
+async fn upgrade_password_hash(
+    user_id: uuid::Uuid,
+    password: Secret<String>,
+    stored_password_hash: Secret<String>,
+    hashing: &PasswordHashing,
+    pool: &PgPool,
+) -> Result<(), anyhow::Error> {
+    let params = hashing.params.clone();
+    let password_hash =
+        spawn_blocking_with_tracing(move || compute_password_hash(password, params))
+            .await?
+            .context("Failed to hash password")?;
+    // Unless the password has been changed in the meantime
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET password_hash = $1
+        WHERE user_id = $2 AND password_hash = $3
+        "#,
+        password_hash.expose_secret(),
+        user_id,
+        stored_password_hash.expose_secret()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the upgraded password hash.")?;
+    Ok(())
 }
 
 // We extracted the db-querying logic in its own function with its own span.
@@ -65,7 +145,7 @@ async fn get_stored_credentials(
         r#"
         SELECT user_id, password_hash
         FROM users
-        WHERE username = $1
+        WHERE username = $1 AND disabled_at IS NULL
         "#,
         username,
     )
@@ -95,15 +175,18 @@ fn verify_password_hash(
         .map_err(AuthError::InvalidCredentials)
 }
 
-#[tracing::instrument(name = "Change password", skip(password, pool))]
+#[tracing::instrument(name = "Change password", skip(password, hashing, executor))]
 pub async fn change_password(
     user_id: uuid::Uuid,
     password: Secret<String>,
-    pool: &PgPool,
+    hashing: &PasswordHashing,
+    executor: impl PgExecutor<'_>,
 ) -> Result<(), anyhow::Error> {
-    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(password))
-        .await?
-        .context("Failed to hash password")?;
+    let params = hashing.params.clone();
+    let password_hash =
+        spawn_blocking_with_tracing(move || compute_password_hash(password, params))
+            .await?
+            .context("Failed to hash password")?;
     sqlx::query!(
         r#"
         UPDATE users
@@ -113,20 +196,138 @@ pub async fn change_password(
         password_hash.expose_secret(),
         user_id
     )
-    .execute(pool)
+    .execute(executor)
     .await
     .context("Failed to change user's password in the database.")?;
     Ok(())
 }
 
-fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>, anyhow::Error> {
-    let salt = SaltString::generate(&mut rand::thread_rng());
-    let password_hash = Argon2::new(
-        Algorithm::Argon2id,
-        Version::V0x13,
-        Params::new(15000, 2, 1, None).unwrap(),
+#[derive(thiserror::Error, Debug)]
+pub enum CreateUserError {
+    #[error("The username {0} is already taken.")]
+    UsernameTaken(String),
+    #[error("The email {0} is already used by another user.")]
+    EmailTaken(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+#[tracing::instrument(name = "Create user", skip(password, email, hashing, connection))]
+pub async fn create_user(
+    username: &str,
+    email: Option<&SubscriberEmail>,
+    password: Secret<String>,
+    role: UserRole,
+    hashing: &PasswordHashing,
+    connection: &mut PgConnection,
+) -> Result<uuid::Uuid, CreateUserError> {
+    let params = hashing.params.clone();
+    let password_hash =
+        spawn_blocking_with_tracing(move || compute_password_hash(password, params))
+            .await
+            .context("Failed to spawn blocking task.")?
+            .context("Failed to hash password")?;
+    let user_id = uuid::Uuid::new_v4();
+    let email = email.map(|e| e.as_ref());
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO users (user_id, username, email, password_hash, role)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id,
+        username,
+        email,
+        password_hash.expose_secret(),
+        role.as_str()
     )
-    .hash_password(password.expose_secret().as_bytes(), &salt)?
-    .to_string();
+    .execute(&mut *connection)
+    .await
+    .context("Failed to insert the new user in the database.")?;
+    if result.rows_affected() == 0 {
+        // Either the username or the email is taken
+        let username_taken = sqlx::query!(
+            r#"SELECT EXISTS(SELECT 1 FROM users WHERE username = $1) AS "taken!""#,
+            username
+        )
+        .fetch_one(&mut *connection)
+        .await
+        .context("Failed to check if the username is taken.")?
+        .taken;
+        return Err(match (username_taken, email) {
+            (false, Some(email)) => CreateUserError::EmailTaken(email.to_owned()),
+            _ => CreateUserError::UsernameTaken(username.to_owned()),
+        });
+    }
+    Ok(user_id)
+}
+
+fn compute_password_hash(
+    password: Secret<String>,
+    params: Params,
+) -> Result<Secret<String>, anyhow::Error> {
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    let password_hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password(password.expose_secret().as_bytes(), &salt)?
+        .to_string();
     Ok(Secret::new(password_hash))
 }
+
+#[cfg(test)]
+mod tests {
+    use argon2::Params;
+    use claims::{assert_err, assert_ok};
+    use secrecy::Secret;
+
+    use super::{PasswordHashing, compute_password_hash, verify_password_hash};
+
+    fn hashing(m_cost: u32, t_cost: u32, p_cost: u32) -> PasswordHashing {
+        PasswordHashing::new(Params::new(m_cost, t_cost, p_cost, None).unwrap()).unwrap()
+    }
+
+    fn hash(m_cost: u32, t_cost: u32, p_cost: u32) -> Secret<String> {
+        let params = Params::new(m_cost, t_cost, p_cost, None).unwrap();
+        compute_password_hash(Secret::new("password".into()), params).unwrap()
+    }
+
+    #[test]
+    fn hashes_with_the_current_parameters_are_kept() {
+        assert!(!hashing(8192, 2, 1).needs_rehash(&hash(8192, 2, 1)));
+    }
+
+    #[test]
+    fn hashes_with_stronger_parameters_are_kept() {
+        assert!(!hashing(8192, 2, 1).needs_rehash(&hash(16384, 3, 1)));
+    }
+
+    #[test]
+    fn hashes_with_any_weaker_parameter_are_replaced() {
+        let hashing = hashing(8192, 2, 2);
+        assert!(hashing.needs_rehash(&hash(4096, 2, 2)));
+        assert!(hashing.needs_rehash(&hash(8192, 1, 2)));
+        assert!(hashing.needs_rehash(&hash(8192, 2, 1)));
+    }
+
+    #[test]
+    fn argon2i_hashes_are_replaced() {
+        let argon2i_hash = Secret::new(
+            "$argon2i$v=19$m=8192,t=2,p=1$c29tZXNhbHQ$zxlKRu/T1rGSoXhoS08sdd86PJ9LJpfKLMF5fp4S+j0"
+                .to_string(),
+        );
+        assert!(hashing(8192, 2, 1).needs_rehash(&argon2i_hash));
+    }
+
+    #[test]
+    fn the_dummy_hash_uses_the_current_parameters() {
+        let hashing = hashing(8192, 2, 1);
+        assert!(!hashing.needs_rehash(&hashing.dummy_hash));
+        assert_err!(verify_password_hash(
+            hashing.dummy_hash.clone(),
+            Secret::new("password".into())
+        ));
+        assert_ok!(verify_password_hash(
+            hash(8192, 2, 1),
+            Secret::new("password".into())
+        ));
+    }
+}
@@ -0,0 +1,277 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use secrecy::{ExposeSecret, Secret};
+use sha1::{Digest, Sha1};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Length of the SHA-1 prefix used to look up a range of breached passwords.
+const RANGE_PREFIX_LENGTH: usize = 5;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PasswordPolicyError {
+    #[error(
+        "The password's length is invalid - It has to be in the range of {min} to {max} characters"
+    )]
+    InvalidLength { min: usize, max: usize },
+    #[error("The password cannot contain the username.")]
+    ContainsUsername,
+    #[error("The password is too easy to guess - try adding more words or characters.")]
+    TooWeak,
+    #[error("The password appears in a list of breached passwords - please choose another one.")]
+    Breached,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+/// The rules new passwords have to follow, whoever sets them.
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    /// The minimum zxcvbn score, from 0 (too guessable) to 4 (very unguessable).
+    pub min_strength: u8,
+    pub breached_passwords: Option<BreachedPasswords>,
+}
+
+impl PasswordPolicy {
+    pub fn check(
+        &self,
+        password: &Secret<String>,
+        username: &str,
+    ) -> Result<(), PasswordPolicyError> {
+        let password = password.expose_secret();
+        // What users see as characters, e.g. an emoji with a skin tone counts as one.
+        let length = password.graphemes(true).count();
+        if !(self.min_length..=self.max_length).contains(&length) {
+            return Err(PasswordPolicyError::InvalidLength {
+                min: self.min_length,
+                max: self.max_length,
+            });
+        }
+        let username = username.trim().to_lowercase();
+        if !username.is_empty() && password.to_lowercase().contains(&username) {
+            return Err(PasswordPolicyError::ContainsUsername);
+        }
+        let strength = zxcvbn::zxcvbn(password, &[&username]).score();
+        if u8::from(strength) < self.min_strength {
+            return Err(PasswordPolicyError::TooWeak);
+        }
+        let is_breached = match &self.breached_passwords {
+            Some(breached) => breached.contains(password)?,
+            None => false,
+        };
+        if is_breached {
+            return Err(PasswordPolicyError::Breached);
+        }
+        Ok(())
+    }
+}
+
+/// A local copy of breached password hashes, in the Pwned Passwords format:
+/// one `<SHA-1>:<count>` per line, sorted by hash as in the downloadable dump.
+/// The file is far too large to be loaded: a lookup binary searches it for
+/// the range of hashes sharing the 5 characters prefix of the password's, as
+/// with the k-anonymity range API, and only reads that range.
+pub struct BreachedPasswords {
+    path: PathBuf,
+}
+
+impl BreachedPasswords {
+    /// Fails early if the file cannot be read or is not in the expected format.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let breached = Self {
+            path: path.as_ref().to_owned(),
+        };
+        let mut file = breached.open()?;
+        next_hash(&mut file).with_context(|| {
+            format!(
+                "Failed to read the breached passwords file {}",
+                breached.path.display()
+            )
+        })?;
+        Ok(breached)
+    }
+
+    fn open(&self) -> Result<BufReader<File>, anyhow::Error> {
+        let file = File::open(&self.path).with_context(|| {
+            format!(
+                "Failed to open the breached passwords file {}",
+                self.path.display()
+            )
+        })?;
+        Ok(BufReader::new(file))
+    }
+
+    /// The suffixes of the breached hashes starting with `prefix`.
+    pub fn range(&self, prefix: &str) -> Result<Vec<String>, anyhow::Error> {
+        let prefix = prefix.to_ascii_uppercase();
+        let mut file = self.open()?;
+        let length = file.get_ref().metadata()?.len();
+        // The first position from which the next line is not before the range
+        let (mut low, mut high) = (0, length);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            seek_line(&mut file, middle)?;
+            match next_hash(&mut file)? {
+                Some(hash) if hash[..RANGE_PREFIX_LENGTH] < *prefix => low = middle + 1,
+                _ => high = middle,
+            }
+        }
+        seek_line(&mut file, low)?;
+        let mut suffixes = vec![];
+        while let Some(hash) = next_hash(&mut file)? {
+            let Some(suffix) = hash.strip_prefix(&prefix) else {
+                break;
+            };
+            suffixes.push(suffix.to_owned());
+        }
+        Ok(suffixes)
+    }
+
+    pub fn contains(&self, password: &str) -> Result<bool, anyhow::Error> {
+        let hash = format!("{:X}", Sha1::digest(password.as_bytes()));
+        let (prefix, suffix) = hash.split_at(RANGE_PREFIX_LENGTH);
+        Ok(self.range(prefix)?.iter().any(|s| s == suffix))
+    }
+}
+
+/// Move to the start of the first line at or after `position`.
+fn seek_line(file: &mut BufReader<File>, position: u64) -> Result<(), anyhow::Error> {
+    if position == 0 {
+        file.seek(SeekFrom::Start(0))?;
+        return Ok(());
+    }
+    // Skip the end of the line `position` may be in the middle of
+    file.seek(SeekFrom::Start(position - 1))?;
+    file.read_until(b'\n', &mut vec![])?;
+    Ok(())
+}
+
+/// The uppercase hash of the next line, or `None` at the end of the file.
+fn next_hash(file: &mut BufReader<File>) -> Result<Option<String>, anyhow::Error> {
+    let mut line = String::new();
+    while line.trim().is_empty() {
+        line.clear();
+        if file.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+    }
+    let hash = line.split(':').next().unwrap_or_default().trim();
+    if hash.len() != 40 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("Invalid breached password hash: {hash}");
+    }
+    Ok(Some(hash.to_ascii_uppercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_matches, assert_ok};
+    use secrecy::Secret;
+    use sha1::{Digest, Sha1};
+
+    use super::{BreachedPasswords, PasswordPolicy, PasswordPolicyError};
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 12,
+            max_length: 128,
+            min_strength: 3,
+            breached_passwords: None,
+        }
+    }
+
+    fn check(policy: &PasswordPolicy, password: &str) -> Result<(), PasswordPolicyError> {
+        policy.check(&Secret::new(password.to_owned()), "ursula")
+    }
+
+    #[test]
+    fn a_strong_password_is_accepted() {
+        assert_ok!(check(&policy(), "correct-horse-battery-staple"));
+    }
+
+    #[test]
+    fn length_is_measured_in_graphemes() {
+        // 12 graphemes, but 3 times as many bytes
+        assert_ok!(check(&policy(), "ひらがなのパスワードです"));
+        let password = "ミ".repeat(129);
+        assert_matches!(
+            check(&policy(), &password),
+            Err(PasswordPolicyError::InvalidLength { .. })
+        );
+    }
+
+    #[test]
+    fn a_password_containing_the_username_is_rejected() {
+        assert_matches!(
+            check(&policy(), "xX-URSULA-rocks-42"),
+            Err(PasswordPolicyError::ContainsUsername)
+        );
+    }
+
+    #[test]
+    fn a_guessable_password_is_rejected() {
+        assert_matches!(
+            check(&policy(), "password1234"),
+            Err(PasswordPolicyError::TooWeak)
+        );
+    }
+
+    /// A breached passwords file made of `lines`.
+    fn breached_passwords(lines: &[String]) -> BreachedPasswords {
+        let path = std::env::temp_dir().join(format!("breached-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, lines.join("\r\n")).unwrap();
+        BreachedPasswords::load(path).unwrap()
+    }
+
+    #[test]
+    fn a_breached_password_is_rejected() {
+        // SHA-1 of "correct-horse-battery-staple"
+        let breached = breached_passwords(&[
+            "0123456789ABCDEF0123456789ABCDEF01234567:3".to_owned(),
+            "dd606cd49bbbd06b4c2606fc2449f8fb87975786:12".to_owned(),
+        ]);
+        let policy = PasswordPolicy {
+            breached_passwords: Some(breached),
+            ..policy()
+        };
+        assert_matches!(
+            check(&policy, "correct-horse-battery-staple"),
+            Err(PasswordPolicyError::Breached)
+        );
+        assert_ok!(check(&policy, "a-completely-different-passphrase"));
+    }
+
+    #[test]
+    fn a_lookup_finds_the_whole_range_of_a_prefix() {
+        let mut hashes: Vec<String> = (0..5000)
+            .map(|i| format!("{:X}", Sha1::digest(i.to_string())))
+            .collect();
+        // A range of more than one hash
+        let shared_prefix = format!("{}0000000000000000000000000000000000", &hashes[1000][..6]);
+        hashes.push(shared_prefix.clone());
+        hashes.sort();
+        let lines: Vec<String> = hashes.iter().map(|hash| format!("{hash}:1")).collect();
+        let breached = breached_passwords(&lines);
+
+        for hash in [&hashes[0], &shared_prefix, &hashes[2500], &hashes[5000]] {
+            let prefix = &hash[..5];
+            let expected: Vec<&str> = hashes
+                .iter()
+                .filter_map(|hash| hash.strip_prefix(prefix))
+                .collect();
+            assert_eq!(breached.range(prefix).unwrap(), expected);
+        }
+        assert!(breached.contains("2500").unwrap());
+        assert!(!breached.contains("5000").unwrap());
+    }
+
+    #[test]
+    fn a_malformed_breached_passwords_file_is_rejected() {
+        let path = std::env::temp_dir().join(format!("breached-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "not-a-hash:1\n").unwrap();
+        assert!(BreachedPasswords::load(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
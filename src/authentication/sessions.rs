@@ -0,0 +1,158 @@
+use actix_web::HttpRequest;
+use actix_web::http::header::USER_AGENT;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::client_ip::client_ip;
+use crate::configuration::SessionSettings;
+
+#[derive(serde::Serialize)]
+pub struct SessionRecord {
+    pub session_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// Record a new session for the user and return its id, to be stored
+/// in the session state.
+/// The expired sessions of the user are removed at the same time.
+#[tracing::instrument(name = "Register a session", skip(request, settings, pool))]
+pub async fn register_session(
+    user_id: Uuid,
+    request: &HttpRequest,
+    settings: &SessionSettings,
+    pool: &PgPool,
+) -> Result<Uuid, anyhow::Error> {
+    let ip_address = client_ip(request);
+    let user_agent = request
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|h| h.to_str().ok())
+        .map(String::from);
+    let session_id = Uuid::new_v4();
+    let now = Utc::now();
+    sqlx::query!(
+        r#"
+        DELETE FROM user_sessions
+        WHERE user_id = $1 AND (last_seen_at < $2 OR created_at < $3)
+        "#,
+        user_id,
+        now - settings.idle_timeout(),
+        now - settings.absolute_lifetime()
+    )
+    .execute(pool)
+    .await
+    .context("Failed to delete the stale sessions of the user.")?;
+    sqlx::query!(
+        r#"
+        INSERT INTO user_sessions (
+            session_id,
+            user_id,
+            created_at,
+            last_seen_at,
+            ip_address,
+            user_agent
+        )
+        VALUES ($1, $2, $3, $3, $4, $5)
+        "#,
+        session_id,
+        user_id,
+        now,
+        ip_address,
+        user_agent
+    )
+    .execute(pool)
+    .await
+    .context("Failed to register the session.")?;
+    Ok(session_id)
+}
+
+/// Update the last activity of the session.
+/// Returns `false` if the session has been revoked.
+#[tracing::instrument(name = "Touch a session", skip(pool))]
+pub async fn touch_session(
+    session_id: Uuid,
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE user_sessions
+        SET last_seen_at = now()
+        WHERE session_id = $1 AND user_id = $2
+        "#,
+        session_id,
+        user_id
+    )
+    .execute(pool)
+    .await
+    .context("Failed to update the last activity of the session.")?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// The sessions of the user that have not expired yet.
+#[tracing::instrument(name = "Get the sessions of a user", skip(settings, pool))]
+pub async fn get_user_sessions(
+    user_id: Uuid,
+    settings: &SessionSettings,
+    pool: &PgPool,
+) -> Result<Vec<SessionRecord>, anyhow::Error> {
+    let now = Utc::now();
+    sqlx::query_as!(
+        SessionRecord,
+        r#"
+        SELECT session_id, created_at, last_seen_at, ip_address, user_agent
+        FROM user_sessions
+        WHERE user_id = $1 AND last_seen_at >= $2 AND created_at >= $3
+        ORDER BY last_seen_at DESC
+        "#,
+        user_id,
+        now - settings.idle_timeout(),
+        now - settings.absolute_lifetime()
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the sessions of the user.")
+}
+
+#[tracing::instrument(name = "Revoke a session", skip(executor))]
+pub async fn revoke_session(
+    user_id: Uuid,
+    session_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "DELETE FROM user_sessions WHERE user_id = $1 AND session_id = $2",
+        user_id,
+        session_id
+    )
+    .execute(executor)
+    .await
+    .context("Failed to revoke the session.")?;
+    Ok(())
+}
+
+/// Log the user out of all their sessions, except the `kept` one if any.
+#[tracing::instrument(name = "Revoke the sessions of a user", skip(executor))]
+pub async fn revoke_sessions(
+    user_id: Uuid,
+    kept: Option<Uuid>,
+    executor: impl PgExecutor<'_>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM user_sessions
+        WHERE user_id = $1 AND session_id IS DISTINCT FROM $2
+        "#,
+        user_id,
+        kept
+    )
+    .execute(executor)
+    .await
+    .context("Failed to revoke the sessions of the user.")?;
+    Ok(())
+}
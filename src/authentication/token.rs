@@ -0,0 +1,18 @@
+use rand::distributions::Alphanumeric;
+use rand::{Rng, thread_rng};
+use sha2::{Digest, Sha256};
+
+/// A random token to be sent to the user, e.g. in an email link.
+pub fn generate_token() -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .map(char::from)
+        .take(32)
+        .collect()
+}
+
+/// Tokens are random and long enough not to need a slow hash:
+/// we only store their hashes so that a database leak does not leak them.
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
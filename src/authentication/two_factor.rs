@@ -0,0 +1,263 @@
+use anyhow::Context;
+use qrcode::QrCode;
+use qrcode::render::svg;
+use rand::distributions::Alphanumeric;
+use rand::{Rng, thread_rng};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use std::time::{SystemTime, UNIX_EPOCH};
+use totp_rs::{Algorithm, TOTP};
+use uuid::Uuid;
+
+use super::token::hash_token;
+
+const ISSUER: &str = "zero2prod";
+const RECOVERY_CODES_COUNT: usize = 10;
+
+/// A new base32-encoded TOTP secret, to be confirmed by the user
+/// before it is stored.
+pub fn generate_totp_secret() -> Secret<String> {
+    Secret::new(totp_rs::Secret::generate_secret().to_encoded().to_string())
+}
+
+fn totp(secret: &Secret<String>, username: &str) -> Result<TOTP, anyhow::Error> {
+    let secret = totp_rs::Secret::Encoded(secret.expose_secret().clone())
+        .to_bytes()
+        .map_err(|e| anyhow::anyhow!("Invalid TOTP secret: {e:?}"))?;
+    // `:` separates the issuer from the account name in the provisioning URL
+    let account_name = username.replace(':', "");
+    // 30 seconds steps, accepting the previous and next codes for clock skew
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        secret,
+        Some(ISSUER.into()),
+        account_name,
+    )
+    .context("Failed to build the TOTP generator")
+}
+
+/// The QR code to scan with an authenticator app, as an SVG image.
+pub fn provisioning_qr_code(
+    secret: &Secret<String>,
+    username: &str,
+) -> Result<String, anyhow::Error> {
+    let url = totp(secret, username)?.get_url();
+    let qr_code = QrCode::new(url).context("Failed to build the QR code")?;
+    Ok(qr_code
+        .render::<svg::Color>()
+        .min_dimensions(200, 200)
+        .build())
+}
+
+/// The time step of `code` if it is one of the codes accepted right now,
+/// for [`use_totp_step`] to make sure it is only used once.
+pub fn verify_totp_code(
+    secret: &Secret<String>,
+    username: &str,
+    code: &str,
+) -> Result<Option<i64>, anyhow::Error> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut totp = totp(secret, username)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("The system clock is before the UNIX epoch")?
+        .as_secs();
+    let current_step = now / totp.step;
+    // Each step accepted with the skew is checked on its own
+    let skew = u64::from(totp.skew);
+    totp.skew = 0;
+    let step = (current_step.saturating_sub(skew)..=current_step + skew)
+        .find(|step| totp.check(&code, step * totp.step));
+    Ok(step.map(|step| step as i64))
+}
+
+/// Record that the code of `step` was used to log in, returning `false`
+/// if this or a later code was already used: a code seen by someone else
+/// cannot be replayed while it is still valid.
+#[tracing::instrument(name = "Use a TOTP time step", skip(pool))]
+pub async fn use_totp_step(user_id: Uuid, step: i64, pool: &PgPool) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_last_used_step = $2
+        WHERE user_id = $1
+            AND (totp_last_used_step IS NULL OR totp_last_used_step < $2)
+        "#,
+        user_id,
+        step
+    )
+    .execute(pool)
+    .await
+    .context("Failed to use a TOTP time step.")?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[tracing::instrument(name = "Get TOTP secret", skip(pool))]
+pub async fn get_totp_secret(
+    user_id: Uuid,
+    pool: &PgPool,
+) -> Result<Option<Secret<String>>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT totp_secret
+        FROM users
+        WHERE user_id = $1
+        "#,
+        user_id,
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to perform a query to retrieve the TOTP secret.")?;
+    Ok(row.totp_secret.map(Secret::new))
+}
+
+/// Store the secret and return freshly generated recovery codes.
+///
+/// Only the hashes of the recovery codes are stored: this is the only
+/// time the user gets to see them.
+#[tracing::instrument(name = "Enable two-factor authentication", skip(secret, pool))]
+pub async fn enable_two_factor(
+    user_id: Uuid,
+    secret: &Secret<String>,
+    pool: &PgPool,
+) -> Result<Vec<String>, anyhow::Error> {
+    let recovery_codes: Vec<String> = (0..RECOVERY_CODES_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+    let code_hashes: Vec<String> = recovery_codes
+        .iter()
+        .map(|c| hash_recovery_code(c))
+        .collect();
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    sqlx::query!(
+        "UPDATE users SET totp_secret = $1, totp_last_used_step = NULL WHERE user_id = $2",
+        secret.expose_secret(),
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to store the TOTP secret.")?;
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to delete the previous recovery codes.")?;
+    sqlx::query!(
+        r#"
+        INSERT INTO recovery_codes (user_id, code_hash)
+        SELECT $1, * FROM UNNEST($2::TEXT[])
+        "#,
+        user_id,
+        &code_hashes
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to store the recovery codes.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to enable two-factor authentication")?;
+
+    Ok(recovery_codes)
+}
+
+#[tracing::instrument(name = "Disable two-factor authentication", skip(pool))]
+pub async fn disable_two_factor(user_id: Uuid, pool: &PgPool) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    sqlx::query!(
+        "UPDATE users SET totp_secret = NULL, totp_last_used_step = NULL WHERE user_id = $1",
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to remove the TOTP secret.")?;
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to delete the recovery codes.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to disable two-factor authentication")?;
+    Ok(())
+}
+
+/// Mark the recovery code as used, returning `false` if it is unknown
+/// or was already used.
+#[tracing::instrument(name = "Use a recovery code", skip(code, pool))]
+pub async fn use_recovery_code(
+    user_id: Uuid,
+    code: &str,
+    pool: &PgPool,
+) -> Result<bool, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE recovery_codes
+        SET used_at = now()
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        "#,
+        user_id,
+        hash_recovery_code(code)
+    )
+    .execute(pool)
+    .await
+    .context("Failed to use a recovery code.")?;
+    Ok(result.rows_affected() > 0)
+}
+
+fn generate_recovery_code() -> String {
+    let code: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .take(10)
+        .collect();
+    format!("{}-{}", &code[..5], &code[5..])
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hash_token(&normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify_totp_code;
+    use super::{generate_recovery_code, generate_totp_secret, hash_recovery_code, totp};
+
+    #[test]
+    fn recovery_codes_are_normalized_before_hashing() {
+        let code = generate_recovery_code();
+        let typed = format!(" {} ", code.replace('-', "").to_uppercase());
+        assert_eq!(hash_recovery_code(&code), hash_recovery_code(&typed));
+    }
+
+    #[test]
+    fn the_current_code_is_accepted() {
+        let secret = generate_totp_secret();
+        let code = totp(&secret, "admin").unwrap().generate_current().unwrap();
+        assert!(verify_totp_code(&secret, "admin", &code).unwrap().is_some());
+    }
+
+    #[test]
+    fn a_code_from_another_secret_is_rejected() {
+        let secret = generate_totp_secret();
+        let code = totp(&generate_totp_secret(), "admin")
+            .unwrap()
+            .generate_current()
+            .unwrap();
+        assert!(verify_totp_code(&secret, "admin", &code).unwrap().is_none());
+    }
+}
@@ -0,0 +1,101 @@
+use std::net::IpAddr;
+
+use actix_web::{HttpRequest, web};
+
+/// The proxies the application is deployed behind, such as a load balancer.
+/// Only they are trusted to tell the IP address of the client.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
+impl TrustedProxies {
+    /// The IP address of the client that sent `request`.
+    ///
+    /// `X-Forwarded-For` is only read when the peer is a trusted proxy, and
+    /// from the right: each proxy appends the address it got the request
+    /// from, so the entries left of the first untrusted one could have been
+    /// written by the client itself.
+    pub fn client_ip(&self, request: &HttpRequest) -> Option<IpAddr> {
+        let mut client_ip = request.peer_addr()?.ip();
+        let forwarded_for: Vec<&str> = request
+            .headers()
+            .get_all("X-Forwarded-For")
+            .filter_map(|header| header.to_str().ok())
+            .flat_map(|header| header.split(','))
+            .collect();
+        for address in forwarded_for.into_iter().rev() {
+            if !self.0.contains(&client_ip) {
+                break;
+            }
+            match address.trim().parse() {
+                Ok(address) => client_ip = address,
+                // The proxy would not have written it
+                Err(_) => break,
+            }
+        }
+        Some(client_ip)
+    }
+}
+
+/// The IP address of the client, given the trusted proxies registered
+/// with the application.
+pub fn client_ip(request: &HttpRequest) -> Option<String> {
+    let client_ip = match request.app_data::<web::Data<TrustedProxies>>() {
+        Some(trusted_proxies) => trusted_proxies.client_ip(request),
+        None => TrustedProxies::default().client_ip(request),
+    };
+    client_ip.map(|ip| ip.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use crate::client_ip::TrustedProxies;
+
+    fn proxies() -> TrustedProxies {
+        TrustedProxies(vec![
+            "10.0.0.1".parse().unwrap(),
+            "10.0.0.2".parse().unwrap(),
+        ])
+    }
+
+    #[test]
+    fn forwarded_addresses_are_ignored_without_a_trusted_proxy() {
+        let request = TestRequest::default()
+            .peer_addr("203.0.113.7:1234".parse().unwrap())
+            .insert_header(("X-Forwarded-For", "198.51.100.1"))
+            .to_http_request();
+        let client_ip = proxies().client_ip(&request).unwrap();
+        assert_eq!(client_ip.to_string(), "203.0.113.7");
+    }
+
+    #[test]
+    fn the_address_forwarded_by_a_trusted_proxy_is_used() {
+        let request = TestRequest::default()
+            .peer_addr("10.0.0.1:1234".parse().unwrap())
+            .insert_header(("X-Forwarded-For", "198.51.100.1"))
+            .to_http_request();
+        let client_ip = proxies().client_ip(&request).unwrap();
+        assert_eq!(client_ip.to_string(), "198.51.100.1");
+    }
+
+    #[test]
+    fn addresses_written_by_the_client_are_skipped() {
+        let request = TestRequest::default()
+            .peer_addr("10.0.0.1:1234".parse().unwrap())
+            .insert_header(("X-Forwarded-For", "192.0.2.9, 198.51.100.1, 10.0.0.2"))
+            .to_http_request();
+        let client_ip = proxies().client_ip(&request).unwrap();
+        assert_eq!(client_ip.to_string(), "198.51.100.1");
+    }
+
+    #[test]
+    fn a_malformed_forwarded_address_is_not_trusted() {
+        let request = TestRequest::default()
+            .peer_addr("10.0.0.1:1234".parse().unwrap())
+            .insert_header(("X-Forwarded-For", "198.51.100.1, not-an-ip"))
+            .to_http_request();
+        let client_ip = proxies().client_ip(&request).unwrap();
+        assert_eq!(client_ip.to_string(), "10.0.0.1");
+    }
+}
@@ -1,7 +1,16 @@
+use crate::anti_abuse::{ChallengeVerifier, NoChallenge, RateLimit, SiteVerifyChallenge};
+use crate::authentication::{BreachedPasswords, PasswordHashing, PasswordPolicy};
 use crate::{domain::SubscriberEmail, email_client::EmailClient};
+use actix_cors::Cors;
+use actix_session::SessionMiddleware;
+use actix_session::config::{BrowserSession, PersistentSession, SessionLifecycle};
+use actix_session::storage::RedisSessionStore;
+use actix_web::cookie::{Key, SameSite, time};
+use actix_web::http::header;
 use secrecy::{ExposeSecret, Secret};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
+use std::net::IpAddr;
 
 #[derive(Clone, serde::Deserialize)]
 pub struct Settings {
@@ -9,6 +18,125 @@ pub struct Settings {
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
     pub redis_uri: Secret<String>,
+    pub anti_abuse: AntiAbuseSettings,
+    pub password_hashing: PasswordHashingSettings,
+    pub password_policy: PasswordPolicySettings,
+    pub idempotency: IdempotencySettings,
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct IdempotencySettings {
+    /// How long a saved response is replayed. An older key is treated as new.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub retention_seconds: u64,
+    /// How often the expired keys are deleted.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub cleanup_interval_seconds: u64,
+    /// How long a request waits for another one holding the same key,
+    /// before giving up with a 409.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub in_flight_timeout_milliseconds: u64,
+}
+
+impl IdempotencySettings {
+    pub fn retention(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.retention_seconds as i64)
+    }
+
+    pub fn cleanup_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.cleanup_interval_seconds)
+    }
+}
+
+/// The Argon2id parameters of new password hashes.
+/// Existing hashes are upgraded the next time their user logs in.
+#[derive(Clone, serde::Deserialize)]
+pub struct PasswordHashingSettings {
+    pub memory_cost_kib: u32,
+    pub time_cost: u32,
+    pub parallelism: u32,
+}
+
+impl PasswordHashingSettings {
+    pub fn hashing(&self) -> Result<PasswordHashing, anyhow::Error> {
+        let params =
+            argon2::Params::new(self.memory_cost_kib, self.time_cost, self.parallelism, None)
+                .map_err(|e| anyhow::anyhow!("Invalid Argon2 parameters: {e}"))?;
+        PasswordHashing::new(params)
+    }
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct PasswordPolicySettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_length: usize,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_length: usize,
+    /// The minimum zxcvbn score, from 0 to 4.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_strength: u8,
+    /// A list of breached password hashes in the Pwned Passwords format,
+    /// sorted by hash.
+    pub breached_passwords_path: Option<String>,
+}
+
+impl PasswordPolicySettings {
+    pub fn policy(&self) -> Result<PasswordPolicy, anyhow::Error> {
+        let breached_passwords = self
+            .breached_passwords_path
+            .as_ref()
+            .map(BreachedPasswords::load)
+            .transpose()?;
+        Ok(PasswordPolicy {
+            min_length: self.min_length,
+            max_length: self.max_length,
+            min_strength: self.min_strength,
+            breached_passwords,
+        })
+    }
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct AntiAbuseSettings {
+    /// Namespace of the rate limit counters in Redis.
+    pub rate_limit_key_prefix: String,
+    pub subscriptions_per_ip: RateLimit,
+    pub subscriptions_per_email: RateLimit,
+    pub password_resets_per_email: RateLimit,
+    pub challenge: Option<ChallengeSettings>,
+    pub login: LoginThrottleSettings,
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct LoginThrottleSettings {
+    pub max_failures_per_username: u64,
+    pub max_failures_per_ip: u64,
+    /// How long a failed attempt is remembered.
+    pub failure_window_seconds: u64,
+    pub lockout_seconds: u64,
+    /// The delay after the first failure, doubled with every other failure.
+    pub base_delay_milliseconds: u64,
+    pub max_delay_milliseconds: u64,
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct ChallengeSettings {
+    pub verify_url: String,
+    pub secret_key: Secret<String>,
+    pub timeout_milliseconds: u64,
+}
+
+impl AntiAbuseSettings {
+    pub fn challenge_verifier(&self) -> Box<dyn ChallengeVerifier> {
+        match &self.challenge {
+            Some(c) => Box::new(SiteVerifyChallenge::new(
+                c.verify_url.clone(),
+                c.secret_key.clone(),
+                std::time::Duration::from_millis(c.timeout_milliseconds),
+            )),
+            None => Box::new(NoChallenge),
+        }
+    }
 }
 
 #[derive(Clone, serde::Deserialize)]
@@ -47,6 +175,107 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub hmac_secret: Secret<String>,
+    pub session: SessionSettings,
+    pub cors: CorsSettings,
+    /// The proxies in front of the application, whose `X-Forwarded-For`
+    /// header tells the IP address of the client.
+    pub trusted_proxies: Vec<IpAddr>,
+    /// The signup forms allowed to identify themselves as the `source`
+    /// of a subscription, recorded as part of the consent evidence.
+    pub subscription_sources: Vec<String>,
+}
+
+/// Which other sites can call the public subscription endpoints from a browser.
+#[derive(Clone, serde::Deserialize)]
+pub struct CorsSettings {
+    pub allowed_origins: Vec<String>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_age_seconds: usize,
+}
+
+impl CorsSettings {
+    /// Our own pages are always allowed, as browsers send an `Origin`
+    /// header with same-origin form submissions as well.
+    pub fn middleware(&self, base_url: &str) -> Cors {
+        self.allowed_origins
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(base_url))
+            .fold(Cors::default(), |cors, origin| {
+                cors.allowed_origin(origin.trim_end_matches('/'))
+            })
+            .allowed_methods(["GET", "POST"])
+            .allowed_headers([header::ACCEPT, header::CONTENT_TYPE])
+            .max_age(self.max_age_seconds)
+    }
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct SessionSettings {
+    pub cookie_name: String,
+    pub cookie_secure: bool,
+    pub cookie_same_site: CookieSameSite,
+    pub mode: SessionMode,
+    /// Sessions end this long after the login, even if they are in use.
+    pub absolute_lifetime_seconds: u64,
+    /// Sessions end after this long without any request.
+    pub idle_timeout_seconds: u64,
+    /// How long after the password the second factor can be entered.
+    pub two_factor_timeout_seconds: u64,
+}
+
+#[derive(Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CookieSameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+#[derive(Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionMode {
+    /// The cookie expires with the session state.
+    Persistent,
+    /// The cookie is deleted when the browser is closed.
+    Browser,
+}
+
+impl SessionSettings {
+    pub fn middleware(
+        &self,
+        store: RedisSessionStore,
+        key: Key,
+    ) -> SessionMiddleware<RedisSessionStore> {
+        let same_site = match self.cookie_same_site {
+            CookieSameSite::Strict => SameSite::Strict,
+            CookieSameSite::Lax => SameSite::Lax,
+            CookieSameSite::None => SameSite::None,
+        };
+        let ttl = time::Duration::seconds(self.absolute_lifetime_seconds as i64);
+        let lifecycle: SessionLifecycle = match self.mode {
+            SessionMode::Persistent => PersistentSession::default().session_ttl(ttl).into(),
+            SessionMode::Browser => BrowserSession::default().state_ttl(ttl).into(),
+        };
+        SessionMiddleware::builder(store, key)
+            .cookie_name(self.cookie_name.clone())
+            .cookie_secure(self.cookie_secure)
+            .cookie_same_site(same_site)
+            .session_lifecycle(lifecycle)
+            .build()
+    }
+
+    pub fn absolute_lifetime(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.absolute_lifetime_seconds as i64)
+    }
+
+    pub fn idle_timeout(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.idle_timeout_seconds as i64)
+    }
+
+    pub fn two_factor_timeout(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.two_factor_timeout_seconds as i64)
+    }
 }
 
 #[derive(Clone, serde::Deserialize)]
@@ -131,7 +360,10 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
         .add_source(
             config::Environment::with_prefix("APP")
                 .prefix_separator("_")
-                .separator("__"),
+                .separator("__")
+                // e.g. `APP_APPLICATION__CORS__ALLOWED_ORIGINS=https://a.com,https://b.com`
+                .list_separator(",")
+                .with_list_parse_key("application.cors.allowed_origins"),
         )
         .build()?;
     settings.try_deserialize::<Settings>()
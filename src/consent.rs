@@ -0,0 +1,154 @@
+use actix_web::HttpRequest;
+use actix_web::http::header::USER_AGENT;
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::client_ip::client_ip;
+
+/// Every statement a subscriber could have agreed to when signing up, oldest first.
+///
+/// Signup forms send back the version they showed, and the matching text is
+/// stored with the consent event. To change the statement, append a new
+/// version: never edit or remove one, subscribers may still be looking at it.
+const CONSENT_TEXTS: [ConsentText; 1] = [ConsentText {
+    version: "2026-10-18",
+    text: "I agree to receive the newsletter by email. \
+        I can unsubscribe at any time and ask for my data to be erased.",
+}];
+
+#[derive(Debug, Clone, Copy, serde::Serialize, utoipa::ToSchema)]
+pub struct ConsentText {
+    #[schema(example = "2026-10-18")]
+    pub version: &'static str,
+    pub text: &'static str,
+}
+
+impl ConsentText {
+    /// The statement new signup forms should show.
+    pub fn current() -> Self {
+        CONSENT_TEXTS[CONSENT_TEXTS.len() - 1]
+    }
+
+    pub fn find(version: &str) -> Option<Self> {
+        CONSENT_TEXTS.into_iter().find(|t| t.version == version)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ConsentEventType {
+    Subscribed,
+    Confirmed,
+    Imported,
+}
+
+impl ConsentEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConsentEventType::Subscribed => "subscribed",
+            ConsentEventType::Confirmed => "confirmed",
+            ConsentEventType::Imported => "imported",
+        }
+    }
+}
+
+/// The `source` values signup forms are allowed to send.
+pub struct SubscriptionSources(pub Vec<String>);
+
+impl SubscriptionSources {
+    pub fn contains(&self, source: &str) -> bool {
+        self.0.iter().any(|s| s == source)
+    }
+}
+
+/// Where a consent event came from.
+pub struct ConsentEvidence {
+    pub source: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl ConsentEvidence {
+    pub fn from_request(request: &HttpRequest, source: impl Into<String>) -> Self {
+        let ip_address = client_ip(request);
+        let user_agent = request
+            .headers()
+            .get(USER_AGENT)
+            .and_then(|h| h.to_str().ok())
+            .map(String::from);
+
+        Self {
+            source: source.into(),
+            ip_address,
+            user_agent,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct ConsentEventRecord {
+    pub event_type: String,
+    pub source: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub consent_text: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(
+    name = "Record a consent event",
+    skip(executor, evidence, consent_text)
+)]
+pub async fn record_consent_event(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+    event_type: ConsentEventType,
+    evidence: &ConsentEvidence,
+    consent_text: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscription_consent_events (
+            event_id,
+            subscriber_id,
+            event_type,
+            source,
+            ip_address,
+            user_agent,
+            consent_text,
+            recorded_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, now())
+        "#,
+        Uuid::new_v4(),
+        subscriber_id,
+        event_type.as_str(),
+        evidence.source,
+        evidence.ip_address,
+        evidence.user_agent,
+        consent_text
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Get consent events", skip(pool))]
+pub async fn get_consent_events(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Vec<ConsentEventRecord>, sqlx::Error> {
+    sqlx::query_as!(
+        ConsentEventRecord,
+        r#"
+        SELECT event_type, source, ip_address, user_agent, consent_text, recorded_at
+        FROM subscription_consent_events
+        WHERE subscriber_id = $1
+        ORDER BY recorded_at
+        "#,
+        subscriber_id
+    )
+    .fetch_all(pool)
+    .await
+}
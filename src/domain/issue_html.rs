@@ -0,0 +1,347 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+use lol_html::html_content::Element;
+use lol_html::{ElementContentHandlers, RewriteStrSettings, Selector, element, rewrite_str, text};
+
+/// Elements that are refused rather than silently removed.
+const FORBIDDEN_ELEMENTS: [&str; 11] = [
+    "script", "iframe", "frame", "frameset", "object", "embed", "applet", "form", "base", "link",
+    "meta",
+];
+/// Attributes kept on every element, for table based email layouts.
+const GENERIC_ATTRIBUTES: [&str; 6] = ["style", "align", "valign", "width", "height", "role"];
+const TABLE_ATTRIBUTES: [&str; 3] = ["cellpadding", "cellspacing", "border"];
+const STYLE_PROPERTIES: [&str; 40] = [
+    "background",
+    "background-color",
+    "border",
+    "border-bottom",
+    "border-collapse",
+    "border-color",
+    "border-left",
+    "border-radius",
+    "border-right",
+    "border-spacing",
+    "border-style",
+    "border-top",
+    "border-width",
+    "color",
+    "display",
+    "font",
+    "font-family",
+    "font-size",
+    "font-style",
+    "font-weight",
+    "height",
+    "letter-spacing",
+    "line-height",
+    "margin",
+    "margin-bottom",
+    "margin-left",
+    "margin-right",
+    "margin-top",
+    "max-width",
+    "min-width",
+    "padding",
+    "padding-bottom",
+    "padding-left",
+    "padding-right",
+    "padding-top",
+    "text-align",
+    "text-decoration",
+    "text-transform",
+    "vertical-align",
+    "width",
+];
+/// Where `inline_styles` keeps the `style` attribute written by the editor,
+/// which wins over the rules of `<style>` blocks.
+const INLINE_STYLE_ATTRIBUTE: &str = "data-inline-style";
+
+/// The HTML content of an issue, safe to send and to show in the archive:
+/// the rules of `<style>` blocks are inlined into the elements they match,
+/// for email clients that ignore stylesheets, and elements, attributes and
+/// style properties outside of an allowlist are removed.
+#[derive(Debug)]
+pub struct IssueHtml(String);
+
+impl IssueHtml {
+    /// Fails with a warning for each part of `html` that cannot be kept
+    /// as written, for the editor to fix before the issue is accepted.
+    pub fn parse(html: &str) -> Result<IssueHtml, Vec<String>> {
+        let warnings = RefCell::new(forbidden_content(html));
+        let html = inline_styles(html, &warnings);
+        let warnings = warnings.into_inner();
+        if !warnings.is_empty() {
+            return Err(warnings);
+        }
+        let html = ammonia::Builder::default()
+            .add_generic_attributes(GENERIC_ATTRIBUTES)
+            .add_tag_attributes("table", TABLE_ATTRIBUTES)
+            .filter_style_properties(HashSet::from(STYLE_PROPERTIES))
+            .clean(&html)
+            .to_string();
+        Ok(Self(html))
+    }
+}
+
+impl AsRef<str> for IssueHtml {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+fn warn(warnings: &RefCell<Vec<String>>, warning: String) {
+    let mut warnings = warnings.borrow_mut();
+    if !warnings.contains(&warning) {
+        warnings.push(warning);
+    }
+}
+
+/// The scripts and alike that `html` contains.
+fn forbidden_content(html: &str) -> Vec<String> {
+    let warnings = RefCell::new(vec![]);
+    let result = rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![element!("*", |el| {
+                let tag_name = el.tag_name();
+                if FORBIDDEN_ELEMENTS.contains(&tag_name.as_str()) {
+                    warn(
+                        &warnings,
+                        format!("The content cannot contain `{tag_name}` elements."),
+                    );
+                }
+                for attribute in el.attributes() {
+                    let name = attribute.name();
+                    if name.starts_with("on") {
+                        warn(
+                            &warnings,
+                            format!("The content cannot contain event handlers such as `{name}`."),
+                        );
+                    }
+                    if attribute
+                        .value()
+                        .trim_start()
+                        .to_lowercase()
+                        .starts_with("javascript:")
+                    {
+                        warn(
+                            &warnings,
+                            "The content cannot contain `javascript:` links.".to_owned(),
+                        );
+                    }
+                }
+                Ok(())
+            })],
+            ..RewriteStrSettings::new()
+        },
+    );
+    if result.is_err() {
+        warn(&warnings, "The content is not valid HTML.".to_owned());
+    }
+    warnings.into_inner()
+}
+
+/// Move the rules of the `<style>` blocks of `html` into the `style`
+/// attribute of the elements they match. Rules apply in the order they are
+/// written, regardless of the specificity of their selectors.
+fn inline_styles(html: &str, warnings: &RefCell<Vec<String>>) -> String {
+    let stylesheet = RefCell::new(String::new());
+    let Ok(html) = rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![
+                element!("style", |el| {
+                    el.remove_and_keep_content();
+                    Ok(())
+                }),
+                text!("style", |chunk| {
+                    stylesheet.borrow_mut().push_str(chunk.as_str());
+                    chunk.remove();
+                    Ok(())
+                }),
+            ],
+            ..RewriteStrSettings::new()
+        },
+    ) else {
+        warn(warnings, "The content is not valid HTML.".to_owned());
+        return html.to_owned();
+    };
+    let rules = parse_rules(&stylesheet.into_inner(), warnings);
+    if rules.is_empty() {
+        return html;
+    }
+
+    let mut handlers = vec![element!("*", |el| {
+        if let Some(style) = el.get_attribute("style") {
+            el.remove_attribute("style");
+            el.set_attribute(INLINE_STYLE_ATTRIBUTE, &style)?;
+        }
+        Ok(())
+    })];
+    for (selector, declarations) in rules {
+        let Ok(parsed_selector) = selector.parse::<Selector>() else {
+            warn(
+                warnings,
+                format!("The CSS selector `{selector}` cannot be inlined."),
+            );
+            continue;
+        };
+        handlers.push((
+            Cow::Owned(parsed_selector),
+            ElementContentHandlers::default().element(move |el: &mut Element<'_, '_>| {
+                append_style(el, &declarations)?;
+                Ok(())
+            }),
+        ));
+    }
+    handlers.push(element!("*", |el| {
+        if let Some(style) = el.get_attribute(INLINE_STYLE_ATTRIBUTE) {
+            el.remove_attribute(INLINE_STYLE_ATTRIBUTE);
+            append_style(el, &style)?;
+        }
+        Ok(())
+    }));
+    rewrite_str(
+        &html,
+        RewriteStrSettings {
+            element_content_handlers: handlers,
+            ..RewriteStrSettings::new()
+        },
+    )
+    .unwrap_or(html)
+}
+
+fn append_style(
+    el: &mut Element<'_, '_>,
+    declarations: &str,
+) -> Result<(), lol_html::errors::AttributeNameError> {
+    let declarations = declarations.trim().trim_end_matches(';');
+    let style = match el.get_attribute("style") {
+        Some(style) => format!("{}; {declarations}", style.trim_end_matches(';')),
+        None => declarations.to_owned(),
+    };
+    el.set_attribute("style", &style)
+}
+
+/// The selectors of `stylesheet`, each with its declarations. At-rules,
+/// such as `@media`, cannot be inlined.
+fn parse_rules(stylesheet: &str, warnings: &RefCell<Vec<String>>) -> Vec<(String, String)> {
+    let mut css = String::new();
+    let mut rest = stylesheet;
+    while let Some(start) = rest.find("/*") {
+        css.push_str(&rest[..start]);
+        rest = rest[start..]
+            .find("*/")
+            .map_or("", |end| &rest[start + end + 2..]);
+    }
+    css.push_str(rest);
+
+    let mut rules = vec![];
+    let mut rest = css.as_str();
+    while let Some(open) = rest.find('{') {
+        let prelude = rest[..open].trim();
+        let block = &rest[open + 1..];
+        if prelude.starts_with('@') {
+            let at_keyword = prelude.split_whitespace().next().unwrap_or(prelude);
+            warn(
+                warnings,
+                format!("The CSS `{at_keyword}` rules cannot be inlined."),
+            );
+            // Skip the whole block, nested rules included
+            let mut depth = 1;
+            let end = block.char_indices().find_map(|(i, c)| {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+                (depth == 0).then_some(i)
+            });
+            rest = end.map_or("", |end| &block[end + 1..]);
+            continue;
+        }
+        let (declarations, next) = block.split_once('}').unwrap_or((block, ""));
+        for selector in prelude.split(',').map(str::trim) {
+            if !selector.is_empty() && !declarations.trim().is_empty() {
+                rules.push((selector.to_owned(), declarations.trim().to_owned()));
+            }
+        }
+        rest = next;
+    }
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::IssueHtml;
+
+    #[test]
+    fn allowed_html_is_kept() {
+        let html =
+            IssueHtml::parse(r#"<p>Hello <a href="https://example.com">world</a></p>"#).unwrap();
+        assert_eq!(
+            html.as_ref(),
+            r#"<p>Hello <a href="https://example.com" rel="noopener noreferrer">world</a></p>"#
+        );
+    }
+
+    #[test]
+    fn scripts_and_event_handlers_are_refused() {
+        let warnings = IssueHtml::parse(r#"<p onclick="alert(1)">Hi</p><script>alert(2)</script>"#)
+            .unwrap_err();
+        assert_eq!(
+            warnings,
+            vec![
+                "The content cannot contain event handlers such as `onclick`.",
+                "The content cannot contain `script` elements.",
+            ]
+        );
+    }
+
+    #[test]
+    fn javascript_links_are_refused() {
+        let warnings = IssueHtml::parse(r#"<a href=" JavaScript:alert(1)">Hi</a>"#).unwrap_err();
+        assert_eq!(
+            warnings,
+            vec!["The content cannot contain `javascript:` links."]
+        );
+    }
+
+    #[test]
+    fn style_blocks_are_inlined() {
+        let html = IssueHtml::parse(
+            "<style>p { color: red; } .big, h1 { font-size: 20px }</style>\
+            <h1>Title</h1><p class=\"big\" style=\"color: blue\">Text</p>",
+        )
+        .unwrap();
+        assert_eq!(
+            html.as_ref(),
+            "<h1 style=\"font-size:20px\">Title</h1>\
+            <p style=\"color:red;font-size:20px;color:blue\">Text</p>"
+        );
+    }
+
+    #[test]
+    fn rules_that_cannot_be_inlined_are_refused() {
+        let warnings = IssueHtml::parse(
+            "<style>@media (max-width: 600px) { p { color: red } } a:hover { color: red }</style>",
+        )
+        .unwrap_err();
+        assert_eq!(
+            warnings,
+            vec![
+                "The CSS `@media` rules cannot be inlined.",
+                "The CSS selector `a:hover` cannot be inlined.",
+            ]
+        );
+    }
+
+    #[test]
+    fn style_properties_outside_the_allowlist_are_removed() {
+        let html = IssueHtml::parse(r#"<p style="color: red; position: fixed">Text</p>"#).unwrap();
+        assert_eq!(html.as_ref(), r#"<p style="color:red">Text</p>"#);
+    }
+}
@@ -0,0 +1,69 @@
+/// The URL-friendly name of an issue in the public archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueSlug(String);
+
+impl IssueSlug {
+    /// Lowercase ASCII letters and digits of the title, separated by dashes.
+    pub fn from_title(title: &str) -> IssueSlug {
+        let slug = title
+            .to_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        if slug.is_empty() {
+            IssueSlug("issue".into())
+        } else {
+            IssueSlug(slug)
+        }
+    }
+
+    /// The slug itself if it is not `taken`, or the first free one
+    /// with a numeric suffix, e.g. `hello-2`.
+    pub fn first_free(self, taken: &[String]) -> IssueSlug {
+        if !taken.contains(&self.0) {
+            return self;
+        }
+        (2..)
+            .map(|n| format!("{}-{n}", self.0))
+            .find(|slug| !taken.contains(slug))
+            .map(IssueSlug)
+            .unwrap()
+    }
+}
+
+impl AsRef<str> for IssueSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::IssueSlug;
+
+    #[test]
+    fn a_title_is_lowercased_and_dashed() {
+        let slug = IssueSlug::from_title("Hello, World! Issue #3");
+        assert_eq!(slug.as_ref(), "hello-world-issue-3");
+    }
+
+    #[test]
+    fn a_title_without_letters_or_digits_gets_a_default_slug() {
+        let slug = IssueSlug::from_title("¡¿?!");
+        assert_eq!(slug.as_ref(), "issue");
+    }
+
+    #[test]
+    fn a_free_slug_is_kept() {
+        let slug = IssueSlug::from_title("Hello").first_free(&["other".into()]);
+        assert_eq!(slug.as_ref(), "hello");
+    }
+
+    #[test]
+    fn a_taken_slug_gets_the_first_free_suffix() {
+        let taken = ["hello".to_string(), "hello-2".to_string()];
+        let slug = IssueSlug::from_title("Hello").first_free(&taken);
+        assert_eq!(slug.as_ref(), "hello-3");
+    }
+}
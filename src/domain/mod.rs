@@ -1,9 +1,17 @@
+mod issue_html;
+mod issue_slug;
 mod new_subscriber;
 mod subscriber_email;
 mod subscriber_name;
+mod subscriber_status;
 mod subscription_token;
+mod user_role;
 
+pub use issue_html::IssueHtml;
+pub use issue_slug::IssueSlug;
 pub use new_subscriber::NewSubscriber;
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use subscriber_status::SubscriberStatus;
 pub use subscription_token::SubscriptionToken;
+pub use user_role::UserRole;
@@ -0,0 +1,71 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriberStatus {
+    PendingConfirmation,
+    Confirmed,
+}
+
+impl SubscriberStatus {
+    pub fn parse(s: &str) -> Result<SubscriberStatus, String> {
+        match s.trim().to_lowercase().as_str() {
+            "pending_confirmation" | "pending" => Ok(Self::PendingConfirmation),
+            "confirmed" => Ok(Self::Confirmed),
+            other => Err(format!(
+                "{other} is not a valid subscriber status. \
+                Use either `pending_confirmation` or `confirmed`."
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SubscriberStatus::PendingConfirmation => "pending_confirmation",
+            SubscriberStatus::Confirmed => "confirmed",
+        }
+    }
+}
+
+impl std::fmt::Display for SubscriberStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::SubscriberStatus;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn stored_statuses_are_parsed_successfully() {
+        assert_ok_eq!(
+            SubscriberStatus::parse("pending_confirmation"),
+            SubscriberStatus::PendingConfirmation
+        );
+        assert_ok_eq!(
+            SubscriberStatus::parse("confirmed"),
+            SubscriberStatus::Confirmed
+        );
+    }
+
+    #[test]
+    fn parsing_ignores_case_and_surrounding_whitespace() {
+        assert_ok_eq!(
+            SubscriberStatus::parse(" Confirmed "),
+            SubscriberStatus::Confirmed
+        );
+    }
+
+    #[test]
+    fn pending_is_accepted_as_a_shorthand() {
+        assert_ok_eq!(
+            SubscriberStatus::parse("pending"),
+            SubscriberStatus::PendingConfirmation
+        );
+    }
+
+    #[test]
+    fn unknown_statuses_are_rejected() {
+        assert_err!(SubscriberStatus::parse("unsubscribed"));
+        assert_err!(SubscriberStatus::parse(""));
+    }
+}
@@ -0,0 +1,64 @@
+/// What an admin user is allowed to do.
+///
+/// Roles are ordered: every role can do what the previous ones can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UserRole {
+    /// Can look around the admin area.
+    Viewer,
+    /// Can also publish newsletter issues and manage subscribers.
+    Editor,
+    /// Can also manage the admin users.
+    Owner,
+}
+
+impl UserRole {
+    pub fn parse(s: &str) -> Result<UserRole, String> {
+        match s.trim().to_lowercase().as_str() {
+            "viewer" => Ok(Self::Viewer),
+            "editor" => Ok(Self::Editor),
+            "owner" => Ok(Self::Owner),
+            other => Err(format!(
+                "{other} is not a valid role. Use either `viewer`, `editor` or `owner`."
+            )),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UserRole::Viewer => "viewer",
+            UserRole::Editor => "editor",
+            UserRole::Owner => "owner",
+        }
+    }
+}
+
+impl std::fmt::Display for UserRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::UserRole;
+    use claims::{assert_err, assert_ok_eq};
+
+    #[test]
+    fn stored_roles_are_parsed_successfully() {
+        assert_ok_eq!(UserRole::parse("viewer"), UserRole::Viewer);
+        assert_ok_eq!(UserRole::parse("editor"), UserRole::Editor);
+        assert_ok_eq!(UserRole::parse(" Owner "), UserRole::Owner);
+    }
+
+    #[test]
+    fn unknown_roles_are_rejected() {
+        assert_err!(UserRole::parse("admin"));
+        assert_err!(UserRole::parse(""));
+    }
+
+    #[test]
+    fn roles_include_the_lower_ones() {
+        assert!(UserRole::Owner > UserRole::Editor);
+        assert!(UserRole::Editor > UserRole::Viewer);
+    }
+}
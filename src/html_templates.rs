@@ -1,6 +1,12 @@
 use lazy_static::lazy_static;
 use tera::Tera;
 
+use crate::anti_abuse::LockoutRecord;
+use crate::authentication::{ApiTokenRecord, SessionRecord};
+use crate::consent::{ConsentEventRecord, ConsentText};
+use crate::routes::{ImportReport, InvitationRecord, IssueSummary, PublicIssue, UserRecord};
+use crate::subscriber_data::SubscriptionRecord;
+
 lazy_static! {
     pub static ref TEMPLATES: Tera = {
         match Tera::new("templates/*.html") {
@@ -16,6 +22,14 @@ lazy_static! {
 pub struct Templates;
 
 impl Templates {
+    pub fn render_home(consent: &ConsentText) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("consent", consent);
+        TEMPLATES
+            .render("home.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render home template: {e}"))
+    }
+
     pub fn render_welcome(
         subscriber_name: &str,
         confirmation_link: &str,
@@ -31,12 +45,207 @@ impl Templates {
     pub fn render_publish_newsletter(
         flash_messages: &str,
         idempotency_key: uuid::Uuid,
+        csrf_token: &str,
     ) -> Result<String, anyhow::Error> {
         let mut context = tera::Context::new();
         context.insert("flash_messages", &flash_messages);
+        context.insert("csrf_token", csrf_token);
         context.insert("idempotency_key", &idempotency_key);
         TEMPLATES
             .render("publish_newsletter.html", &context)
             .map_err(|e| anyhow::anyhow!("Could not render send newsletter template: {e}"))
     }
+
+    pub fn render_import_subscribers(
+        flash_messages: &str,
+        csrf_token: &str,
+    ) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("flash_messages", &flash_messages);
+        context.insert("csrf_token", csrf_token);
+        TEMPLATES
+            .render("import_subscribers.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render import subscribers template: {e}"))
+    }
+
+    pub fn render_import_subscribers_report(
+        report: &ImportReport,
+    ) -> Result<String, anyhow::Error> {
+        let context = tera::Context::from_serialize(report)?;
+        TEMPLATES
+            .render("import_subscribers_report.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render import report template: {e}"))
+    }
+
+    pub fn render_subscribers(
+        subscribers: &[SubscriptionRecord],
+        page: i64,
+        has_next_page: bool,
+    ) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("subscribers", subscribers);
+        context.insert("page", &page);
+        context.insert("has_next_page", &has_next_page);
+        TEMPLATES
+            .render("subscribers.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render subscribers template: {e}"))
+    }
+
+    pub fn render_subscriber(
+        subscriber: &SubscriptionRecord,
+        consent_events: &[ConsentEventRecord],
+    ) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("subscriber", subscriber);
+        context.insert("consent_events", consent_events);
+        TEMPLATES
+            .render("subscriber.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render subscriber template: {e}"))
+    }
+
+    pub fn render_issues(
+        issues: &[IssueSummary],
+        page: i64,
+        has_next_page: bool,
+    ) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("issues", issues);
+        context.insert("page", &page);
+        context.insert("has_next_page", &has_next_page);
+        TEMPLATES
+            .render("issues.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render issues template: {e}"))
+    }
+
+    pub fn render_issue(issue: &PublicIssue) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("issue", issue);
+        TEMPLATES
+            .render("issue.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render issue template: {e}"))
+    }
+
+    /// The HTML of an issue written in Markdown, laid out for email clients.
+    pub fn render_issue_email(title: &str, content: &str) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("title", title);
+        context.insert("content", content);
+        TEMPLATES
+            .render("issue_email.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render issue email template: {e}"))
+    }
+
+    pub fn render_lockouts(lockouts: &[LockoutRecord]) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("lockouts", lockouts);
+        TEMPLATES
+            .render("lockouts.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render lockouts template: {e}"))
+    }
+
+    /// `enrollment` holds the QR code and the secret, if two-factor
+    /// authentication is not enabled yet.
+    pub fn render_two_factor(
+        flash_messages: &str,
+        enrollment: Option<(&str, &str)>,
+        csrf_token: &str,
+    ) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("flash_messages", &flash_messages);
+        context.insert("csrf_token", csrf_token);
+        context.insert("enabled", &enrollment.is_none());
+        if let Some((qr_code, secret)) = enrollment {
+            context.insert("qr_code", qr_code);
+            context.insert("secret", secret);
+        }
+        TEMPLATES
+            .render("two_factor.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render two-factor template: {e}"))
+    }
+
+    pub fn render_recovery_codes(recovery_codes: &[String]) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("recovery_codes", recovery_codes);
+        TEMPLATES
+            .render("recovery_codes.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render recovery codes template: {e}"))
+    }
+
+    pub fn render_users(
+        flash_messages: &str,
+        users: &[UserRecord],
+        invitations: &[InvitationRecord],
+        csrf_token: &str,
+    ) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("flash_messages", &flash_messages);
+        context.insert("csrf_token", csrf_token);
+        context.insert("users", users);
+        context.insert("invitations", invitations);
+        TEMPLATES
+            .render("users.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render users template: {e}"))
+    }
+
+    pub fn render_accept_invitation(
+        flash_messages: &str,
+        invitation_token: &str,
+        email: &str,
+        role: &str,
+    ) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("flash_messages", &flash_messages);
+        context.insert("invitation_token", invitation_token);
+        context.insert("email", email);
+        context.insert("role", role);
+        TEMPLATES
+            .render("accept_invitation.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render accept invitation template: {e}"))
+    }
+
+    pub fn render_password_reset(
+        flash_messages: &str,
+        reset_token: &str,
+    ) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("flash_messages", &flash_messages);
+        context.insert("reset_token", reset_token);
+        TEMPLATES
+            .render("password_reset.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render password reset template: {e}"))
+    }
+
+    pub fn render_sessions(
+        flash_messages: &str,
+        sessions: &[SessionRecord],
+        current_session_id: uuid::Uuid,
+        csrf_token: &str,
+    ) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("flash_messages", &flash_messages);
+        context.insert("csrf_token", csrf_token);
+        context.insert("sessions", sessions);
+        context.insert("current_session_id", &current_session_id);
+        TEMPLATES
+            .render("sessions.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render sessions template: {e}"))
+    }
+
+    pub fn render_api_tokens(
+        flash_messages: &str,
+        new_api_token: Option<&str>,
+        api_tokens: &[ApiTokenRecord],
+        scopes: &[&str],
+        csrf_token: &str,
+    ) -> Result<String, anyhow::Error> {
+        let mut context = tera::Context::new();
+        context.insert("flash_messages", &flash_messages);
+        context.insert("csrf_token", csrf_token);
+        context.insert("new_api_token", &new_api_token);
+        context.insert("api_tokens", api_tokens);
+        context.insert("scopes", scopes);
+        TEMPLATES
+            .render("api_tokens.html", &context)
+            .map_err(|e| anyhow::anyhow!("Could not render API tokens template: {e}"))
+    }
 }
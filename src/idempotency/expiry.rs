@@ -0,0 +1,27 @@
+use sqlx::PgPool;
+
+use super::delete_expired_keys;
+use crate::configuration::{IdempotencySettings, Settings};
+use crate::startup::get_connection_pool;
+
+async fn expiry_loop(pool: PgPool, settings: IdempotencySettings) -> Result<(), anyhow::Error> {
+    loop {
+        match delete_expired_keys(&pool, settings.retention()).await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!(deleted = n, "Deleted expired idempotency keys"),
+            Err(e) => {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to delete expired idempotency keys",
+                );
+            }
+        }
+        tokio::time::sleep(settings.cleanup_interval()).await;
+    }
+}
+
+pub async fn run_expiry_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    expiry_loop(connection_pool, configuration.idempotency).await
+}
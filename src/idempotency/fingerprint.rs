@@ -0,0 +1,77 @@
+use sha2::{Digest, Sha256};
+
+/// A hash of the payload an idempotency key was first used with.
+///
+/// Reusing a key with a different payload is an error rather than a replay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestFingerprint(Vec<u8>);
+
+impl RequestFingerprint {
+    /// Hash the given fields, in order.
+    pub fn from_fields<T: AsRef<[u8]>>(fields: impl IntoIterator<Item = T>) -> Self {
+        let mut hasher = Sha256::new();
+        for field in fields {
+            let field = field.as_ref();
+            // Length-prefixed, so that `["ab", "c"]` and `["a", "bc"]` differ
+            hasher.update((field.len() as u64).to_be_bytes());
+            hasher.update(field);
+        }
+        Self(hasher.finalize().to_vec())
+    }
+
+    /// Hash the path of a form submission and its fields, in any order:
+    /// the same form can be sent with its fields in a different order.
+    pub fn from_form<'a>(
+        path: &'a str,
+        fields: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Self {
+        let mut fields: Vec<_> = fields.into_iter().collect();
+        fields.sort_unstable();
+        Self::from_fields(
+            std::iter::once(path).chain(fields.into_iter().flat_map(|(name, value)| [name, value])),
+        )
+    }
+}
+
+impl AsRef<[u8]> for RequestFingerprint {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RequestFingerprint;
+
+    #[test]
+    fn same_fields_give_the_same_fingerprint() {
+        assert_eq!(
+            RequestFingerprint::from_fields(["title", "body"]),
+            RequestFingerprint::from_fields(["title", "body"])
+        );
+    }
+
+    #[test]
+    fn the_order_of_form_fields_does_not_matter() {
+        assert_eq!(
+            RequestFingerprint::from_form("/admin/newsletters", [("title", "a"), ("body", "b")]),
+            RequestFingerprint::from_form("/admin/newsletters", [("body", "b"), ("title", "a")])
+        );
+    }
+
+    #[test]
+    fn form_values_stay_with_their_field() {
+        assert_ne!(
+            RequestFingerprint::from_form("/admin/newsletters", [("title", "a"), ("body", "b")]),
+            RequestFingerprint::from_form("/admin/newsletters", [("title", "b"), ("body", "a")])
+        );
+    }
+
+    #[test]
+    fn field_boundaries_are_part_of_the_fingerprint() {
+        assert_ne!(
+            RequestFingerprint::from_fields(["ab", "c"]),
+            RequestFingerprint::from_fields(["a", "bc"])
+        );
+    }
+}
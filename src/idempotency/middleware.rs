@@ -0,0 +1,256 @@
+use std::cell::RefCell;
+use std::future::{Ready, ready};
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::{FromRequest, HttpMessage, HttpRequest, HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::{IdempotencyKey, NextAction, RequestFingerprint, save_response, try_processing};
+use crate::authentication::{ApiToken, UserId};
+use crate::configuration::IdempotencySettings;
+use crate::utils::{e500, see_other};
+
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+const IDEMPOTENCY_KEY_FIELD: &str = "idempotency_key";
+/// The form fields that are not part of the payload of a request.
+const IGNORED_FORM_FIELDS: [&str; 2] = [IDEMPOTENCY_KEY_FIELD, "csrf_token"];
+const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+/// Where `idempotent` keeps the transaction for the handler to pick up,
+/// and the handler hands it back once it is done.
+#[derive(Clone)]
+struct TransactionSlot(Rc<RefCell<Option<PgTransaction>>>);
+
+/// The transaction holding the idempotency key of the current request.
+///
+/// The handler's writes and its response are committed together only if
+/// it calls [`IdempotentTransaction::complete`]. Dropping it instead rolls
+/// them back and lets the key be used again, e.g. after a validation error.
+///
+/// A handler taking it must therefore complete it on every path returning
+/// a success: otherwise the response is sent but nothing is saved, and a
+/// retry is processed again. `idempotent` logs `2xx` responses sent without
+/// completing it as errors; redirects are not, as admin forms are sent back
+/// with a flash message on validation errors.
+pub struct IdempotentTransaction {
+    transaction: PgTransaction,
+    slot: TransactionSlot,
+}
+
+impl IdempotentTransaction {
+    /// Hand the transaction back to `idempotent`, to save the response in it.
+    pub fn complete(self) {
+        self.slot.0.borrow_mut().replace(self.transaction);
+    }
+}
+
+impl Deref for IdempotentTransaction {
+    type Target = PgTransaction;
+    fn deref(&self) -> &Self::Target {
+        &self.transaction
+    }
+}
+
+impl DerefMut for IdempotentTransaction {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.transaction
+    }
+}
+
+impl FromRequest for IdempotentTransaction {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let slot = req.extensions().get::<TransactionSlot>().cloned();
+        let transaction = slot.and_then(|slot| {
+            let transaction = slot.0.borrow_mut().take()?;
+            Some(Self { transaction, slot })
+        });
+        ready(transaction.ok_or_else(|| e500("The route is not wrapped in `idempotent`")))
+    }
+}
+
+/// Who is making the request, which decides how errors are rendered.
+enum Caller {
+    Api(Uuid),
+    Admin(Uuid),
+}
+
+impl Caller {
+    fn from_request(req: &ServiceRequest) -> Option<Self> {
+        let extensions = req.extensions();
+        if let Some(api_token) = extensions.get::<ApiToken>() {
+            Some(Self::Api(api_token.user_id))
+        } else {
+            extensions
+                .get::<UserId>()
+                .map(|user_id| Self::Admin(**user_id))
+        }
+    }
+
+    fn user_id(&self) -> Uuid {
+        match self {
+            Self::Api(user_id) | Self::Admin(user_id) => *user_id,
+        }
+    }
+
+    fn error(&self, status: StatusCode, message: &str) -> actix_web::Error {
+        let response = match self {
+            Self::Api(_) => {
+                HttpResponse::build(status).json(serde_json::json!({ "error": message }))
+            }
+            Self::Admin(_) => HttpResponse::build(status).body(message.to_owned()),
+        };
+        InternalError::from_response(anyhow::anyhow!("{message}"), response).into()
+    }
+}
+
+/// Make a route idempotent: a request is processed once per key, and
+/// retries with the same key get the saved response back.
+///
+/// The key is read from the `Idempotency-Key` header or, for forms, the
+/// `idempotency_key` field; admin forms without one are sent back with
+/// an error message. Handlers take an [`IdempotentTransaction`]
+/// to write in the same transaction as the saved response.
+/// Must be registered inside `reject_anonymous_users` or
+/// `reject_invalid_api_tokens`.
+pub async fn idempotent(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    idempotent_with(req, next, || {}).await
+}
+
+/// Like [`idempotent`], calling `on_replay` before returning a saved response,
+/// e.g. to send the flash message the handler would have sent.
+pub async fn idempotent_with(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+    on_replay: impl FnOnce(),
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let caller = Caller::from_request(&req)
+        .ok_or_else(|| e500("`idempotent` is registered outside authentication"))?;
+    let body = req
+        .extract::<web::Payload>()
+        .await?
+        .to_bytes_limited(MAX_BODY_BYTES)
+        .await
+        .map_err(|_| caller.error(StatusCode::PAYLOAD_TOO_LARGE, "The request is too large."))??;
+    let form_fields = (req.content_type() == "application/x-www-form-urlencoded")
+        .then(|| serde_urlencoded::from_bytes::<Vec<(String, String)>>(&body).ok())
+        .flatten();
+
+    let idempotency_key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        Some(header) => Some(header.to_str().map(str::to_owned).map_err(|_| {
+            caller.error(StatusCode::BAD_REQUEST, "Invalid Idempotency-Key header.")
+        })?),
+        None => form_fields
+            .iter()
+            .flatten()
+            .find(|(name, _)| name == IDEMPOTENCY_KEY_FIELD)
+            .map(|(_, value)| value.clone()),
+    };
+    let Some(idempotency_key) = idempotency_key else {
+        return match caller {
+            // A form without the field is as broken as one missing any other
+            Caller::Admin(_) => {
+                FlashMessage::error(
+                    "The form fields are incorrect, incomplete or badly formatted.",
+                )
+                .send();
+                let response = see_other(req.path());
+                Ok(req.into_response(response))
+            }
+            Caller::Api(_) => {
+                Err(caller.error(StatusCode::BAD_REQUEST, "Missing Idempotency-Key header."))
+            }
+        };
+    };
+    let idempotency_key: IdempotencyKey = idempotency_key
+        .try_into()
+        .map_err(|e: anyhow::Error| caller.error(StatusCode::BAD_REQUEST, &e.to_string()))?;
+    // Reusing a key on another route is a different payload too
+    let path = req.path().to_owned();
+    let fingerprint = match &form_fields {
+        Some(fields) => RequestFingerprint::from_form(
+            &path,
+            fields
+                .iter()
+                .filter(|(name, _)| !IGNORED_FORM_FIELDS.contains(&name.as_str()))
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        ),
+        None => RequestFingerprint::from_fields([path.as_bytes(), &body]),
+    };
+
+    let pool = req
+        .app_data::<web::Data<PgPool>>()
+        .expect("The database pool is not registered.")
+        .clone();
+    let settings = req
+        .app_data::<web::Data<IdempotencySettings>>()
+        .expect("The idempotency settings are not registered.")
+        .clone();
+    let user_id = caller.user_id();
+    let transaction =
+        match try_processing(&pool, &idempotency_key, user_id, &fingerprint, &settings)
+            .await
+            .map_err(e500)?
+        {
+            NextAction::StartProcessing(transaction) => transaction,
+            NextAction::ReturnSavedResponse(saved_response) => {
+                on_replay();
+                return Ok(req.into_response(saved_response));
+            }
+            NextAction::RequestInProgress => {
+                return Err(caller.error(
+                    StatusCode::CONFLICT,
+                    "A request with this idempotency key is still in progress.",
+                ));
+            }
+            NextAction::PayloadMismatch => {
+                return Err(caller.error(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "The idempotency key was already used with a different request.",
+                ));
+            }
+        };
+
+    let slot = TransactionSlot(Rc::new(RefCell::new(Some(transaction))));
+    req.extensions_mut().insert(slot.clone());
+    req.set_payload(Payload::from(body));
+    let response = next.call(req).await?.map_into_boxed_body();
+    // Failed requests are not saved: the transaction is rolled back
+    // when dropped, so a retry is processed again
+    let transaction = slot.0.borrow_mut().take();
+    match transaction {
+        Some(transaction)
+            if !response.status().is_client_error() && !response.status().is_server_error() =>
+        {
+            let (req, response) = response.into_parts();
+            let response = save_response(transaction, &idempotency_key, user_id, response)
+                .await
+                .map_err(e500)?;
+            Ok(ServiceResponse::new(req, response))
+        }
+        None if response.status().is_success() => {
+            // The handler took the transaction without completing it
+            tracing::error!(
+                path,
+                "A successful response was not saved: the idempotent transaction was not completed"
+            );
+            Ok(response)
+        }
+        _ => Ok(response),
+    }
+}
@@ -1,9 +1,13 @@
+mod expiry;
+mod fingerprint;
 mod key;
+mod middleware;
 mod persistence;
 
+pub use expiry::run_expiry_until_stopped;
+pub use fingerprint::RequestFingerprint;
 pub use key::IdempotencyKey;
+pub use middleware::{IdempotentTransaction, idempotent, idempotent_with};
 pub use persistence::get_saved_response;
 pub use persistence::save_response;
-pub use persistence::{NextAction, try_processing};
-
-// TODO: Add a background task to expire the keys
+pub use persistence::{NextAction, delete_expired_keys, try_processing};
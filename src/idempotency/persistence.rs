@@ -1,10 +1,15 @@
-use super::IdempotencyKey;
+use super::{IdempotencyKey, RequestFingerprint};
+use crate::configuration::IdempotencySettings;
 use actix_web::body::to_bytes;
 use actix_web::{HttpResponse, http::StatusCode};
+use chrono::Utc;
 use sqlx::PgPool;
 use sqlx::{Executor, Postgres, Transaction};
 use uuid::Uuid;
 
+/// The SQLSTATE of a `lock_timeout` expiring.
+const LOCK_NOT_AVAILABLE: &str = "55P03";
+
 #[derive(Debug, sqlx::Type)]
 #[sqlx(type_name = "header_pair")]
 struct HeaderPairRecord {
@@ -17,6 +22,10 @@ pub enum NextAction {
     // Return transaction for later usage
     StartProcessing(Transaction<'static, Postgres>),
     ReturnSavedResponse(HttpResponse),
+    // Another request with the same key did not finish in time
+    RequestInProgress,
+    // The key was first used with a different payload
+    PayloadMismatch,
 }
 
 pub async fn get_saved_response(
@@ -99,32 +108,109 @@ pub async fn save_response(
     Ok(http_response)
 }
 
+/// A key older than the retention window is treated as new, even if its row
+/// has not been deleted yet. So is a key whose row was committed without a
+/// response, which is left behind by a request that never finished.
+///
+/// While another request holds the same key, this waits for it to finish for
+/// at most the in-flight timeout.
+///
+/// The saved response is only replayed for the payload the key was first
+/// used with, as identified by `fingerprint`.
 pub async fn try_processing(
     pool: &PgPool,
     idempotency_key: &IdempotencyKey,
     user_id: Uuid,
+    fingerprint: &RequestFingerprint,
+    settings: &IdempotencySettings,
 ) -> Result<NextAction, anyhow::Error> {
     let mut transaction = pool.begin().await?;
+    // `SET` cannot be prepared, hence the simple query
+    transaction
+        .execute(
+            format!(
+                "SET LOCAL lock_timeout = {}",
+                settings.in_flight_timeout_milliseconds
+            )
+            .as_str(),
+        )
+        .await?;
     let query = sqlx::query!(
         r#"
         INSERT INTO idempotency (
             user_id,
             idempotency_key,
+            request_hash,
             created_at
         )
-        VALUES ($1, $2, now())
-        ON CONFLICT DO NOTHING
+        VALUES ($1, $2, $3, now())
+        ON CONFLICT (user_id, idempotency_key) DO UPDATE
+        SET
+            request_hash = EXCLUDED.request_hash,
+            created_at = now(),
+            response_status_code = NULL,
+            response_headers = NULL,
+            response_body = NULL
+        WHERE
+            idempotency.created_at < $4 OR
+            idempotency.response_status_code IS NULL
         "#,
         user_id,
-        idempotency_key.as_ref()
+        idempotency_key.as_ref(),
+        fingerprint.as_ref(),
+        Utc::now() - settings.retention()
     );
-    let n_inserted_rows = transaction.execute(query).await?.rows_affected();
+    let n_inserted_rows = match transaction.execute(query).await {
+        Ok(result) => result.rows_affected(),
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some(LOCK_NOT_AVAILABLE) => {
+            return Ok(NextAction::RequestInProgress);
+        }
+        Err(e) => return Err(e.into()),
+    };
     if n_inserted_rows > 0 {
+        transaction
+            .execute("SET LOCAL lock_timeout = DEFAULT")
+            .await?;
         Ok(NextAction::StartProcessing(transaction))
     } else {
+        let saved_fingerprint = sqlx::query_scalar!(
+            r#"
+            SELECT request_hash
+            FROM idempotency
+            WHERE
+                user_id = $1 AND
+                idempotency_key = $2
+            "#,
+            user_id,
+            idempotency_key.as_ref()
+        )
+        .fetch_one(pool)
+        .await?;
+        // Keys saved before fingerprinting have no hash to compare
+        if saved_fingerprint.is_some_and(|f| f != fingerprint.as_ref()) {
+            return Ok(NextAction::PayloadMismatch);
+        }
         let saved_response = get_saved_response(pool, idempotency_key, user_id)
             .await?
             .ok_or_else(|| anyhow::anyhow!("We expected a saved response, we didn't find it"))?;
         Ok(NextAction::ReturnSavedResponse(saved_response))
     }
 }
+
+/// Delete the keys older than `retention`, returning how many were deleted.
+#[tracing::instrument(name = "Delete expired idempotency keys", skip(pool))]
+pub async fn delete_expired_keys(
+    pool: &PgPool,
+    retention: chrono::Duration,
+) -> Result<u64, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM idempotency
+        WHERE created_at < $1
+        "#,
+        Utc::now() - retention
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
@@ -5,7 +5,8 @@ use sqlx::{Executor, PgPool, Postgres, Transaction};
 use tracing::{Span, field::display};
 use uuid::Uuid;
 
-use crate::domain::SubscriberEmail;
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
+use crate::routes::send_confirmation_email;
 use crate::{configuration::Settings, startup::get_connection_pool};
 
 pub enum ExecutionOutcome {
@@ -13,6 +14,20 @@ pub enum ExecutionOutcome {
     EmptyQueue,
 }
 
+/// Deliver an issue to a subscriber or, once every issue is delivered,
+/// send a confirmation email to an imported subscriber.
+pub async fn try_execute_task(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    if let ExecutionOutcome::TaskCompleted = try_deliver_issue(pool, email_client, base_url).await?
+    {
+        return Ok(ExecutionOutcome::TaskCompleted);
+    }
+    try_send_confirmation_email(pool, email_client, base_url).await
+}
+
 #[tracing::instrument(
     skip_all,
     fields(
@@ -21,9 +36,10 @@ pub enum ExecutionOutcome {
     ),
     err
 )]
-pub async fn try_execute_task(
+async fn try_deliver_issue(
     pool: &PgPool,
     email_client: &EmailClient,
+    base_url: &str,
 ) -> Result<ExecutionOutcome, anyhow::Error> {
     let task = dequeue_task(pool).await?;
     if task.is_none() {
@@ -33,10 +49,10 @@ pub async fn try_execute_task(
     Span::current()
         .record("newsletter_issue_id", display(issue_id))
         .record("subscriber_email", display(&email));
-    match SubscriberEmail::parse(email.clone()) {
+    let delivered = match SubscriberEmail::parse(email.clone()) {
         Ok(email) => {
-            let issue = get_issue(pool, issue_id).await?;
-            if let Err(e) = email_client
+            let issue = get_issue(pool, issue_id).await?.with_archive_link(base_url);
+            match email_client
                 .send_email(
                     &email,
                     &issue.title,
@@ -45,12 +61,16 @@ pub async fn try_execute_task(
                 )
                 .await
             {
-                tracing::error!(
-                    error.cause_chain = ?e,
-                    error.message = %e,
-                    "Failed to deliver issue to a confirmed subscriber. \
-                    Skipping.",
-                );
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to deliver issue to a confirmed subscriber. \
+                        Skipping.",
+                    );
+                    false
+                }
             }
         }
         Err(e) => {
@@ -60,9 +80,10 @@ pub async fn try_execute_task(
                 "Skipping a confirmed subscriber. \
                 Their stored contact details are invalid",
             );
+            false
         }
-    }
-    delete_task(transaction, issue_id, &email).await?;
+    };
+    delete_task(transaction, issue_id, &email, delivered).await?;
     Ok(ExecutionOutcome::TaskCompleted)
 }
 
@@ -100,6 +121,7 @@ async fn delete_task(
     mut transaction: PgTransaction,
     issue_id: Uuid,
     email: &str,
+    delivered: bool,
 ) -> Result<(), anyhow::Error> {
     let query = sqlx::query!(
         r#"
@@ -112,14 +134,111 @@ async fn delete_task(
         email
     );
     transaction.execute(query).await?;
+    // Keep the delivery statistics in the same transaction as the queue,
+    // so that a task is never counted twice.
+    let query = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            delivered_count = delivered_count + $2,
+            failed_count = failed_count + $3
+        WHERE
+            newsletter_issue_id = $1
+        "#,
+        issue_id,
+        i32::from(delivered),
+        i32::from(!delivered)
+    );
+    transaction.execute(query).await?;
     transaction.commit().await?;
     Ok(())
 }
 
+#[tracing::instrument(skip_all, fields(subscriber_email=tracing::field::Empty), err)]
+async fn try_send_confirmation_email(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let task = sqlx::query!(
+        r#"
+        SELECT q.subscription_token, s.email, s.name
+        FROM confirmation_email_queue q
+        JOIN subscription_tokens t ON t.subscription_token = q.subscription_token
+        JOIN subscriptions s ON s.id = t.subscriber_id
+        FOR UPDATE OF q
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+    let Some(task) = task else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current().record("subscriber_email", display(&task.email));
+    let new_subscriber = SubscriberEmail::parse(task.email).and_then(|email| {
+        let name = SubscriberName::parse(task.name)?;
+        Ok(NewSubscriber { email, name })
+    });
+    match new_subscriber {
+        Ok(new_subscriber) => {
+            if let Err(e) = send_confirmation_email(
+                email_client,
+                new_subscriber,
+                base_url,
+                &task.subscription_token,
+            )
+            .await
+            {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to send a confirmation email to an imported subscriber. \
+                    Skipping.",
+                );
+            }
+        }
+        Err(e) => {
+            tracing::error!(
+                error.message = %e,
+                "Skipping an imported subscriber. \
+                Their stored contact details are invalid",
+            );
+        }
+    }
+    let query = sqlx::query!(
+        "DELETE FROM confirmation_email_queue WHERE subscription_token = $1",
+        task.subscription_token
+    );
+    transaction.execute(query).await?;
+    transaction.commit().await?;
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
 struct NewsletterIssue {
     title: String,
     text_content: String,
     html_content: String,
+    slug: String,
+    public: bool,
+}
+
+impl NewsletterIssue {
+    /// Public issues open with a "view in browser" link to their archive page.
+    fn with_archive_link(mut self, base_url: &str) -> Self {
+        if self.public {
+            let link = format!("{}/issues/{}", base_url, self.slug);
+            self.html_content = format!(
+                "<p><a href=\"{}\">View in browser</a></p>\n{}",
+                htmlescape::encode_minimal(&link),
+                self.html_content
+            );
+            self.text_content = format!("View in browser: {}\n\n{}", link, self.text_content);
+        }
+        self
+    }
 }
 
 #[tracing::instrument(skip_all)]
@@ -127,7 +246,7 @@ async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, any
     let issue = sqlx::query_as!(
         NewsletterIssue,
         r#"
-        SELECT title, text_content, html_content
+        SELECT title, text_content, html_content, slug, public
         FROM newsletter_issues
         WHERE
             newsletter_issue_id = $1
@@ -139,9 +258,13 @@ async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, any
     Ok(issue)
 }
 
-async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyhow::Error> {
+async fn worker_loop(
+    pool: PgPool,
+    email_client: EmailClient,
+    base_url: String,
+) -> Result<(), anyhow::Error> {
     loop {
-        match try_execute_task(&pool, &email_client).await {
+        match try_execute_task(&pool, &email_client, &base_url).await {
             Ok(ExecutionOutcome::EmptyQueue) => {
                 tokio::time::sleep(Duration::from_secs(10)).await;
             }
@@ -158,5 +281,10 @@ pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), any
     let connection_pool = get_connection_pool(&configuration.database);
     // Use helper function!
     let email_client = configuration.email_client.client();
-    worker_loop(connection_pool, email_client).await
+    worker_loop(
+        connection_pool,
+        email_client,
+        configuration.application.base_url,
+    )
+    .await
 }
@@ -1,12 +1,18 @@
+pub mod anti_abuse;
 pub mod authentication;
+pub mod client_ip;
 pub mod configuration;
+pub mod consent;
 pub mod domain;
 pub mod email_client;
 pub mod html_templates;
 pub mod idempotency;
 pub mod issue_delivery_worker;
+pub mod markdown;
+pub mod openapi;
 pub mod routes;
 pub mod session_state;
 pub mod startup;
+pub mod subscriber_data;
 pub mod telemetry;
 pub mod utils;
@@ -0,0 +1,135 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd, html};
+
+fn parser(markdown: &str) -> Parser<'_> {
+    Parser::new_ext(
+        markdown,
+        Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES,
+    )
+}
+
+/// Render `markdown` to HTML. Raw HTML in the source is kept only if it
+/// is allowed by `ammonia`: scripts, event handlers and the like are removed.
+pub fn to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    html::push_html(&mut html, parser(markdown));
+    ammonia::clean(&html)
+}
+
+/// Render `markdown` to plain text: formatting and raw HTML are dropped,
+/// list items keep their marker and links are followed by their URL.
+pub fn to_text(markdown: &str) -> String {
+    let mut text = String::new();
+    // The next number of each list being rendered, `None` for bullet lists
+    let mut lists: Vec<Option<u64>> = vec![];
+    // The URL of each link being rendered, and where its text starts
+    let mut links = vec![];
+    for event in parser(markdown) {
+        match event {
+            Event::Text(content) | Event::Code(content) => text.push_str(&content),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => {
+                text.push_str("----------");
+                end_block(&mut text, !lists.is_empty());
+            }
+            Event::Start(Tag::List(first_number)) => {
+                end_line(&mut text);
+                lists.push(first_number);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                end_block(&mut text, !lists.is_empty());
+            }
+            Event::Start(Tag::Item) => {
+                text.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        text.push_str(&format!("{number}. "));
+                        *number += 1;
+                    }
+                    _ => text.push_str("- "),
+                }
+            }
+            Event::End(TagEnd::Item) => end_line(&mut text),
+            Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. }) => {
+                links.push((dest_url, text.len()));
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                if let Some((url, start)) = links.pop() {
+                    // Autolinks already show their URL
+                    if text[start..] != *url {
+                        text.push_str(&format!(" ({url})"));
+                    }
+                }
+            }
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Heading(_)
+                | TagEnd::CodeBlock
+                | TagEnd::BlockQuote(_)
+                | TagEnd::Table,
+            ) => end_block(&mut text, !lists.is_empty()),
+            Event::End(TagEnd::TableRow | TagEnd::TableHead) => end_line(&mut text),
+            Event::End(TagEnd::TableCell) => text.push('\t'),
+            _ => {}
+        }
+    }
+    text.trim_end().to_owned()
+}
+
+fn end_line(text: &mut String) {
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+/// Blocks are separated by an empty line, except in lists.
+fn end_block(text: &mut String, in_list: bool) {
+    end_line(text);
+    if !in_list && !text.ends_with("\n\n") {
+        text.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::{to_html, to_text};
+
+    #[test]
+    fn markdown_is_rendered_to_html() {
+        let html = to_html("# Title\n\nSome *emphasis* and a [link](https://example.com).");
+        assert!(html.contains("<h1>Title</h1>"));
+        assert!(html.contains("<em>emphasis</em>"));
+        assert!(
+            html.contains(r#"<a href="https://example.com" rel="noopener noreferrer">link</a>"#)
+        );
+    }
+
+    #[test]
+    fn scripts_and_event_handlers_are_removed_from_the_html() {
+        let html = to_html("<script>alert(1)</script>\n\n<p onclick=\"alert(2)\">Hello</p>");
+        assert!(!html.contains("script"));
+        assert!(!html.contains("alert"));
+        assert!(html.contains("<p>Hello</p>"));
+    }
+
+    #[test]
+    fn markdown_is_rendered_to_plain_text() {
+        let text = to_text(
+            "# Title\n\nSome **bold** and a [link](https://example.com).\n\n\
+            - One\n- Two\n\n1. First\n2. Second",
+        );
+        assert_eq!(
+            text,
+            "Title\n\nSome bold and a link (https://example.com).\n\n\
+            - One\n- Two\n\n1. First\n2. Second"
+        );
+    }
+
+    #[test]
+    fn autolinks_are_not_repeated_in_plain_text() {
+        assert_eq!(
+            to_text("See <https://example.com>"),
+            "See https://example.com"
+        );
+    }
+}
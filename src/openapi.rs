@@ -0,0 +1,240 @@
+use utoipa::OpenApi;
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+
+use crate::configuration::SessionSettings;
+use crate::routes::*;
+
+/// The OpenAPI description of every route registered in `startup::run`.
+#[derive(OpenApi)]
+#[openapi(
+    info(description = "A newsletter: public subscription pages, an admin area and a JSON API."),
+    paths(
+        health_check,
+        openapi_document,
+        home,
+        list_issues,
+        issue_page,
+        rss_feed,
+        atom_feed,
+        subscribe,
+        consent_text,
+        confirm,
+        subscriber_data,
+        erase_subscriber_data,
+        login_form,
+        login,
+        login_two_factor_form,
+        login_two_factor,
+        accept_invitation_form,
+        accept_invitation,
+        request_password_reset_form,
+        request_password_reset,
+        password_reset_form,
+        reset_password,
+        publish_issue,
+        get_issue_delivery_status,
+        list_subscribers_api,
+        admin_dashboard,
+        change_password_form,
+        change_password,
+        change_email_form,
+        change_email,
+        list_sessions,
+        log_out_other_sessions,
+        log_out_session,
+        log_out,
+        list_api_tokens,
+        create_api_token_admin,
+        revoke_api_token_admin,
+        login_lockouts,
+        two_factor_form,
+        enable_two_factor_authentication,
+        disable_two_factor_authentication,
+        publish_newsletter_form,
+        publish_newsletter,
+        export_newsletter_issues,
+        list_subscribers,
+        subscriber_details,
+        export_subscribers,
+        subscriber_data_form,
+        export_subscriber_data,
+        erase_subscriber_data_admin,
+        import_subscribers_form,
+        import_subscribers,
+        list_users,
+        create_user_admin,
+        invite_user,
+        disable_user,
+        enable_user,
+        delete_user,
+    ),
+    tags(
+        (name = "operations"),
+        (name = "pages", description = "Public HTML pages."),
+        (name = "subscriptions", description = "Subscribing, confirming and managing a subscription. \
+            `POST /subscriptions` and `GET /subscriptions/confirm` also answer in JSON."),
+        (name = "authentication", description = "Logging in, invitations and password resets."),
+        (name = "admin", description = "The admin area, for logged in users. \
+            Form submissions must carry the CSRF token of the session."),
+        (name = "api", description = "The versioned JSON API, authenticated with API tokens."),
+    )
+)]
+pub struct ApiDoc;
+
+impl ApiDoc {
+    /// The document, with the security schemes as configured.
+    pub fn document(session_settings: &SessionSettings) -> utoipa::openapi::OpenApi {
+        let mut openapi = Self::openapi();
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::with_description(
+                session_settings.cookie_name.as_str(),
+                "The session cookie, set by logging in.",
+            ))),
+        );
+        components.add_security_scheme(
+            "csrf_token",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                "X-CSRF-Token",
+                "The CSRF token of the session, also accepted as a `csrf_token` form field.",
+            ))),
+        );
+        components.add_security_scheme(
+            "api_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("An API token, created in the admin area."))
+                    .build(),
+            ),
+        );
+        openapi
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use utoipa::OpenApi;
+
+    use super::ApiDoc;
+
+    /// The `(method, path)` of the routes registered in `startup::run`,
+    /// read from its source so that a new route cannot be forgotten.
+    fn registered_routes() -> BTreeSet<(String, String)> {
+        let source = include_str!("startup.rs");
+        let start = source.find("HttpServer::new").unwrap();
+        let end = start + source[start..].find(".listen(").unwrap();
+        let source: String = source[start..end]
+            .lines()
+            .map(|line| line.split("//").next().unwrap())
+            .flat_map(str::split_whitespace)
+            .collect();
+
+        let mut routes = BTreeSet::new();
+        // The prefixes of the enclosing scopes and resources,
+        // with the nesting depth at which they end.
+        let mut prefixes: Vec<(usize, &str)> = Vec::new();
+        let mut depth = 0;
+        let mut rest = source.as_str();
+        while let Some(c) = rest.chars().next() {
+            if let Some(after) = rest
+                .strip_prefix("web::scope(\"")
+                .or_else(|| rest.strip_prefix("web::resource(\""))
+            {
+                prefixes.push((depth, after.split('"').next().unwrap()));
+            } else if let Some(after) = rest.strip_prefix(".route(") {
+                // Resources register routes without a path.
+                let (path, after) = match after.strip_prefix('"') {
+                    Some(after) => after.split_once("\",").unwrap(),
+                    None => ("", after),
+                };
+                let method = after
+                    .strip_prefix("web::")
+                    .and_then(|after| after.split_once("()"))
+                    .unwrap()
+                    .0;
+                let prefix: String = prefixes.iter().map(|(_, prefix)| *prefix).collect();
+                routes.insert((method.to_owned(), without_patterns(&(prefix + path))));
+            }
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    prefixes.retain(|(d, _)| *d <= depth);
+                }
+                _ => {}
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+        routes
+    }
+
+    /// `{id:[0-9]+}` matches `{id}` in the document.
+    fn without_patterns(path: &str) -> String {
+        let mut result = String::new();
+        let mut depth = 0;
+        let mut in_pattern = false;
+        for c in path.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                ':' if depth == 1 => in_pattern = true,
+                _ => {}
+            }
+            if depth == 0 {
+                in_pattern = false;
+            }
+            if !in_pattern || (c == '}' && depth == 0) {
+                result.push(c);
+            }
+        }
+        result
+    }
+
+    fn documented_routes() -> BTreeSet<(String, String)> {
+        let mut routes = BTreeSet::new();
+        for (path, item) in ApiDoc::openapi().paths.paths {
+            let operations = [
+                ("get", &item.get),
+                ("post", &item.post),
+                ("put", &item.put),
+                ("patch", &item.patch),
+                ("delete", &item.delete),
+            ];
+            for (method, operation) in operations {
+                if operation.is_some() {
+                    routes.insert((method.to_owned(), path.clone()));
+                }
+            }
+        }
+        routes
+    }
+
+    #[test]
+    fn every_route_is_documented() {
+        let registered = registered_routes();
+        let documented = documented_routes();
+        let undocumented: Vec<_> = registered.difference(&documented).collect();
+        assert!(
+            undocumented.is_empty(),
+            "Routes missing from the OpenAPI document: {undocumented:?}"
+        );
+        let unknown: Vec<_> = documented.difference(&registered).collect();
+        assert!(
+            unknown.is_empty(),
+            "Routes in the OpenAPI document but not in `startup::run`: {unknown:?}"
+        );
+    }
+
+    #[test]
+    fn path_patterns_are_removed() {
+        assert_eq!(
+            without_patterns("/subscribers/{subscriber_id:[0-9a-f-]{36}}/x"),
+            "/subscribers/{subscriber_id}/x"
+        );
+        assert_eq!(without_patterns("/users/{user_id}"), "/users/{user_id}");
+    }
+}
@@ -0,0 +1,59 @@
+use std::fmt::Write;
+
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+use crate::authentication::{ApiScope, CsrfToken, UserId, get_user_api_tokens};
+use crate::domain::UserRole;
+use crate::html_templates::Templates;
+use crate::session_state::TypedSession;
+use crate::utils::e500;
+
+#[utoipa::path(
+    get,
+    path = "/admin/api-tokens",
+    tag = "admin",
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The API tokens of the user.", content_type = "text/html"),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+    )
+)]
+pub async fn list_api_tokens(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    role: web::ReqData<UserRole>,
+    csrf_token: web::ReqData<CsrfToken>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        // Errors about the form quote the submitted values
+        let content = htmlescape::encode_minimal(m.content());
+        writeln!(msg_html, "<p><i>{content}</i></p>").unwrap();
+    }
+    let new_api_token = session.take_new_api_token().map_err(e500)?;
+    let api_tokens = get_user_api_tokens(**user_id, &pool).await.map_err(e500)?;
+    // Only the scopes allowed by the role of the user can be granted.
+    let scopes: Vec<&str> = ApiScope::ALL
+        .iter()
+        .filter(|scope| *role >= scope.required_role())
+        .map(ApiScope::as_str)
+        .collect();
+
+    let html_body = Templates::render_api_tokens(
+        &msg_html,
+        new_api_token.as_ref().map(|t| t.expose_secret().as_str()),
+        &api_tokens,
+        &scopes,
+        csrf_token.as_ref(),
+    )
+    .map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
@@ -0,0 +1,8 @@
+mod get;
+mod post;
+
+pub use get::{__path_list_api_tokens, list_api_tokens};
+pub use post::{
+    __path_create_api_token_admin, __path_revoke_api_token_admin, create_api_token_admin,
+    revoke_api_token_admin,
+};
@@ -0,0 +1,96 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use secrecy::Secret;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{ApiScope, UserId, create_api_token, revoke_api_token};
+use crate::domain::UserRole;
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+// The form has a `scope` field per checked scope, which a struct
+// cannot capture: we keep the raw fields instead.
+#[utoipa::path(
+    post,
+    path = "/admin/api-tokens",
+    tag = "admin",
+    request_body(content = String, description = "A `name`, and a `scope` field per scope.", content_type = "application/x-www-form-urlencoded"),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "Redirects back to the form, with the outcome as a flash message. The new token is shown there once."),
+    )
+)]
+pub async fn create_api_token_admin(
+    form: web::Form<Vec<(String, String)>>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    role: web::ReqData<UserRole>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let role = role.into_inner();
+    let mut name = String::new();
+    let mut scopes = Vec::new();
+    for (key, value) in form.into_inner() {
+        match key.as_str() {
+            "name" => name = value.trim().to_owned(),
+            "scope" => match ApiScope::parse(&value) {
+                Ok(scope) if !scopes.contains(&scope) => scopes.push(scope),
+                Ok(_) => {}
+                Err(e) => {
+                    FlashMessage::error(e).send();
+                    return Ok(see_other("/admin/api-tokens"));
+                }
+            },
+            _ => {}
+        }
+    }
+    if name.is_empty() {
+        FlashMessage::error("The name of the token cannot be empty.").send();
+        return Ok(see_other("/admin/api-tokens"));
+    }
+    if scopes.is_empty() {
+        FlashMessage::error("Select at least one scope.").send();
+        return Ok(see_other("/admin/api-tokens"));
+    }
+    if let Some(scope) = scopes.iter().find(|s| role < s.required_role()) {
+        FlashMessage::error(format!("The {role} role cannot grant the {scope} scope.")).send();
+        return Ok(see_other("/admin/api-tokens"));
+    }
+
+    let api_token = create_api_token(**user_id, &name, &scopes, &pool)
+        .await
+        .map_err(e500)?;
+    session
+        .insert_new_api_token(&Secret::new(api_token))
+        .map_err(e500)?;
+    FlashMessage::info(format!("The API token {name} has been created.")).send();
+    Ok(see_other("/admin/api-tokens"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/api-tokens/{api_token_id}/revoke",
+    tag = "admin",
+    params(("api_token_id" = Uuid, Path)),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "The token is revoked: redirects to the API tokens."),
+    )
+)]
+pub async fn revoke_api_token_admin(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // Scoped to the user: the tokens of others cannot be revoked from here.
+    if revoke_api_token(**user_id, path.into_inner(), pool.get_ref())
+        .await
+        .map_err(e500)?
+    {
+        FlashMessage::info("The API token has been revoked.").send();
+    } else {
+        FlashMessage::error("There is no such API token.").send();
+    }
+    Ok(see_other("/admin/api-tokens"))
+}
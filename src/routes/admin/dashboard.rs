@@ -1,19 +1,48 @@
+use std::fmt::Write;
+
 use actix_web::HttpResponse;
 use actix_web::http::header::ContentType;
 use actix_web::web;
+use actix_web_flash_messages::IncomingFlashMessages;
 use anyhow::Context;
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::authentication::UserId;
+use crate::authentication::{CsrfToken, UserId};
+use crate::domain::UserRole;
 use crate::utils::e500;
 
+#[utoipa::path(
+    get,
+    path = "/admin/dashboard",
+    tag = "admin",
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The admin dashboard.", content_type = "text/html"),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+    )
+)]
 pub async fn admin_dashboard(
+    flash_messages: IncomingFlashMessages,
     pool: web::Data<PgPool>,
     user_id: web::ReqData<UserId>,
+    role: web::ReqData<UserRole>,
+    csrf_token: web::ReqData<CsrfToken>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let csrf_token = csrf_token.as_ref();
     let user_id = user_id.into_inner();
+    let role = role.into_inner();
     let username = get_username(*user_id, &pool).await.map_err(e500)?;
+    let owner_actions = if role == UserRole::Owner {
+        r#"<li><a href="/admin/users">Users</a></li>
+        <li><a href="/admin/lockouts">Login lockouts</a></li>"#
+    } else {
+        ""
+    };
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
         .body(format!(
@@ -24,13 +53,34 @@ pub async fn admin_dashboard(
     <title>Admin dashboard</title>
 </head>
 <body>
+    {msg_html}
     <p>Welcome {username}!</p>
+    <p>You are logged in as {role}.</p>
     <p>Available actions:</p>
     <ol>
         <li><a href="/admin/password">Change password</a></li>
+        <li><a href="/admin/email">Change email</a></li>
+        <li><a href="/admin/sessions">Active sessions</a></li>
+        <li><a href="/admin/api-tokens">API tokens</a></li>
+        <li><a href="/admin/two-factor">Two-factor authentication</a></li>
         <li><a href="/admin/newsletters">Send a newsletter issue</a></li>
+        <li><a href="/admin/subscribers">Subscribers</a></li>
+        <li><a href="/admin/subscribers/import">Import subscribers</a></li>
+        <li>
+            Export subscribers as
+            <a href="/admin/subscribers/export?format=csv">CSV</a> or
+            <a href="/admin/subscribers/export?format=json">JSON</a>
+        </li>
+        <li><a href="/admin/subscribers/data">Subscriber data requests</a></li>
+        <li>
+            Export newsletter issues as
+            <a href="/admin/newsletters/export?format=csv">CSV</a> or
+            <a href="/admin/newsletters/export?format=json">JSON</a>
+        </li>
+        {owner_actions}
         <li>
             <form name="logoutForm" action="/admin/logout" method="post">
+                <input type="hidden" name="csrf_token" value="{csrf_token}">
                 <input type="submit" value="Logout">
             </form>
         </li>
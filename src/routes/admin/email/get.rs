@@ -0,0 +1,81 @@
+use std::fmt::Write;
+
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::authentication::{CsrfToken, UserId};
+use crate::utils::e500;
+
+#[utoipa::path(
+    get,
+    path = "/admin/email",
+    tag = "admin",
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The form to change the email.", content_type = "text/html"),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+    )
+)]
+pub async fn change_email_form(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let csrf_token = csrf_token.as_ref();
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        // Errors about the form quote the submitted values
+        let content = htmlescape::encode_minimal(m.content());
+        writeln!(msg_html, "<p><i>{content}</i></p>").unwrap();
+    }
+    let email = sqlx::query_scalar!("SELECT email FROM users WHERE user_id = $1", **user_id)
+        .fetch_one(pool.get_ref())
+        .await
+        .context("Failed to retrieve the email of the user.")
+        .map_err(e500)?;
+    let current_email = match email {
+        Some(email) => format!("Your email is {}.", tera::escape_html(&email)),
+        None => "You have not set an email yet.".to_owned(),
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Change Email</title>
+</head>
+<body>
+    {msg_html}
+    <p>{current_email} It is used to send you a link if you forget your password.</p>
+    <form action="/admin/email" method="post">
+        <input type="hidden" name="csrf_token" value="{csrf_token}">
+        <label>Current password
+            <input
+                type="password"
+                placeholder="Enter current password"
+                name="current_password"
+            >
+        </label>
+        <br>
+        <label>New email
+            <input
+                type="email"
+                placeholder="Enter new email"
+                name="email"
+            >
+        </label>
+        <br>
+        <button type="submit">Change email</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::{__path_change_email_form, change_email_form};
+pub use post::{__path_change_email, change_email};
@@ -0,0 +1,94 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use secrecy::Secret;
+use sqlx::PgPool;
+
+use crate::authentication::{
+    AuthError, Credentials, PasswordHashing, UserId, validate_credentials,
+};
+use crate::domain::SubscriberEmail;
+use crate::routes::get_username;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+#[schema(as = ChangeEmailForm)]
+pub struct FormData {
+    #[schema(value_type = String, format = Password)]
+    current_password: Secret<String>,
+    email: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/email",
+    tag = "admin",
+    request_body(content = FormData, content_type = "application/x-www-form-urlencoded"),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "Redirects back to the form, with the outcome as a flash message."),
+    )
+)]
+pub async fn change_email(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashing>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let FormData {
+        current_password,
+        email,
+    } = form.0;
+    let email = match SubscriberEmail::parse(email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/email"));
+        }
+    };
+
+    // The email is enough to take over the account, so it is
+    // protected by the password like the password itself.
+    let username = get_username(*user_id, &pool).await.map_err(e500)?;
+    let credentials = Credentials {
+        username,
+        password: current_password,
+    };
+    if let Err(e) = validate_credentials(credentials, &hashing, &pool).await {
+        return match e {
+            AuthError::InvalidCredentials(_) => {
+                FlashMessage::error("The current password is incorrect.").send();
+                Ok(see_other("/admin/email"))
+            }
+            AuthError::UnexpectedError(_) => Err(e500(e)),
+        };
+    }
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE users
+        SET email = $1
+        WHERE user_id = $2
+            AND NOT EXISTS (
+                SELECT 1 FROM users WHERE lower(email) = lower($1) AND user_id <> $2
+            )
+        "#,
+        email.as_ref(),
+        *user_id
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to change the email of the user.")
+    .map_err(e500)?;
+    if result.rows_affected() == 0 {
+        FlashMessage::error(format!(
+            "The email {} is already used by another user.",
+            email.as_ref()
+        ))
+        .send();
+    } else {
+        FlashMessage::info("Your email has been changed.").send();
+    }
+    Ok(see_other("/admin/email"))
+}
@@ -0,0 +1,129 @@
+use std::borrow::Cow;
+
+use actix_web::HttpResponse;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::web::Bytes;
+use futures_util::{Stream, StreamExt};
+
+use crate::utils::e500;
+
+#[derive(serde::Deserialize, Clone, Copy, Default, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportParameters {
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+impl ExportParameters {
+    pub fn format(&self) -> ExportFormat {
+        self.format
+    }
+}
+
+/// A row that can be written to every export format.
+///
+/// JSON rows are the `Serialize` representation of the type, while CSV rows
+/// default to it as well: types with fields that CSV cannot represent
+/// (e.g. sequences) must flatten them by overriding `write_csv`.
+pub trait ExportRecord: serde::Serialize {
+    fn write_csv(&self, writer: &mut csv::Writer<Vec<u8>>) -> Result<(), csv::Error> {
+        writer.serialize(self)
+    }
+}
+
+/// A text value as a CSV cell that spreadsheets do not run as a formula:
+/// values starting like one are prefixed with a `'`.
+pub fn csv_text(value: &str) -> Cow<'_, str> {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        Cow::Owned(format!("'{value}"))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Stream the rows as an attachment, serializing each one as soon as it
+/// comes out of the database instead of buffering the whole export in memory.
+pub fn export_response<T, S>(format: ExportFormat, name: &str, rows: S) -> HttpResponse
+where
+    T: ExportRecord,
+    S: Stream<Item = Result<T, anyhow::Error>> + 'static,
+{
+    let body = async_stream::stream! {
+        let mut rows = std::pin::pin!(rows);
+        let mut is_first_row = true;
+        if let ExportFormat::Json = format {
+            yield Ok(Bytes::from_static(b"["));
+        }
+        while let Some(row) = rows.next().await {
+            let chunk = row.and_then(|row| match format {
+                ExportFormat::Csv => {
+                    // The header is derived from the first record only.
+                    let mut writer = csv::WriterBuilder::new()
+                        .has_headers(is_first_row)
+                        .from_writer(vec![]);
+                    row.write_csv(&mut writer)?;
+                    Ok(writer.into_inner()?)
+                }
+                ExportFormat::Json => {
+                    let mut chunk = if is_first_row { vec![] } else { vec![b','] };
+                    serde_json::to_writer(&mut chunk, &row)?;
+                    Ok(chunk)
+                }
+            });
+            match chunk {
+                Ok(chunk) => yield Ok(Bytes::from(chunk)),
+                Err(e) => {
+                    // The status code is already gone, all we can do is to
+                    // abort the transfer so that the export is not mistaken
+                    // for a complete one.
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to stream an export",
+                    );
+                    yield Err(e500(e));
+                    return;
+                }
+            }
+            is_first_row = false;
+        }
+        if let ExportFormat::Json = format {
+            yield Ok(Bytes::from_static(b"]"));
+        }
+    };
+
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "{name}.{}",
+                format.extension()
+            ))],
+        })
+        .streaming(body)
+}
@@ -0,0 +1,26 @@
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use sqlx::PgPool;
+
+use crate::anti_abuse::get_latest_lockouts;
+use crate::html_templates::Templates;
+use crate::utils::e500;
+
+#[utoipa::path(
+    get,
+    path = "/admin/lockouts",
+    tag = "admin",
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The latest login lockouts.", content_type = "text/html"),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+        (status = 403, description = "Only owners are allowed."),
+    )
+)]
+pub async fn login_lockouts(pool: web::Data<PgPool>) -> Result<HttpResponse, actix_web::Error> {
+    let lockouts = get_latest_lockouts(&pool, 100).await.map_err(e500)?;
+    let html_body = Templates::render_lockouts(&lockouts).map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
@@ -1,10 +1,29 @@
-use actix_web::HttpResponse;
+use actix_web::{HttpResponse, web};
 use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
 
+use crate::authentication::{SessionId, UserId, revoke_session};
 use crate::session_state::TypedSession;
-use crate::utils::see_other;
+use crate::utils::{e500, see_other};
 
-pub async fn log_out(session: TypedSession) -> Result<HttpResponse, actix_web::Error> {
+#[utoipa::path(
+    post,
+    path = "/admin/logout",
+    tag = "admin",
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "Logged out: redirects to the login form."),
+    )
+)]
+pub async fn log_out(
+    session: TypedSession,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session_id: web::ReqData<SessionId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    revoke_session(**user_id, **session_id, pool.get_ref())
+        .await
+        .map_err(e500)?;
     session.log_out();
     FlashMessage::info("You have successfully logged out.").send();
     Ok(see_other("/login"))
@@ -1,9 +1,24 @@
+mod api_tokens;
 mod dashboard;
+mod email;
+mod export;
+mod lockouts;
 mod logout;
 mod newsletters;
 mod password;
+mod sessions;
+mod subscribers;
+mod two_factor;
+mod users;
 
+pub use api_tokens::*;
 pub use dashboard::*;
+pub use email::*;
+pub use lockouts::*;
 pub use logout::*;
 pub use newsletters::*;
 pub use password::*;
+pub use sessions::*;
+pub use subscribers::*;
+pub use two_factor::*;
+pub use users::*;
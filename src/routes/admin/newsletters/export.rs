@@ -0,0 +1,93 @@
+use std::borrow::Cow;
+
+use actix_web::{HttpResponse, web};
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::routes::admin::export::{ExportParameters, ExportRecord, csv_text, export_response};
+
+#[derive(serde::Serialize)]
+struct NewsletterIssueRecord {
+    newsletter_issue_id: Uuid,
+    title: String,
+    published_at: DateTime<Utc>,
+    recipients_count: i32,
+    delivered_count: i32,
+    failed_count: i32,
+    dropped_count: i32,
+    pending_count: i64,
+}
+
+#[derive(serde::Serialize)]
+struct NewsletterIssueCsvRecord<'a> {
+    newsletter_issue_id: Uuid,
+    title: Cow<'a, str>,
+    published_at: DateTime<Utc>,
+    recipients_count: i32,
+    delivered_count: i32,
+    failed_count: i32,
+    dropped_count: i32,
+    pending_count: i64,
+}
+
+impl ExportRecord for NewsletterIssueRecord {
+    fn write_csv(&self, writer: &mut csv::Writer<Vec<u8>>) -> Result<(), csv::Error> {
+        writer.serialize(NewsletterIssueCsvRecord {
+            newsletter_issue_id: self.newsletter_issue_id,
+            title: csv_text(&self.title),
+            published_at: self.published_at,
+            recipients_count: self.recipients_count,
+            delivered_count: self.delivered_count,
+            failed_count: self.failed_count,
+            dropped_count: self.dropped_count,
+            pending_count: self.pending_count,
+        })
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/newsletters/export",
+    tag = "admin",
+    params(ExportParameters),
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The newsletter issues, as a CSV or JSON attachment."),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+    )
+)]
+#[tracing::instrument(name = "Export newsletter issues", skip_all)]
+pub async fn export_newsletter_issues(
+    parameters: web::Query<ExportParameters>,
+    pool: web::Data<PgPool>,
+) -> HttpResponse {
+    let rows = async_stream::try_stream! {
+        let mut rows = sqlx::query_as!(
+            NewsletterIssueRecord,
+            r#"
+            SELECT
+                newsletter_issue_id,
+                title,
+                published_at,
+                recipients_count,
+                delivered_count,
+                failed_count,
+                dropped_count,
+                (
+                    SELECT count(*)
+                    FROM issue_delivery_queue q
+                    WHERE q.newsletter_issue_id = i.newsletter_issue_id
+                ) as "pending_count!"
+            FROM newsletter_issues i
+            ORDER BY published_at
+            "#
+        )
+        .fetch(pool.get_ref());
+        while let Some(row) = rows.try_next().await? {
+            yield row;
+        }
+    };
+    export_response(parameters.format(), "newsletter_issues", rows)
+}
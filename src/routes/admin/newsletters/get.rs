@@ -1,23 +1,38 @@
 use std::fmt::Write;
 
-use actix_web::HttpResponse;
 use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
 use actix_web_flash_messages::IncomingFlashMessages;
 
+use crate::authentication::CsrfToken;
 use crate::html_templates::Templates;
 use crate::utils::e500;
 
+#[utoipa::path(
+    get,
+    path = "/admin/newsletters",
+    tag = "admin",
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The form to publish a newsletter issue.", content_type = "text/html"),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+    )
+)]
 pub async fn publish_newsletter_form(
     flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let mut messages = String::new();
     for m in flash_messages.iter() {
-        writeln!(messages, "<p><i>{}</i></p>", m.content()).unwrap();
+        // Warnings about the content of an issue quote it
+        let content = htmlescape::encode_minimal(m.content());
+        writeln!(messages, "<p><i>{content}</i></p>").unwrap();
     }
 
     let idempotency_key = uuid::Uuid::new_v4();
     let html_body =
-        Templates::render_publish_newsletter(&messages, idempotency_key).map_err(e500)?;
+        Templates::render_publish_newsletter(&messages, idempotency_key, csrf_token.as_ref())
+            .map_err(e500)?;
     Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
         .body(html_body))
@@ -1,5 +1,7 @@
+mod export;
 mod get;
 mod post;
 
+pub use export::*;
 pub use get::*;
 pub use post::*;
@@ -1,3 +1,6 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
 use actix_web::{HttpResponse, error::UrlencodedError, web};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
@@ -7,18 +10,44 @@ use uuid::Uuid;
 
 use crate::{
     authentication::UserId,
-    idempotency::{IdempotencyKey, NextAction, save_response, try_processing},
-    utils::{e400, e500, see_other},
+    domain::{IssueHtml, IssueSlug},
+    html_templates::Templates,
+    idempotency::{IdempotentTransaction, idempotent_with},
+    markdown,
+    utils::{e500, see_other},
 };
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, utoipa::ToSchema)]
 pub struct NewsletterData {
     title: String,
-    html_content: String,
-    text_content: String,
-    idempotency_key: String,
+    /// Required without `markdown_content`. The rules of its `<style>` blocks
+    /// are inlined, and it is refused if it contains scripts or event handlers.
+    html_content: Option<String>,
+    /// Required without `markdown_content`.
+    text_content: Option<String>,
+    /// Written in Markdown, the HTML and plain text are generated from it.
+    markdown_content: Option<String>,
+    /// A checkbox: present to show the issue in the public archive.
+    public: Option<String>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/admin/newsletters",
+    tag = "admin",
+    request_body(
+        content = NewsletterData,
+        content_type = "application/x-www-form-urlencoded",
+        description = "Along with an `idempotency_key` field: resubmitting the form with the same key does not publish the issue twice."
+    ),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "The issue is queued for delivery. Redirects back to the form, with the outcome as a flash message."),
+        (status = 403, description = "Only editors and owners are allowed."),
+        (status = 409, description = "Another request with the same idempotency key is still in progress."),
+        (status = 422, description = "The idempotency key was already used with a different issue."),
+    )
+)]
 #[tracing::instrument(
     name = "Publish a newsletter issue",
     skip_all,
@@ -28,13 +57,14 @@ pub async fn publish_newsletter(
     form: Result<web::Form<NewsletterData>, actix_web::Error>,
     pool: web::Data<PgPool>,
     user_id: web::ReqData<UserId>,
+    mut transaction: IdempotentTransaction,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = user_id.into_inner();
     let NewsletterData {
         title,
         text_content,
         html_content,
-        idempotency_key,
+        markdown_content,
+        public,
     } = match form {
         Ok(form) => form.0,
         Err(error) => {
@@ -52,20 +82,30 @@ pub async fn publish_newsletter(
         return Ok(see_other("/admin/newsletters"));
     }
 
+    // The form always sends the field, empty unless it is used
+    let markdown_content = markdown_content.filter(|markdown| !markdown.trim().is_empty());
+    let (html_content, text_content) = match (&markdown_content, html_content, text_content) {
+        (Some(markdown), _, _) => render_markdown_issue(&title, markdown).map_err(e500)?,
+        (None, Some(html_content), Some(text_content)) => (html_content, text_content),
+        _ => {
+            FlashMessage::error("The form fields are incorrect, incomplete or badly formatted.")
+                .send();
+            return Ok(see_other("/admin/newsletters"));
+        }
+    };
+
     if html_content.is_empty() || text_content.trim().is_empty() {
         FlashMessage::error("The content cannot be empty.").send();
         return Ok(see_other("/admin/newsletters"));
     }
 
-    let idempotency_key: IdempotencyKey = idempotency_key.try_into().map_err(e400)?;
-    let mut transaction = match try_processing(&pool, &idempotency_key, *user_id)
-        .await
-        .map_err(e500)?
-    {
-        NextAction::StartProcessing(t) => t,
-        NextAction::ReturnSavedResponse(saved_response) => {
-            success_message().send();
-            return Ok(saved_response);
+    let html_content = match IssueHtml::parse(&html_content) {
+        Ok(html_content) => html_content,
+        Err(warnings) => {
+            for warning in warnings {
+                FlashMessage::warning(warning).send();
+            }
+            return Ok(see_other("/admin/newsletters"));
         }
     };
 
@@ -76,20 +116,32 @@ pub async fn publish_newsletter(
         return Ok(see_other("/admin/newsletters"));
     }
 
-    let issue_id = insert_newsletter_issue(&mut transaction, &title, &text_content, &html_content)
-        .await
-        .context("Failed to store newsletter issue details")
-        .map_err(e500)?;
+    let issue_id = insert_newsletter_issue(
+        &mut transaction,
+        &title,
+        &text_content,
+        html_content.as_ref(),
+        markdown_content.as_deref(),
+        public.is_some(),
+    )
+    .await
+    .context("Failed to store newsletter issue details")
+    .map_err(e500)?;
     enqueue_delivery_tasks(&mut transaction, issue_id)
         .await
         .context("Failed to enqueue delivery tasks")
         .map_err(e500)?;
-    let response = see_other("/admin/newsletters");
-    let response = save_response(transaction, &idempotency_key, *user_id, response)
-        .await
-        .map_err(e500)?;
+    transaction.complete();
     success_message().send();
-    Ok(response)
+    Ok(see_other("/admin/newsletters"))
+}
+
+/// `idempotent`, sending the success message again along with a replayed response.
+pub async fn idempotent_publish_newsletter(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    idempotent_with(req, next, || success_message().send()).await
 }
 
 fn success_message() -> FlashMessage {
@@ -99,8 +151,17 @@ fn success_message() -> FlashMessage {
     )
 }
 
+/// The HTML and plain text of an issue written in Markdown.
+pub fn render_markdown_issue(
+    title: &str,
+    markdown_content: &str,
+) -> Result<(String, String), anyhow::Error> {
+    let html_content = Templates::render_issue_email(title, &markdown::to_html(markdown_content))?;
+    Ok((html_content, markdown::to_text(markdown_content)))
+}
+
 #[tracing::instrument(name = "Check confirmed subscribers", skip(pool))]
-async fn check_confirmed_subscribers(pool: &PgPool) -> Result<bool, anyhow::Error> {
+pub async fn check_confirmed_subscribers(pool: &PgPool) -> Result<bool, anyhow::Error> {
     let confirmed_subscribers_check = sqlx::query!(
         r#"
         SELECT count(id) as "count!"
@@ -115,14 +176,30 @@ async fn check_confirmed_subscribers(pool: &PgPool) -> Result<bool, anyhow::Erro
     Ok(confirmed_subscribers_check.count > 0)
 }
 
+/// Public issues are listed in the web archive, under a slug of their title.
+/// The Markdown source is kept for issues written in Markdown.
 #[tracing::instrument(skip_all)]
-async fn insert_newsletter_issue(
+pub async fn insert_newsletter_issue(
     transaction: &mut Transaction<'_, Postgres>,
     title: &str,
     text_content: &str,
     html_content: &str,
+    markdown_content: Option<&str>,
+    public: bool,
 ) -> Result<Uuid, sqlx::Error> {
     let newsletter_issue_id = Uuid::new_v4();
+    let slug = IssueSlug::from_title(title);
+    let taken_slugs = sqlx::query_scalar!(
+        r#"
+        SELECT slug
+        FROM newsletter_issues
+        WHERE slug = $1 OR slug LIKE $1 || '-%'
+        "#,
+        slug.as_ref()
+    )
+    .fetch_all(&mut **transaction)
+    .await?;
+    let slug = slug.first_free(&taken_slugs);
     let query = sqlx::query!(
         r#"
         INSERT INTO newsletter_issues (
@@ -130,21 +207,27 @@ async fn insert_newsletter_issue(
             title,
             text_content,
             html_content,
-            published_at
+            markdown_content,
+            published_at,
+            slug,
+            public
         )
-        VALUES ($1, $2, $3, $4, now())
+        VALUES ($1, $2, $3, $4, $5, now(), $6, $7)
         "#,
         newsletter_issue_id,
         title,
         text_content,
-        html_content
+        html_content,
+        markdown_content,
+        slug.as_ref(),
+        public
     );
     transaction.execute(query).await?;
     Ok(newsletter_issue_id)
 }
 
 #[tracing::instrument(skip_all)]
-async fn enqueue_delivery_tasks(
+pub async fn enqueue_delivery_tasks(
     transaction: &mut Transaction<'_, Postgres>,
     newsletter_issue_id: Uuid,
 ) -> Result<(), sqlx::Error> {
@@ -160,6 +243,16 @@ async fn enqueue_delivery_tasks(
         "#,
         newsletter_issue_id,
     );
+    let n_enqueued_tasks = transaction.execute(query).await?.rows_affected();
+    let query = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET recipients_count = $2
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+        n_enqueued_tasks as i32
+    );
     transaction.execute(query).await?;
     Ok(())
 }
@@ -1,12 +1,26 @@
 use std::fmt::Write;
 
-use actix_web::HttpResponse;
 use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
 use actix_web_flash_messages::IncomingFlashMessages;
 
+use crate::authentication::CsrfToken;
+
+#[utoipa::path(
+    get,
+    path = "/admin/password",
+    tag = "admin",
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The form to change the password.", content_type = "text/html"),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+    )
+)]
 pub async fn change_password_form(
     flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    let csrf_token = csrf_token.as_ref();
     let mut msg_html = String::new();
     for m in flash_messages.iter() {
         writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
@@ -24,6 +38,7 @@ pub async fn change_password_form(
 <body>
     {msg_html}
     <form action="/admin/password" method="post">
+        <input type="hidden" name="csrf_token" value="{csrf_token}">
         <label>Current password
             <input
                 type="password"
@@ -4,22 +4,42 @@ use secrecy::ExposeSecret;
 use secrecy::Secret;
 use sqlx::PgPool;
 
-use crate::authentication::UserId;
-use crate::authentication::{AuthError, Credentials, validate_credentials};
+use crate::authentication::{
+    AuthError, Credentials, PasswordHashing, PasswordPolicy, PasswordPolicyError, revoke_sessions,
+    validate_credentials,
+};
+use crate::authentication::{SessionId, UserId};
 use crate::routes::admin::dashboard::get_username;
 use crate::utils::{e500, see_other};
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+#[schema(as = ChangePasswordForm)]
 pub struct FormData {
+    #[schema(value_type = String, format = Password)]
     current_password: Secret<String>,
+    #[schema(value_type = String, format = Password)]
     new_password: Secret<String>,
+    #[schema(value_type = String, format = Password)]
     new_password_check: Secret<String>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/admin/password",
+    tag = "admin",
+    request_body(content = FormData, content_type = "application/x-www-form-urlencoded"),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "The password is changed and the other sessions are logged out. Redirects back to the form, with the outcome as a flash message."),
+    )
+)]
 pub async fn change_password(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashing>,
+    policy: web::Data<PasswordPolicy>,
     user_id: web::ReqData<UserId>,
+    session_id: web::ReqData<SessionId>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = user_id.into_inner();
 
@@ -31,23 +51,21 @@ pub async fn change_password(
         return Ok(see_other("/admin/password"));
     }
 
-    // NOTE: This really should be measuring characters and not bytes
-    // but it's ok for the time being
-    let new_password_len = form.new_password.expose_secret().len();
-    if !(12..=128).contains(&new_password_len) {
-        FlashMessage::error(
-            "The new password's length is invalid - It has to be in the range of 12 to 128 characters",
-        )
-        .send();
-        return Ok(see_other("/admin/password"));
+    let username = get_username(*user_id, &pool).await.map_err(e500)?;
+    match policy.check(&form.new_password, &username) {
+        Ok(()) => {}
+        Err(PasswordPolicyError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other("/admin/password"));
+        }
     }
 
-    let username = get_username(*user_id, &pool).await.map_err(e500)?;
     let credentials = Credentials {
         username,
         password: form.0.current_password,
     };
-    if let Err(e) = validate_credentials(credentials, &pool).await {
+    if let Err(e) = validate_credentials(credentials, &hashing, &pool).await {
         return match e {
             AuthError::InvalidCredentials(_) => {
                 FlashMessage::error("The current password is incorrect.").send();
@@ -56,7 +74,11 @@ pub async fn change_password(
             AuthError::UnexpectedError(_) => Err(e500(e)),
         };
     }
-    crate::authentication::change_password(*user_id, form.0.new_password, &pool)
+    crate::authentication::change_password(*user_id, form.0.new_password, &hashing, pool.get_ref())
+        .await
+        .map_err(e500)?;
+    // Whoever knew the old password cannot keep using a session started with it.
+    revoke_sessions(*user_id, Some(**session_id), pool.get_ref())
         .await
         .map_err(e500)?;
 
@@ -0,0 +1,45 @@
+use std::fmt::Write;
+
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+
+use crate::authentication::{CsrfToken, SessionId, UserId, get_user_sessions};
+use crate::configuration::SessionSettings;
+use crate::html_templates::Templates;
+use crate::utils::e500;
+
+#[utoipa::path(
+    get,
+    path = "/admin/sessions",
+    tag = "admin",
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The active sessions of the user.", content_type = "text/html"),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+    )
+)]
+pub async fn list_sessions(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session_id: web::ReqData<SessionId>,
+    settings: web::Data<SessionSettings>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    let sessions = get_user_sessions(**user_id, &settings, &pool)
+        .await
+        .map_err(e500)?;
+
+    let html_body =
+        Templates::render_sessions(&msg_html, &sessions, **session_id, csrf_token.as_ref())
+            .map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
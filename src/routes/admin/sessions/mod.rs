@@ -0,0 +1,7 @@
+mod get;
+mod post;
+
+pub use get::{__path_list_sessions, list_sessions};
+pub use post::{
+    __path_log_out_other_sessions, __path_log_out_session, log_out_other_sessions, log_out_session,
+};
@@ -0,0 +1,57 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{SessionId, UserId, revoke_session, revoke_sessions};
+use crate::utils::{e500, see_other};
+
+#[utoipa::path(
+    post,
+    path = "/admin/sessions/logout-others",
+    tag = "admin",
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "The other sessions are logged out: redirects to the sessions."),
+    )
+)]
+pub async fn log_out_other_sessions(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session_id: web::ReqData<SessionId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    revoke_sessions(**user_id, Some(**session_id), pool.get_ref())
+        .await
+        .map_err(e500)?;
+    FlashMessage::info("You have been logged out of all your other sessions.").send();
+    Ok(see_other("/admin/sessions"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/sessions/{session_id}/logout",
+    tag = "admin",
+    params(("session_id" = String, Path, description = "The identifier of one of the user's sessions.")),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "The session is logged out: redirects to the sessions."),
+    )
+)]
+pub async fn log_out_session(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session_id: web::ReqData<SessionId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let target_session_id = path.into_inner();
+    if target_session_id == **session_id {
+        FlashMessage::error("Use the logout button to end the current session.").send();
+        return Ok(see_other("/admin/sessions"));
+    }
+    // Scoped to the user: the sessions of others cannot be revoked from here.
+    revoke_session(**user_id, target_session_id, pool.get_ref())
+        .await
+        .map_err(e500)?;
+    FlashMessage::info("The session has been logged out.").send();
+    Ok(see_other("/admin/sessions"))
+}
@@ -0,0 +1,111 @@
+use std::fmt::Write;
+
+use actix_web::http::header::{ContentDisposition, ContentType, DispositionParam, DispositionType};
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sqlx::PgPool;
+
+use crate::authentication::CsrfToken;
+use crate::subscriber_data::get_subscriber_data;
+use crate::utils::{e500, see_other};
+
+#[utoipa::path(
+    get,
+    path = "/admin/subscribers/data",
+    tag = "admin",
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The form to export or erase the data of a subscriber.", content_type = "text/html"),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+    )
+)]
+pub async fn subscriber_data_form(
+    flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let csrf_token = csrf_token.as_ref();
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Subscriber data requests</title>
+</head>
+<body>
+    {msg_html}
+    <h2>Access request</h2>
+    <form action="/admin/subscribers/data/export" method="get">
+        <label>Email
+            <input
+                type="email"
+                placeholder="Enter the subscriber's email"
+                name="email"
+            >
+        </label>
+        <button type="submit">Download data</button>
+    </form>
+    <h2>Erasure request</h2>
+    <p>This permanently deletes the subscriber and cannot be undone.</p>
+    <form action="/admin/subscribers/data/erase" method="post">
+        <input type="hidden" name="csrf_token" value="{csrf_token}">
+        <label>Email
+            <input
+                type="email"
+                placeholder="Enter the subscriber's email"
+                name="email"
+            >
+        </label>
+        <button type="submit">Erase data</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        )))
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EmailParameters {
+    email: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/subscribers/data/export",
+    tag = "admin",
+    params(EmailParameters),
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "All the data held about the subscriber, as a JSON attachment.", content_type = "application/json"),
+        (status = 303, description = "There is no such subscriber: redirects to the form."),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+    )
+)]
+#[tracing::instrument(name = "Export subscriber data", skip_all)]
+pub async fn export_subscriber_data(
+    parameters: web::Query<EmailParameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    match get_subscriber_data(&pool, &parameters.email)
+        .await
+        .map_err(e500)?
+    {
+        Some(bundle) => Ok(HttpResponse::Ok()
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename("subscriber_data.json".into())],
+            })
+            .json(bundle)),
+        None => {
+            FlashMessage::error("There is no subscriber with the given email.").send();
+            Ok(see_other("/admin/subscribers/data"))
+        }
+    }
+}
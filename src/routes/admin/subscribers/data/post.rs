@@ -0,0 +1,35 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+
+use crate::subscriber_data::erase_subscriber;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct EraseData {
+    email: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/subscribers/data/erase",
+    tag = "admin",
+    request_body(content = EraseData, content_type = "application/x-www-form-urlencoded"),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "Redirects back to the form, with the outcome as a flash message."),
+        (status = 403, description = "Only editors and owners are allowed."),
+    )
+)]
+#[tracing::instrument(name = "Erase subscriber data", skip_all)]
+pub async fn erase_subscriber_data_admin(
+    form: web::Form<EraseData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if erase_subscriber(&pool, &form.email).await.map_err(e500)? {
+        FlashMessage::info("The subscriber's data has been erased.").send();
+    } else {
+        FlashMessage::error("There is no subscriber with the given email.").send();
+    }
+    Ok(see_other("/admin/subscribers/data"))
+}
@@ -0,0 +1,124 @@
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::consent::get_consent_events;
+use crate::html_templates::Templates;
+use crate::subscriber_data::SubscriptionRecord;
+use crate::utils::e500;
+
+const PAGE_SIZE: i64 = 50;
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParameters {
+    pub page: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/subscribers",
+    tag = "admin",
+    params(PageParameters),
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "A page of subscribers.", content_type = "text/html"),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+    )
+)]
+pub async fn list_subscribers(
+    parameters: web::Query<PageParameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let page = parameters.page.unwrap_or(1).max(1);
+    let (subscribers, has_next_page) = get_subscribers_page(&pool, page).await.map_err(e500)?;
+
+    let html_body =
+        Templates::render_subscribers(&subscribers, page, has_next_page).map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/subscribers/{subscriber_id}",
+    tag = "admin",
+    params(("subscriber_id" = Uuid, Path)),
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The subscriber and their consent history.", content_type = "text/html"),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+        (status = 404, description = "There is no such subscriber."),
+    )
+)]
+pub async fn subscriber_details(
+    subscriber_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let subscriber_id = subscriber_id.into_inner();
+    let Some(subscriber) = get_subscriber(&pool, subscriber_id).await.map_err(e500)? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let consent_events = get_consent_events(&pool, subscriber_id)
+        .await
+        .context("Failed to fetch the consent events.")
+        .map_err(e500)?;
+
+    let html_body = Templates::render_subscriber(&subscriber, &consent_events).map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
+
+/// The subscribers of the page, and whether there is a next page.
+#[tracing::instrument(name = "Get subscribers page", skip(pool))]
+pub async fn get_subscribers_page(
+    pool: &PgPool,
+    page: i64,
+) -> Result<(Vec<SubscriptionRecord>, bool), anyhow::Error> {
+    // Pages too far to even compute the offset of are past the last one
+    let Some(offset) = (page - 1).checked_mul(PAGE_SIZE) else {
+        return Ok((Vec::new(), false));
+    };
+    let mut subscribers = sqlx::query_as!(
+        SubscriptionRecord,
+        r#"
+        SELECT id, email, name, status, subscribed_at, tags
+        FROM subscriptions
+        ORDER BY subscribed_at DESC, email
+        LIMIT $1 OFFSET $2
+        "#,
+        PAGE_SIZE + 1,
+        offset
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch subscribers.")?;
+    // We fetch one extra row to know if there is a next page
+    let has_next_page = subscribers.len() as i64 > PAGE_SIZE;
+    subscribers.truncate(PAGE_SIZE as usize);
+    Ok((subscribers, has_next_page))
+}
+
+#[tracing::instrument(name = "Get subscriber", skip(pool))]
+async fn get_subscriber(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Option<SubscriptionRecord>, anyhow::Error> {
+    let subscriber = sqlx::query_as!(
+        SubscriptionRecord,
+        r#"
+        SELECT id, email, name, status, subscribed_at, tags
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        subscriber_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the subscriber.")?;
+    Ok(subscriber)
+}
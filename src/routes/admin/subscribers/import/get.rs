@@ -0,0 +1,37 @@
+use std::fmt::Write;
+
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+
+use crate::authentication::CsrfToken;
+use crate::html_templates::Templates;
+use crate::utils::e500;
+
+#[utoipa::path(
+    get,
+    path = "/admin/subscribers/import",
+    tag = "admin",
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The form to import subscribers from a CSV.", content_type = "text/html"),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+    )
+)]
+pub async fn import_subscribers_form(
+    flash_messages: IncomingFlashMessages,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut messages = String::new();
+    for m in flash_messages.iter() {
+        // Errors about the form quote the submitted values
+        let content = htmlescape::encode_minimal(m.content());
+        writeln!(messages, "<p><i>{content}</i></p>").unwrap();
+    }
+
+    let html_body =
+        Templates::render_import_subscribers(&messages, csrf_token.as_ref()).map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
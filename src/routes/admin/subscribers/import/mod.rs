@@ -0,0 +1,6 @@
+mod get;
+mod parse;
+mod post;
+
+pub use get::*;
+pub use post::*;
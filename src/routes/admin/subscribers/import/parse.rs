@@ -0,0 +1,187 @@
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriberStatus};
+
+#[derive(Debug)]
+pub struct ImportRow {
+    pub line: u64,
+    pub subscriber: NewSubscriber,
+    pub status: Option<SubscriberStatus>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedCsv {
+    pub rows: Vec<ImportRow>,
+    pub errors: Vec<RowError>,
+}
+
+struct Columns {
+    email: usize,
+    name: usize,
+    status: Option<usize>,
+    tags: Option<usize>,
+}
+
+impl Columns {
+    fn from_headers(headers: &csv::StringRecord) -> Result<Self, String> {
+        let position = |column: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(column))
+        };
+        let email = position("email").ok_or("The CSV header is missing an `email` column.")?;
+        let name = position("name").ok_or("The CSV header is missing a `name` column.")?;
+
+        Ok(Self {
+            email,
+            name,
+            status: position("status"),
+            tags: position("tags"),
+        })
+    }
+}
+
+/// Parse an `email,name[,status][,tags]` CSV document into subscribers.
+///
+/// The header row is mandatory, columns can appear in any order and
+/// `tags` is a `;`-separated list.
+/// Invalid rows are collected as errors instead of aborting the whole import,
+/// an error is only returned if the document as a whole is unusable.
+pub fn parse_csv(content: &str) -> Result<ParsedCsv, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Failed to read the CSV header: {e}"))?;
+    let columns = Columns::from_headers(headers)?;
+
+    let mut parsed = ParsedCsv::default();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map(|p| p.line()).unwrap_or_default();
+                parsed.errors.push(RowError {
+                    line,
+                    message: format!("Failed to read the row: {e}"),
+                });
+                continue;
+            }
+        };
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        match parse_row(&record, &columns) {
+            Ok((subscriber, status, tags)) => parsed.rows.push(ImportRow {
+                line,
+                subscriber,
+                status,
+                tags,
+            }),
+            Err(message) => parsed.errors.push(RowError { line, message }),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_row(
+    record: &csv::StringRecord,
+    columns: &Columns,
+) -> Result<(NewSubscriber, Option<SubscriberStatus>, Vec<String>), String> {
+    let field = |index: Option<usize>| index.and_then(|i| record.get(i)).unwrap_or_default();
+
+    let email = SubscriberEmail::parse(field(Some(columns.email)).to_string())?;
+    let name = SubscriberName::parse(field(Some(columns.name)).to_string())?;
+    let status = match field(columns.status) {
+        "" => None,
+        status => Some(SubscriberStatus::parse(status)?),
+    };
+    let tags = field(columns.tags)
+        .split(';')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(String::from)
+        .collect();
+
+    Ok((NewSubscriber { email, name }, status, tags))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_csv;
+    use crate::domain::SubscriberStatus;
+    use claims::{assert_err, assert_ok};
+
+    #[test]
+    fn a_header_without_email_is_rejected() {
+        assert_err!(parse_csv("name,status\nUrsula,confirmed\n"));
+    }
+
+    #[test]
+    fn a_header_without_name_is_rejected() {
+        assert_err!(parse_csv("email\nursula@example.com\n"));
+    }
+
+    #[test]
+    fn valid_rows_are_parsed_successfully() {
+        let content = "email,name\n\
+            ursula@example.com,Ursula Le Guin\n\
+            octavia@example.com,Octavia Butler\n";
+
+        let parsed = assert_ok!(parse_csv(content));
+
+        assert_eq!(parsed.rows.len(), 2);
+        assert!(parsed.errors.is_empty());
+        assert_eq!(
+            parsed.rows[0].subscriber.email.as_ref(),
+            "ursula@example.com"
+        );
+        assert_eq!(parsed.rows[1].subscriber.name.as_ref(), "Octavia Butler");
+        assert_eq!(parsed.rows[0].status, None);
+    }
+
+    #[test]
+    fn columns_can_appear_in_any_order_and_case() {
+        let content = "Tags, Status ,NAME,Email\n\
+            beta; early-adopter ,Confirmed,Ursula Le Guin,ursula@example.com\n";
+
+        let parsed = assert_ok!(parse_csv(content));
+
+        let row = &parsed.rows[0];
+        assert_eq!(row.subscriber.email.as_ref(), "ursula@example.com");
+        assert_eq!(row.status, Some(SubscriberStatus::Confirmed));
+        assert_eq!(row.tags, vec!["beta", "early-adopter"]);
+    }
+
+    #[test]
+    fn invalid_rows_are_reported_with_their_line_number() {
+        let content = "email,name,status\n\
+            ursula@example.com,Ursula Le Guin,\n\
+            not-an-email,Octavia Butler,\n\
+            ted@example.com,,\n\
+            iain@example.com,Iain Banks,unsubscribed\n";
+
+        let parsed = assert_ok!(parse_csv(content));
+
+        assert_eq!(parsed.rows.len(), 1);
+        let lines: Vec<u64> = parsed.errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn short_rows_are_reported_instead_of_aborting_the_import() {
+        let content = "email,name\nursula@example.com\noctavia@example.com,Octavia Butler\n";
+
+        let parsed = assert_ok!(parse_csv(content));
+
+        assert_eq!(parsed.rows.len(), 1);
+        assert_eq!(parsed.errors.len(), 1);
+        assert_eq!(parsed.errors[0].line, 2);
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashSet;
+
+use actix_web::http::header::ContentType;
+use actix_web::{HttpRequest, HttpResponse, error::UrlencodedError, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::parse::{ImportRow, RowError, parse_csv};
+use crate::consent::{ConsentEventType, ConsentEvidence, record_consent_event};
+use crate::domain::{NewSubscriber, SubscriberStatus, SubscriptionToken};
+use crate::html_templates::Templates;
+use crate::routes::store_token;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct ImportData {
+    csv_content: String,
+    default_status: String,
+}
+
+#[derive(Default, serde::Serialize)]
+pub struct ImportReport {
+    imported: usize,
+    confirmation_emails: usize,
+    skipped: Vec<RowError>,
+    errors: Vec<RowError>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/subscribers/import",
+    tag = "admin",
+    request_body(content = ImportData, content_type = "application/x-www-form-urlencoded"),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 200, description = "The import report.", content_type = "text/html"),
+        (status = 303, description = "The form is invalid. Redirects back to the form, with the outcome as a flash message."),
+        (status = 403, description = "Only editors and owners are allowed."),
+    )
+)]
+#[tracing::instrument(
+    name = "Import subscribers",
+    skip_all,
+    fields(imported=tracing::field::Empty, failed=tracing::field::Empty)
+)]
+pub async fn import_subscribers(
+    form: Result<web::Form<ImportData>, actix_web::Error>,
+    pool: web::Data<PgPool>,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let ImportData {
+        csv_content,
+        default_status,
+    } = match form {
+        Ok(form) => form.0,
+        Err(error) => {
+            let message = match error.as_error::<UrlencodedError>() {
+                Some(_) => "The form fields are incorrect, incomplete or badly formatted.",
+                _ => "Something unexpected happened, please report it to the web admin.",
+            };
+            FlashMessage::error(message).send();
+            return Ok(see_other("/admin/subscribers/import"));
+        }
+    };
+
+    if csv_content.trim().is_empty() {
+        FlashMessage::error("The CSV content cannot be empty.").send();
+        return Ok(see_other("/admin/subscribers/import"));
+    }
+
+    let default_status = match SubscriberStatus::parse(&default_status) {
+        Ok(status) => status,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/subscribers/import"));
+        }
+    };
+
+    let parsed = match parse_csv(&csv_content) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/subscribers/import"));
+        }
+    };
+
+    let mut report = ImportReport {
+        errors: parsed.errors,
+        ..Default::default()
+    };
+    let rows = remove_duplicates(&pool, parsed.rows, &mut report.skipped)
+        .await
+        .map_err(e500)?;
+
+    // Consent was collected by the previous provider: we record who
+    // imported the subscriber and from where.
+    let evidence = ConsentEvidence::from_request(&request, "csv_import");
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    for row in rows {
+        let status = row.status.unwrap_or(default_status);
+        let subscriber_id =
+            insert_imported_subscriber(&mut transaction, &row.subscriber, status, &row.tags)
+                .await
+                .context("Failed to insert an imported subscriber in the database")
+                .map_err(e500)?;
+        let Some(subscriber_id) = subscriber_id else {
+            // Somebody subscribed with the same email while we were importing
+            report.skipped.push(duplicate(&row));
+            continue;
+        };
+        record_consent_event(
+            &mut *transaction,
+            subscriber_id,
+            ConsentEventType::Imported,
+            &evidence,
+            None,
+        )
+        .await
+        .context("Failed to record the consent of an imported subscriber")
+        .map_err(e500)?;
+        report.imported += 1;
+        if status == SubscriberStatus::PendingConfirmation {
+            let subscription_token = SubscriptionToken::new();
+            store_token(&mut transaction, subscriber_id, subscription_token.as_ref())
+                .await
+                .context("Failed to store the confirmation token for an imported subscriber")
+                .map_err(e500)?;
+            enqueue_confirmation_email(&mut transaction, subscription_token.as_ref())
+                .await
+                .context("Failed to enqueue the confirmation email of an imported subscriber")
+                .map_err(e500)?;
+            report.confirmation_emails += 1;
+        }
+    }
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store imported subscribers")
+        .map_err(e500)?;
+
+    report.errors.sort_by_key(|e| e.line);
+
+    tracing::Span::current()
+        .record("imported", report.imported)
+        .record("failed", report.errors.len());
+    let html_body = Templates::render_import_subscribers_report(&report).map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
+
+// The emails are sent by the background worker: an import of thousands
+// of subscribers would outlast the request otherwise.
+#[tracing::instrument(name = "Enqueue a confirmation email", skip_all)]
+async fn enqueue_confirmation_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscription_token: &str,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        "INSERT INTO confirmation_email_queue (subscription_token) VALUES ($1)",
+        subscription_token
+    );
+    transaction.execute(query).await?;
+    Ok(())
+}
+
+fn duplicate(row: &ImportRow) -> RowError {
+    RowError {
+        line: row.line,
+        message: format!("{} is already subscribed.", row.subscriber.email),
+    }
+}
+
+// Drop the rows whose email appears earlier in the file or is already stored.
+async fn remove_duplicates(
+    pool: &PgPool,
+    rows: Vec<ImportRow>,
+    skipped: &mut Vec<RowError>,
+) -> Result<Vec<ImportRow>, anyhow::Error> {
+    let emails: Vec<String> = rows
+        .iter()
+        .map(|r| r.subscriber.email.as_ref().to_owned())
+        .collect();
+    let mut seen = get_existing_emails(pool, &emails)
+        .await
+        .context("Failed to fetch existing subscribers")?;
+
+    let mut unique = Vec::with_capacity(rows.len());
+    for row in rows {
+        if seen.insert(row.subscriber.email.as_ref().to_owned()) {
+            unique.push(row);
+        } else {
+            skipped.push(duplicate(&row));
+        }
+    }
+    Ok(unique)
+}
+
+#[tracing::instrument(name = "Get existing subscriber emails", skip_all)]
+async fn get_existing_emails(
+    pool: &PgPool,
+    emails: &[String],
+) -> Result<HashSet<String>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT email
+        FROM subscriptions
+        WHERE email = ANY($1)
+        "#,
+        emails
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.email).collect())
+}
+
+#[tracing::instrument(name = "Saving imported subscriber details in the database", skip_all)]
+async fn insert_imported_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    new_subscriber: &NewSubscriber,
+    status: SubscriberStatus,
+    tags: &[String],
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let subscriber_id = Uuid::new_v4();
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status, tags)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (email) DO NOTHING
+        "#,
+        subscriber_id,
+        new_subscriber.email.as_ref(),
+        new_subscriber.name.as_ref(),
+        Utc::now(),
+        status.as_str(),
+        tags
+    );
+    let n_inserted_rows = transaction.execute(query).await?.rows_affected();
+
+    Ok((n_inserted_rows > 0).then_some(subscriber_id))
+}
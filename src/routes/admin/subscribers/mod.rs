@@ -0,0 +1,9 @@
+mod data;
+mod export;
+mod get;
+mod import;
+
+pub use data::*;
+pub use export::*;
+pub use get::*;
+pub use import::*;
@@ -0,0 +1,72 @@
+use std::fmt::Write;
+
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+use crate::authentication::{
+    CsrfToken, UserId, generate_totp_secret, get_totp_secret, provisioning_qr_code,
+};
+use crate::html_templates::Templates;
+use crate::routes::get_username;
+use crate::session_state::TypedSession;
+use crate::utils::e500;
+
+#[utoipa::path(
+    get,
+    path = "/admin/two-factor",
+    tag = "admin",
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The form to enable or disable two-factor authentication.", content_type = "text/html"),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+    )
+)]
+pub async fn two_factor_form(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let enabled = get_totp_secret(*user_id, &pool)
+        .await
+        .map_err(e500)?
+        .is_some();
+    let html_body = if enabled {
+        Templates::render_two_factor(&msg_html, None, csrf_token.as_ref())
+    } else {
+        // The secret is only stored for the user once they prove
+        // that their authenticator app is set up.
+        let secret = match session.get_totp_enrollment_secret().map_err(e500)? {
+            Some(secret) => secret,
+            None => {
+                let secret = generate_totp_secret();
+                session
+                    .insert_totp_enrollment_secret(&secret)
+                    .map_err(e500)?;
+                secret
+            }
+        };
+        let username = get_username(*user_id, &pool).await.map_err(e500)?;
+        let qr_code = provisioning_qr_code(&secret, &username).map_err(e500)?;
+        Templates::render_two_factor(
+            &msg_html,
+            Some((&qr_code, secret.expose_secret())),
+            csrf_token.as_ref(),
+        )
+    }
+    .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
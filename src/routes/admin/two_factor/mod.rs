@@ -0,0 +1,8 @@
+mod get;
+mod post;
+
+pub use get::{__path_two_factor_form, two_factor_form};
+pub use post::{
+    __path_disable_two_factor_authentication, __path_enable_two_factor_authentication,
+    disable_two_factor_authentication, enable_two_factor_authentication,
+};
@@ -0,0 +1,104 @@
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use secrecy::Secret;
+use sqlx::PgPool;
+
+use crate::authentication::{
+    AuthError, Credentials, PasswordHashing, UserId, disable_two_factor, enable_two_factor,
+    validate_credentials, verify_totp_code,
+};
+use crate::html_templates::Templates;
+use crate::routes::get_username;
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct EnableFormData {
+    code: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/two-factor/enable",
+    tag = "admin",
+    request_body(content = EnableFormData, content_type = "application/x-www-form-urlencoded"),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 200, description = "Two-factor authentication is enabled: the recovery codes, shown once.", content_type = "text/html"),
+        (status = 303, description = "The code is invalid or the enrollment has expired. Redirects back to the form, with the outcome as a flash message."),
+    )
+)]
+pub async fn enable_two_factor_authentication(
+    form: web::Form<EnableFormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let Some(secret) = session.get_totp_enrollment_secret().map_err(e500)? else {
+        FlashMessage::error("The enrollment has expired, please scan the new QR code.").send();
+        return Ok(see_other("/admin/two-factor"));
+    };
+    let username = get_username(*user_id, &pool).await.map_err(e500)?;
+    if verify_totp_code(&secret, &username, &form.code)
+        .map_err(e500)?
+        .is_none()
+    {
+        FlashMessage::error("The authentication code is invalid.").send();
+        return Ok(see_other("/admin/two-factor"));
+    }
+
+    let recovery_codes = enable_two_factor(*user_id, &secret, &pool)
+        .await
+        .map_err(e500)?;
+    session.remove_totp_enrollment_secret();
+
+    let html_body = Templates::render_recovery_codes(&recovery_codes).map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct DisableFormData {
+    #[schema(value_type = String, format = Password)]
+    current_password: Secret<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/two-factor/disable",
+    tag = "admin",
+    request_body(content = DisableFormData, content_type = "application/x-www-form-urlencoded"),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "Redirects back to the form, with the outcome as a flash message."),
+    )
+)]
+pub async fn disable_two_factor_authentication(
+    form: web::Form<DisableFormData>,
+    pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashing>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let username = get_username(*user_id, &pool).await.map_err(e500)?;
+    let credentials = Credentials {
+        username,
+        password: form.0.current_password,
+    };
+    if let Err(e) = validate_credentials(credentials, &hashing, &pool).await {
+        return match e {
+            AuthError::InvalidCredentials(_) => {
+                FlashMessage::error("The current password is incorrect.").send();
+                Ok(see_other("/admin/two-factor"))
+            }
+            AuthError::UnexpectedError(_) => Err(e500(e)),
+        };
+    }
+
+    disable_two_factor(*user_id, &pool).await.map_err(e500)?;
+    FlashMessage::info("Two-factor authentication has been disabled.").send();
+    Ok(see_other("/admin/two-factor"))
+}
@@ -0,0 +1,99 @@
+use std::fmt::Write;
+
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::CsrfToken;
+use crate::html_templates::Templates;
+use crate::utils::e500;
+
+#[derive(serde::Serialize)]
+pub struct UserRecord {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: String,
+    pub disabled: bool,
+    pub two_factor_enabled: bool,
+}
+
+#[derive(serde::Serialize)]
+pub struct InvitationRecord {
+    pub email: String,
+    pub role: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/users",
+    tag = "admin",
+    security(("session" = [])),
+    responses(
+        (status = 200, description = "The users and the pending invitations.", content_type = "text/html"),
+        (status = 303, description = "Not logged in: redirects to the login form."),
+        (status = 403, description = "Only owners are allowed."),
+    )
+)]
+pub async fn list_users(
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+    csrf_token: web::ReqData<CsrfToken>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        // Errors about the form quote the submitted values
+        let content = htmlescape::encode_minimal(m.content());
+        writeln!(msg_html, "<p><i>{content}</i></p>").unwrap();
+    }
+    let users = get_users(&pool).await.map_err(e500)?;
+    let invitations = get_pending_invitations(&pool).await.map_err(e500)?;
+
+    let html_body = Templates::render_users(&msg_html, &users, &invitations, csrf_token.as_ref())
+        .map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
+
+#[tracing::instrument(name = "Get users", skip(pool))]
+async fn get_users(pool: &PgPool) -> Result<Vec<UserRecord>, anyhow::Error> {
+    let users = sqlx::query_as!(
+        UserRecord,
+        r#"
+        SELECT
+            user_id,
+            username,
+            role,
+            disabled_at IS NOT NULL AS "disabled!",
+            totp_secret IS NOT NULL AS "two_factor_enabled!"
+        FROM users
+        ORDER BY username
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the users.")?;
+    Ok(users)
+}
+
+#[tracing::instrument(name = "Get pending invitations", skip(pool))]
+async fn get_pending_invitations(pool: &PgPool) -> Result<Vec<InvitationRecord>, anyhow::Error> {
+    let invitations = sqlx::query_as!(
+        InvitationRecord,
+        r#"
+        SELECT email, role, expires_at
+        FROM user_invitations
+        WHERE accepted_at IS NULL AND expires_at > now()
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the pending invitations.")?;
+    Ok(invitations)
+}
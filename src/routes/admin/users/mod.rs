@@ -0,0 +1,8 @@
+mod get;
+mod post;
+
+pub use get::{__path_list_users, InvitationRecord, UserRecord, list_users};
+pub use post::{
+    __path_create_user_admin, __path_delete_user, __path_disable_user, __path_enable_user,
+    __path_invite_user, create_user_admin, delete_user, disable_user, enable_user, invite_user,
+};
@@ -0,0 +1,354 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{Duration, Utc};
+use secrecy::Secret;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::{
+    CreateUserError, PasswordHashing, PasswordPolicy, PasswordPolicyError, UserId, create_user,
+    generate_token, hash_token,
+};
+use crate::domain::{SubscriberEmail, UserRole};
+use crate::email_client::EmailClient;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::{e500, see_other};
+
+const INVITATION_VALIDITY_DAYS: i64 = 7;
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct CreateUserFormData {
+    username: String,
+    // Optional, used to send password reset links
+    #[serde(default)]
+    email: String,
+    #[schema(value_type = String, format = Password)]
+    password: Secret<String>,
+    role: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users",
+    tag = "admin",
+    request_body(content = CreateUserFormData, content_type = "application/x-www-form-urlencoded"),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "Redirects back to the form, with the outcome as a flash message."),
+        (status = 403, description = "Only owners are allowed."),
+    )
+)]
+pub async fn create_user_admin(
+    form: web::Form<CreateUserFormData>,
+    pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashing>,
+    policy: web::Data<PasswordPolicy>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let CreateUserFormData {
+        username,
+        email,
+        password,
+        role,
+    } = form.0;
+    let username = username.trim();
+    if username.is_empty() {
+        FlashMessage::error("The username cannot be empty.").send();
+        return Ok(see_other("/admin/users"));
+    }
+    match policy.check(&password, username) {
+        Ok(()) => {}
+        Err(PasswordPolicyError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other("/admin/users"));
+        }
+    }
+    let email = match email.trim() {
+        "" => None,
+        email => match SubscriberEmail::parse(email.to_owned()) {
+            Ok(email) => Some(email),
+            Err(e) => {
+                FlashMessage::error(e).send();
+                return Ok(see_other("/admin/users"));
+            }
+        },
+    };
+    let role = match UserRole::parse(&role) {
+        Ok(role) => role,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/users"));
+        }
+    };
+
+    let mut connection = pool
+        .acquire()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    match create_user(
+        username,
+        email.as_ref(),
+        password,
+        role,
+        &hashing,
+        &mut connection,
+    )
+    .await
+    {
+        Ok(_) => FlashMessage::info(format!("The user {username} has been created.")).send(),
+        Err(e @ (CreateUserError::UsernameTaken(_) | CreateUserError::EmailTaken(_))) => {
+            FlashMessage::error(e.to_string()).send()
+        }
+        Err(e) => return Err(e500(e)),
+    }
+    Ok(see_other("/admin/users"))
+}
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct InviteFormData {
+    email: String,
+    role: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/invite",
+    tag = "admin",
+    request_body(content = InviteFormData, content_type = "application/x-www-form-urlencoded"),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "The invitation is emailed. Redirects back to the form, with the outcome as a flash message."),
+        (status = 403, description = "Only owners are allowed."),
+    )
+)]
+pub async fn invite_user(
+    form: web::Form<InviteFormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let InviteFormData { email, role } = form.0;
+    let email = match SubscriberEmail::parse(email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/users"));
+        }
+    };
+    let role = match UserRole::parse(&role) {
+        Ok(role) => role,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/admin/users"));
+        }
+    };
+
+    let invitation_token = generate_token();
+    store_invitation(&pool, &invitation_token, &email, role, **user_id)
+        .await
+        .map_err(e500)?;
+    send_invitation_email(&email_client, &email, role, &base_url.0, &invitation_token)
+        .await
+        .map_err(e500)?;
+
+    FlashMessage::info(format!(
+        "An invitation has been sent to {}.",
+        email.as_ref()
+    ))
+    .send();
+    Ok(see_other("/admin/users"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{user_id}/disable",
+    tag = "admin",
+    params(("user_id" = Uuid, Path)),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "The user is disabled: redirects to the users."),
+        (status = 403, description = "Only owners are allowed."),
+    )
+)]
+pub async fn disable_user(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let target_user_id = path.into_inner();
+    if target_user_id == **user_id {
+        FlashMessage::error("You cannot disable your own account.").send();
+        return Ok(see_other("/admin/users"));
+    }
+    set_user_disabled(&pool, target_user_id, true)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info("The user has been disabled.").send();
+    Ok(see_other("/admin/users"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{user_id}/enable",
+    tag = "admin",
+    params(("user_id" = Uuid, Path)),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "The user is enabled: redirects to the users."),
+        (status = 403, description = "Only owners are allowed."),
+    )
+)]
+pub async fn enable_user(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    set_user_disabled(&pool, path.into_inner(), false)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info("The user has been enabled.").send();
+    Ok(see_other("/admin/users"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{user_id}/delete",
+    tag = "admin",
+    params(("user_id" = Uuid, Path)),
+    security(("session" = [], "csrf_token" = [])),
+    responses(
+        (status = 303, description = "The user is deleted: redirects to the users."),
+        (status = 403, description = "Only owners are allowed."),
+    )
+)]
+pub async fn delete_user(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let target_user_id = path.into_inner();
+    if target_user_id == **user_id {
+        FlashMessage::error("You cannot delete your own account.").send();
+        return Ok(see_other("/admin/users"));
+    }
+    delete_user_from_database(&pool, target_user_id)
+        .await
+        .map_err(e500)?;
+    FlashMessage::info("The user has been deleted.").send();
+    Ok(see_other("/admin/users"))
+}
+
+#[tracing::instrument(name = "Store an invitation", skip(pool, invitation_token, email))]
+async fn store_invitation(
+    pool: &PgPool,
+    invitation_token: &str,
+    email: &SubscriberEmail,
+    role: UserRole,
+    invited_by: Uuid,
+) -> Result<(), anyhow::Error> {
+    let created_at = Utc::now();
+    sqlx::query!(
+        r#"
+        INSERT INTO user_invitations (
+            invitation_id,
+            token_hash,
+            email,
+            role,
+            invited_by,
+            created_at,
+            expires_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        Uuid::new_v4(),
+        hash_token(invitation_token),
+        email.as_ref(),
+        role.as_str(),
+        invited_by,
+        created_at,
+        created_at + Duration::days(INVITATION_VALIDITY_DAYS)
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the invitation.")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+    name = "Send an invitation email",
+    skip(email_client, email, base_url, invitation_token)
+)]
+async fn send_invitation_email(
+    email_client: &EmailClient,
+    email: &SubscriberEmail,
+    role: UserRole,
+    base_url: &str,
+    invitation_token: &str,
+) -> Result<(), anyhow::Error> {
+    let invitation_link = format!(
+        "{}/invitations/accept?invitation_token={}",
+        base_url, invitation_token
+    );
+    let plain_body = format!(
+        "You have been invited to manage our newsletter as {role}.\n\
+        Visit {invitation_link} to create your account. \
+        The link expires in {INVITATION_VALIDITY_DAYS} days."
+    );
+    let html_body = format!(
+        "You have been invited to manage our newsletter as {role}.<br />\
+        Click <a href=\"{invitation_link}\">here</a> to create your account. \
+        The link expires in {INVITATION_VALIDITY_DAYS} days."
+    );
+    email_client
+        .send_email(
+            email,
+            "You have been invited to manage our newsletter",
+            &html_body,
+            &plain_body,
+        )
+        .await
+}
+
+#[tracing::instrument(name = "Set user disabled", skip(pool))]
+async fn set_user_disabled(
+    pool: &PgPool,
+    user_id: Uuid,
+    disabled: bool,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET disabled_at = CASE WHEN $2 THEN COALESCE(disabled_at, now()) END
+        WHERE user_id = $1
+        "#,
+        user_id,
+        disabled
+    )
+    .execute(pool)
+    .await
+    .context("Failed to update the user.")?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Delete user", skip(pool))]
+async fn delete_user_from_database(pool: &PgPool, user_id: Uuid) -> Result<(), anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    sqlx::query!("DELETE FROM idempotency WHERE user_id = $1", user_id)
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to delete the saved responses of the user.")?;
+    sqlx::query!("DELETE FROM users WHERE user_id = $1", user_id)
+        .execute(&mut *transaction)
+        .await
+        .context("Failed to delete the user.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to delete a user")?;
+    Ok(())
+}
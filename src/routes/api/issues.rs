@@ -0,0 +1,176 @@
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{ApiError, ApiErrorBody, require_scope};
+use crate::authentication::{ApiScope, ApiToken};
+use crate::domain::IssueHtml;
+use crate::idempotency::IdempotentTransaction;
+use crate::routes::{
+    check_confirmed_subscribers, enqueue_delivery_tasks, insert_newsletter_issue,
+    render_markdown_issue,
+};
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct IssueData {
+    title: String,
+    /// Required without `markdown_content`. The rules of its `<style>` blocks
+    /// are inlined, and it is refused if it contains scripts or event handlers.
+    html_content: Option<String>,
+    /// Required without `markdown_content`.
+    text_content: Option<String>,
+    /// Written in Markdown, the HTML and plain text are generated from it.
+    markdown_content: Option<String>,
+    /// Whether to show the issue in the public archive.
+    #[serde(default)]
+    public: bool,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct PublishedIssue {
+    newsletter_issue_id: Uuid,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct IssueDeliveryStatus {
+    newsletter_issue_id: Uuid,
+    title: String,
+    published_at: DateTime<Utc>,
+    recipients_count: i32,
+    delivered_count: i32,
+    failed_count: i32,
+    dropped_count: i32,
+    pending_count: i64,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/issues",
+    tag = "api",
+    params(("Idempotency-Key" = String, Header, description = "Retries with the same key get the same response.")),
+    request_body = IssueData,
+    security(("api_token" = ["issues:write"])),
+    responses(
+        (status = 202, description = "The issue is queued for delivery.", body = PublishedIssue),
+        (status = 400, description = "The issue or the idempotency key is invalid.", body = ApiErrorBody),
+        (status = 401, description = "The API token is missing or invalid.", body = ApiErrorBody),
+        (status = 403, description = "The API token or its user is not allowed to use the issues:write scope.", body = ApiErrorBody),
+        (status = 409, description = "The newsletter has no confirmed subscribers, or another request with the same idempotency key is still in progress.", body = ApiErrorBody),
+        (status = 422, description = "The idempotency key was already used with a different issue.", body = ApiErrorBody),
+    )
+)]
+#[tracing::instrument(
+    name = "Publish a newsletter issue through the API",
+    skip_all,
+    fields(user_id=%api_token.user_id)
+)]
+pub async fn publish_issue(
+    body: web::Json<IssueData>,
+    pool: web::Data<PgPool>,
+    api_token: web::ReqData<ApiToken>,
+    mut transaction: IdempotentTransaction,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&api_token, ApiScope::IssuesWrite)?;
+    let IssueData {
+        title,
+        html_content,
+        text_content,
+        markdown_content,
+        public,
+    } = body.0;
+    if title.trim().is_empty() {
+        return Err(ApiError::ValidationError(
+            "The title cannot be empty.".into(),
+        ));
+    }
+    let (html_content, text_content) = match (&markdown_content, html_content, text_content) {
+        (Some(markdown), _, _) => render_markdown_issue(&title, markdown)?,
+        (None, Some(html_content), Some(text_content)) => (html_content, text_content),
+        _ => {
+            return Err(ApiError::ValidationError(
+                "Either markdown_content or both html_content and text_content are required."
+                    .into(),
+            ));
+        }
+    };
+    if html_content.trim().is_empty() || text_content.trim().is_empty() {
+        return Err(ApiError::ValidationError(
+            "The content cannot be empty.".into(),
+        ));
+    }
+    let html_content = IssueHtml::parse(&html_content)
+        .map_err(|warnings| ApiError::ValidationError(warnings.join(" ")))?;
+
+    if !check_confirmed_subscribers(&pool).await? {
+        return Err(ApiError::Conflict(
+            "The newsletter has no confirmed subscribers.".into(),
+        ));
+    }
+    let issue_id = insert_newsletter_issue(
+        &mut transaction,
+        &title,
+        &text_content,
+        html_content.as_ref(),
+        markdown_content.as_deref(),
+        public,
+    )
+    .await
+    .context("Failed to store newsletter issue details")?;
+    enqueue_delivery_tasks(&mut transaction, issue_id)
+        .await
+        .context("Failed to enqueue delivery tasks")?;
+    transaction.complete();
+    Ok(HttpResponse::Accepted().json(PublishedIssue {
+        newsletter_issue_id: issue_id,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/issues/{newsletter_issue_id}",
+    tag = "api",
+    params(("newsletter_issue_id" = Uuid, Path)),
+    security(("api_token" = ["issues:read"])),
+    responses(
+        (status = 200, description = "The delivery status of the issue.", body = IssueDeliveryStatus),
+        (status = 401, description = "The API token is missing or invalid.", body = ApiErrorBody),
+        (status = 403, description = "The API token or its user is not allowed to use the issues:read scope.", body = ApiErrorBody),
+        (status = 404, description = "There is no such newsletter issue.", body = ApiErrorBody),
+    )
+)]
+pub async fn get_issue_delivery_status(
+    path: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    api_token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&api_token, ApiScope::IssuesRead)?;
+    let newsletter_issue_id = path.into_inner();
+    let status = sqlx::query_as!(
+        IssueDeliveryStatus,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            published_at,
+            recipients_count,
+            delivered_count,
+            failed_count,
+            dropped_count,
+            (
+                SELECT count(*)
+                FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = i.newsletter_issue_id
+            ) as "pending_count!"
+        FROM newsletter_issues i
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to fetch the newsletter issue.")?
+    .ok_or_else(|| ApiError::NotFound("There is no such newsletter issue.".into()))?;
+    Ok(HttpResponse::Ok().json(status))
+}
@@ -0,0 +1,71 @@
+mod issues;
+mod subscribers;
+
+pub use issues::{
+    __path_get_issue_delivery_status, __path_publish_issue, get_issue_delivery_status,
+    publish_issue,
+};
+pub use subscribers::{__path_list_subscribers_api, list_subscribers_api};
+
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+
+use crate::authentication::{ApiScope, ApiToken};
+use crate::routes::error_chain_fmt;
+
+/// The body of the errors of the JSON API.
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct ApiErrorBody {
+    #[schema(example = "There is no such newsletter issue.")]
+    pub error: String,
+}
+
+/// The errors of the JSON API, returned as `{"error": "<message>"}`.
+#[derive(thiserror::Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("The API token is not allowed to use the {0} scope.")]
+    MissingScope(ApiScope),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            ApiError::MissingScope(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        // The cause of unexpected errors is only logged.
+        let message = match self {
+            ApiError::UnexpectedError(_) => "Something went wrong.".to_string(),
+            e => e.to_string(),
+        };
+        HttpResponse::build(self.status_code()).json(ApiErrorBody { error: message })
+    }
+}
+
+fn require_scope(api_token: &ApiToken, scope: ApiScope) -> Result<(), ApiError> {
+    if api_token.allows(scope) {
+        Ok(())
+    } else {
+        Err(ApiError::MissingScope(scope))
+    }
+}
@@ -0,0 +1,41 @@
+use actix_web::{HttpResponse, web};
+use sqlx::PgPool;
+
+use super::{ApiError, ApiErrorBody, require_scope};
+use crate::authentication::{ApiScope, ApiToken};
+use crate::routes::{PageParameters, get_subscribers_page};
+use crate::subscriber_data::SubscriptionRecord;
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct SubscribersPage {
+    subscribers: Vec<SubscriptionRecord>,
+    page: i64,
+    has_next_page: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/subscribers",
+    tag = "api",
+    params(PageParameters),
+    security(("api_token" = ["subscribers:read"])),
+    responses(
+        (status = 200, description = "A page of subscribers, the most recent first.", body = SubscribersPage),
+        (status = 401, description = "The API token is missing or invalid.", body = ApiErrorBody),
+        (status = 403, description = "The API token or its user is not allowed to use the subscribers:read scope.", body = ApiErrorBody),
+    )
+)]
+pub async fn list_subscribers_api(
+    parameters: web::Query<PageParameters>,
+    pool: web::Data<PgPool>,
+    api_token: web::ReqData<ApiToken>,
+) -> Result<HttpResponse, ApiError> {
+    require_scope(&api_token, ApiScope::SubscribersRead)?;
+    let page = parameters.page.unwrap_or(1).max(1);
+    let (subscribers, has_next_page) = get_subscribers_page(&pool, page).await?;
+    Ok(HttpResponse::Ok().json(SubscribersPage {
+        subscribers,
+        page,
+        has_next_page,
+    }))
+}
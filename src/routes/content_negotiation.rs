@@ -0,0 +1,78 @@
+use actix_web::error::InternalError;
+use actix_web::http::header::ACCEPT;
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, ResponseError};
+
+/// A client error about a single field of the request.
+#[derive(Debug)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for FieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// The details of an error, for clients that asked for JSON.
+pub trait JsonErrorDetails: ResponseError {
+    /// The request field at fault, if any.
+    fn field(&self) -> Option<&'static str>;
+    /// A stable identifier of the error, for clients to match on.
+    fn code(&self) -> &'static str;
+    /// A message that can be shown to end users.
+    fn message(&self) -> String;
+}
+
+/// The JSON body of the errors of the public endpoints.
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct JsonError {
+    /// The request field at fault, if any.
+    #[schema(example = "email")]
+    pub field: Option<&'static str>,
+    /// A stable identifier of the error, for clients to match on.
+    #[schema(example = "invalid_email")]
+    pub code: &'static str,
+    /// A message that can be shown to end users.
+    pub message: String,
+}
+
+/// Whether the client sent JSON or asked for it.
+pub fn wants_json(request: &HttpRequest) -> bool {
+    let accepts_json = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|h| h.contains("application/json"));
+    accepts_json || request.content_type() == "application/json"
+}
+
+/// Render the error as `{"field": ..., "code": ..., "message": ...}` for
+/// clients that want JSON, and as usual otherwise.
+/// The error itself is kept as the cause, for logging.
+pub fn negotiate_error<E>(e: E, request: &HttpRequest) -> actix_web::Error
+where
+    E: JsonErrorDetails + 'static,
+{
+    if !wants_json(request) {
+        return e.into();
+    }
+    let response = HttpResponse::build(e.status_code()).json(JsonError {
+        field: e.field(),
+        code: e.code(),
+        message: e.message(),
+    });
+    InternalError::from_response(e, response).into()
+}
@@ -0,0 +1,222 @@
+use std::fmt::Write;
+use std::time::SystemTime;
+
+use actix_web::http::header::{
+    self, ETag, EntityTag, HttpDate, IfModifiedSince, IfNoneMatch, LastModified,
+};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
+use anyhow::Context;
+use chrono::{DateTime, SecondsFormat, SubsecRound, Utc};
+use htmlescape::encode_minimal;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::e500;
+
+const FEED_SIZE: i64 = 20;
+const FEED_TITLE: &str = "Newsletter";
+
+struct FeedIssue {
+    title: String,
+    slug: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/feed.rss",
+    tag = "pages",
+    responses(
+        (status = 200, description = "An RSS 2.0 feed of the latest public issues.", content_type = "application/rss+xml"),
+        (status = 304, description = "The feed did not change since the `If-None-Match` or `If-Modified-Since` of the request."),
+    )
+)]
+pub async fn rss_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issues = get_latest_public_issues(&pool).await.map_err(e500)?;
+    let body = rss(&issues, &base_url.0);
+    Ok(feed_response(
+        &request,
+        "application/rss+xml; charset=utf-8",
+        body,
+        issues.first().map(|issue| issue.published_at),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/feed.atom",
+    tag = "pages",
+    responses(
+        (status = 200, description = "An Atom feed of the latest public issues.", content_type = "application/atom+xml"),
+        (status = 304, description = "The feed did not change since the `If-None-Match` or `If-Modified-Since` of the request."),
+    )
+)]
+pub async fn atom_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issues = get_latest_public_issues(&pool).await.map_err(e500)?;
+    let body = atom(&issues, &base_url.0);
+    Ok(feed_response(
+        &request,
+        "application/atom+xml; charset=utf-8",
+        body,
+        issues.first().map(|issue| issue.published_at),
+    ))
+}
+
+/// Answer with the feed, or with a 304 if the client already has it.
+fn feed_response(
+    request: &HttpRequest,
+    content_type: &str,
+    body: String,
+    last_published_at: Option<DateTime<Utc>>,
+) -> HttpResponse {
+    let etag = EntityTag::new_strong(format!("{:x}", Sha256::digest(&body)));
+    // HTTP dates have no fractions of a second
+    let last_modified = last_published_at
+        .map(|published_at| HttpDate::from(SystemTime::from(published_at.trunc_subsecs(0))));
+
+    // `If-Modified-Since` is only looked at without `If-None-Match`
+    let not_modified = match request.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(etags)) => etags.iter().any(|tag| tag.weak_eq(&etag)),
+        None => match (request.get_header::<IfModifiedSince>(), last_modified) {
+            (Some(IfModifiedSince(since)), Some(last_modified)) => last_modified <= since,
+            _ => false,
+        },
+    };
+
+    let mut response = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    response.insert_header(ETag(etag));
+    if let Some(last_modified) = last_modified {
+        response.insert_header(LastModified(last_modified));
+    }
+    if not_modified {
+        response.finish()
+    } else {
+        response
+            .insert_header((header::CONTENT_TYPE, content_type))
+            .body(body)
+    }
+}
+
+fn rss(issues: &[FeedIssue], base_url: &str) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    write!(
+        xml,
+        r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"><channel>"#
+    )
+    .unwrap();
+    write!(
+        xml,
+        "<title>{}</title><link>{}/issues</link><description>The past issues of the newsletter.</description>",
+        FEED_TITLE,
+        encode_minimal(base_url)
+    )
+    .unwrap();
+    write!(
+        xml,
+        r#"<atom:link href="{}/feed.rss" rel="self" type="application/rss+xml"/>"#,
+        encode_minimal(base_url)
+    )
+    .unwrap();
+    if let Some(issue) = issues.first() {
+        write!(
+            xml,
+            "<lastBuildDate>{}</lastBuildDate>",
+            issue.published_at.to_rfc2822()
+        )
+        .unwrap();
+    }
+    for issue in issues {
+        let link = encode_minimal(&format!("{base_url}/issues/{}", issue.slug));
+        write!(
+            xml,
+            "<item><title>{}</title><link>{link}</link><guid>{link}</guid>\
+            <pubDate>{}</pubDate><description>{}</description></item>",
+            encode_minimal(&issue.title),
+            issue.published_at.to_rfc2822(),
+            encode_minimal(&issue.html_content)
+        )
+        .unwrap();
+    }
+    xml.push_str("</channel></rss>");
+    xml
+}
+
+fn atom(issues: &[FeedIssue], base_url: &str) -> String {
+    // An empty feed still needs an `updated` date, which must not change
+    // between requests for the ETag to stay the same
+    let updated = issues
+        .first()
+        .map_or(DateTime::UNIX_EPOCH, |issue| issue.published_at);
+    let base_url = encode_minimal(base_url);
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    write!(
+        xml,
+        r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>{FEED_TITLE}</title>"#
+    )
+    .unwrap();
+    write!(
+        xml,
+        r#"<id>{base_url}/feed.atom</id><link href="{base_url}/feed.atom" rel="self"/>"#
+    )
+    .unwrap();
+    write!(
+        xml,
+        r#"<link href="{base_url}/issues"/><updated>{}</updated><author><name>{FEED_TITLE}</name></author>"#,
+        updated.to_rfc3339_opts(SecondsFormat::Secs, true)
+    )
+    .unwrap();
+    for issue in issues {
+        let link = format!("{base_url}/issues/{}", encode_minimal(&issue.slug));
+        let published_at = issue
+            .published_at
+            .to_rfc3339_opts(SecondsFormat::Secs, true);
+        write!(
+            xml,
+            r#"<entry><title>{}</title><id>{link}</id><link href="{link}"/>"#,
+            encode_minimal(&issue.title)
+        )
+        .unwrap();
+        write!(
+            xml,
+            r#"<published>{published_at}</published><updated>{published_at}</updated><content type="html">{}</content></entry>"#,
+            encode_minimal(&issue.html_content)
+        )
+        .unwrap();
+    }
+    xml.push_str("</feed>");
+    xml
+}
+
+#[tracing::instrument(name = "Get latest public issues", skip(pool))]
+async fn get_latest_public_issues(pool: &PgPool) -> Result<Vec<FeedIssue>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        FeedIssue,
+        r#"
+        SELECT title, slug, html_content, published_at
+        FROM newsletter_issues
+        WHERE public
+        ORDER BY published_at DESC, slug
+        LIMIT $1
+        "#,
+        FEED_SIZE
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the latest public issues.")?;
+    Ok(issues)
+}
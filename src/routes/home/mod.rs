@@ -1,7 +1,19 @@
 use actix_web::{HttpResponse, http::header::ContentType};
 
-pub async fn home() -> HttpResponse {
-    HttpResponse::Ok()
+use crate::consent::ConsentText;
+use crate::html_templates::Templates;
+use crate::utils::e500;
+
+/// Shows the signup form, with the current consent text.
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "pages",
+    responses((status = 200, description = "The home page.", content_type = "text/html"))
+)]
+pub async fn home() -> Result<HttpResponse, actix_web::Error> {
+    let html_body = Templates::render_home(&ConsentText::current()).map_err(e500)?;
+    Ok(HttpResponse::Ok()
         .content_type(ContentType::html())
-        .body(include_str!("home.html"))
+        .body(html_body))
 }
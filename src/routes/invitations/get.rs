@@ -0,0 +1,56 @@
+use std::fmt::Write;
+
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+
+use super::get_pending_invitation;
+use crate::html_templates::Templates;
+use crate::utils::e500;
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Parameters {
+    invitation_token: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/invitations/accept",
+    tag = "authentication",
+    params(Parameters),
+    responses(
+        (status = 200, description = "The form to create the invited account.", content_type = "text/html"),
+        (status = 401, description = "The invitation is unknown, expired or already accepted."),
+    )
+)]
+pub async fn accept_invitation_form(
+    parameters: web::Query<Parameters>,
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(invitation) =
+        get_pending_invitation(pool.get_ref(), &parameters.invitation_token, false)
+            .await
+            .map_err(e500)?
+    else {
+        // Unknown, expired or already accepted
+        return Ok(HttpResponse::Unauthorized().finish());
+    };
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let html_body = Templates::render_accept_invitation(
+        &msg_html,
+        &parameters.invitation_token,
+        &invitation.email,
+        &invitation.role,
+    )
+    .map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
@@ -0,0 +1,53 @@
+mod get;
+mod post;
+
+pub use get::{__path_accept_invitation_form, accept_invitation_form};
+pub use post::{__path_accept_invitation, accept_invitation};
+
+use anyhow::Context;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::authentication::hash_token;
+
+struct PendingInvitation {
+    invitation_id: Uuid,
+    email: String,
+    role: String,
+}
+
+#[tracing::instrument(name = "Get pending invitation", skip_all)]
+async fn get_pending_invitation(
+    executor: impl PgExecutor<'_>,
+    invitation_token: &str,
+    for_update: bool,
+) -> Result<Option<PendingInvitation>, anyhow::Error> {
+    let token_hash = hash_token(invitation_token);
+    let invitation = if for_update {
+        sqlx::query_as!(
+            PendingInvitation,
+            r#"
+            SELECT invitation_id, email, role
+            FROM user_invitations
+            WHERE token_hash = $1 AND accepted_at IS NULL AND expires_at > now()
+            FOR UPDATE
+            "#,
+            token_hash
+        )
+        .fetch_optional(executor)
+        .await
+    } else {
+        sqlx::query_as!(
+            PendingInvitation,
+            r#"
+            SELECT invitation_id, email, role
+            FROM user_invitations
+            WHERE token_hash = $1 AND accepted_at IS NULL AND expires_at > now()
+            "#,
+            token_hash
+        )
+        .fetch_optional(executor)
+        .await
+    };
+    invitation.context("Failed to fetch the invitation.")
+}
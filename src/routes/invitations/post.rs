@@ -0,0 +1,123 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use super::get_pending_invitation;
+use crate::authentication::{
+    CreateUserError, PasswordHashing, PasswordPolicy, PasswordPolicyError, create_user,
+};
+use crate::domain::{SubscriberEmail, UserRole};
+use crate::utils::{e500, see_other};
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+#[schema(as = AcceptInvitationForm)]
+pub struct FormData {
+    invitation_token: String,
+    username: String,
+    #[schema(value_type = String, format = Password)]
+    password: Secret<String>,
+    #[schema(value_type = String, format = Password)]
+    password_check: Secret<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/invitations/accept",
+    tag = "authentication",
+    request_body(content = FormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "The account is created: redirects to the login form. \
+            Otherwise redirects back to the invitation form, with the reason as a flash message."),
+        (status = 401, description = "The invitation is unknown, expired or already accepted."),
+    )
+)]
+pub async fn accept_invitation(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashing>,
+    policy: web::Data<PasswordPolicy>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let FormData {
+        invitation_token,
+        username,
+        password,
+        password_check,
+    } = form.0;
+    let form_url = format!(
+        "/invitations/accept?invitation_token={}",
+        urlencoding::encode(&invitation_token)
+    );
+    let username = username.trim();
+    if username.is_empty() {
+        FlashMessage::error("The username cannot be empty.").send();
+        return Ok(see_other(&form_url));
+    }
+    if password.expose_secret() != password_check.expose_secret() {
+        FlashMessage::error("You entered two different passwords - the field values must match.")
+            .send();
+        return Ok(see_other(&form_url));
+    }
+    match policy.check(&password, username) {
+        Ok(()) => {}
+        Err(PasswordPolicyError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other(&form_url));
+        }
+    }
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    // Locking the invitation makes sure it is only accepted once.
+    let Some(invitation) = get_pending_invitation(&mut *transaction, &invitation_token, true)
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::Unauthorized().finish());
+    };
+    let role = UserRole::parse(&invitation.role)
+        .map_err(anyhow::Error::msg)
+        .map_err(e500)?;
+    let email = SubscriberEmail::parse(invitation.email)
+        .map_err(anyhow::Error::msg)
+        .map_err(e500)?;
+    match create_user(
+        username,
+        Some(&email),
+        password,
+        role,
+        &hashing,
+        &mut transaction,
+    )
+    .await
+    {
+        Ok(_) => {}
+        Err(e @ (CreateUserError::UsernameTaken(_) | CreateUserError::EmailTaken(_))) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other(&form_url));
+        }
+        Err(e) => return Err(e500(e)),
+    }
+    sqlx::query!(
+        "UPDATE user_invitations SET accepted_at = now() WHERE invitation_id = $1",
+        invitation.invitation_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to mark the invitation as accepted.")
+    .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to accept an invitation")
+        .map_err(e500)?;
+
+    tracing::info!(invitation_id = %invitation.invitation_id, "An invitation was accepted");
+    FlashMessage::info("Your account has been created, you can now log in.").send();
+    Ok(see_other("/login"))
+}
@@ -0,0 +1,117 @@
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::html_templates::Templates;
+use crate::routes::PageParameters;
+use crate::utils::e500;
+
+const PAGE_SIZE: i64 = 20;
+
+#[derive(serde::Serialize)]
+pub struct IssueSummary {
+    pub title: String,
+    pub slug: String,
+    pub published_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+pub struct PublicIssue {
+    pub title: String,
+    pub html_content: String,
+    pub published_at: DateTime<Utc>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/issues",
+    tag = "pages",
+    params(PageParameters),
+    responses((status = 200, description = "A page of the public archive of issues, newest first.", content_type = "text/html"))
+)]
+pub async fn list_issues(
+    parameters: web::Query<PageParameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let page = parameters.page.unwrap_or(1).max(1);
+    let (issues, has_next_page) = get_public_issues_page(&pool, page).await.map_err(e500)?;
+
+    let html_body = Templates::render_issues(&issues, page, has_next_page).map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
+
+#[utoipa::path(
+    get,
+    path = "/issues/{slug}",
+    tag = "pages",
+    params(("slug" = String, Path)),
+    responses(
+        (status = 200, description = "A public issue.", content_type = "text/html"),
+        (status = 404, description = "There is no such public issue."),
+    )
+)]
+pub async fn issue_page(
+    slug: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(issue) = get_public_issue(&pool, &slug).await.map_err(e500)? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let html_body = Templates::render_issue(&issue).map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
+
+/// The public issues of the page, and whether there is a next page.
+#[tracing::instrument(name = "Get public issues page", skip(pool))]
+async fn get_public_issues_page(
+    pool: &PgPool,
+    page: i64,
+) -> Result<(Vec<IssueSummary>, bool), anyhow::Error> {
+    // Pages too far to even compute the offset of are past the last one
+    let Some(offset) = (page - 1).checked_mul(PAGE_SIZE) else {
+        return Ok((Vec::new(), false));
+    };
+    let mut issues = sqlx::query_as!(
+        IssueSummary,
+        r#"
+        SELECT title, slug, published_at
+        FROM newsletter_issues
+        WHERE public
+        ORDER BY published_at DESC, slug
+        LIMIT $1 OFFSET $2
+        "#,
+        PAGE_SIZE + 1,
+        offset
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch public issues.")?;
+    // We fetch one extra row to know if there is a next page
+    let has_next_page = issues.len() as i64 > PAGE_SIZE;
+    issues.truncate(PAGE_SIZE as usize);
+    Ok((issues, has_next_page))
+}
+
+#[tracing::instrument(name = "Get public issue", skip(pool))]
+async fn get_public_issue(pool: &PgPool, slug: &str) -> Result<Option<PublicIssue>, anyhow::Error> {
+    let issue = sqlx::query_as!(
+        PublicIssue,
+        r#"
+        SELECT title, html_content, published_at
+        FROM newsletter_issues
+        WHERE slug = $1 AND public
+        "#,
+        slug
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the public issue.")?;
+    Ok(issue)
+}
@@ -2,6 +2,12 @@ use actix_web::{HttpResponse, http::header::ContentType};
 use actix_web_flash_messages::IncomingFlashMessages;
 use std::fmt::Write;
 
+#[utoipa::path(
+    get,
+    path = "/login",
+    tag = "authentication",
+    responses((status = 200, description = "The login form.", content_type = "text/html"))
+)]
 pub async fn login_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
     let mut error_html = String::new();
     for m in flash_messages.iter() {
@@ -35,6 +41,7 @@ pub async fn login_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
         </label>
     <button type="submit">Login</button>
     </form>
+    <p><a href="/password-reset">Forgot your password?</a></p>
 </body>
 </html>"#,
         ))
@@ -1,5 +1,9 @@
 mod get;
 mod post;
+mod two_factor;
 
-pub use get::login_form;
-pub use post::login;
+pub use get::{__path_login_form, login_form};
+pub use post::{__path_login, login};
+pub use two_factor::{
+    __path_login_two_factor, __path_login_two_factor_form, login_two_factor, login_two_factor_form,
+};
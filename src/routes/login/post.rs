@@ -1,10 +1,16 @@
-use actix_web::{HttpResponse, error::InternalError, http::header::LOCATION, web};
+use actix_web::{HttpRequest, HttpResponse, error::InternalError, http::header::LOCATION, web};
 use actix_web_flash_messages::FlashMessage;
 use secrecy::Secret;
 use sqlx::PgPool;
 
 use crate::{
-    authentication::{AuthError, Credentials, validate_credentials},
+    anti_abuse::{CountedAttempt, LoginAttempt, LoginThrottle},
+    authentication::{
+        AuthError, Credentials, PasswordHashing, get_totp_secret, register_session,
+        validate_credentials,
+    },
+    client_ip::client_ip,
+    configuration::SessionSettings,
     routes::error_chain_fmt,
     session_state::TypedSession,
 };
@@ -13,6 +19,10 @@ use crate::{
 pub enum LoginError {
     #[error("Authentication failed")]
     AuthError(#[source] anyhow::Error),
+    #[error("Too many failed login attempts. Try again in {0} minute(s).")]
+    LockedOut(u64),
+    #[error("The authentication code is invalid.")]
+    InvalidCode,
     #[error("Something went wrong")]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -24,49 +34,143 @@ impl std::fmt::Debug for LoginError {
 }
 
 #[allow(unused)]
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+#[schema(as = LoginForm)]
 pub struct FormData {
     username: String,
+    #[schema(value_type = String, format = Password)]
     password: Secret<String>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/login",
+    tag = "authentication",
+    request_body(content = FormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Logged in: redirects to the dashboard, or to the second factor form if enabled. \
+            Otherwise redirects back to the login form, with the reason as a flash message."),
+    )
+)]
 #[tracing::instrument(
-    skip(form, pool, session),
+    skip(form, pool, session, request, throttle, session_settings, hashing),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn login(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
     session: TypedSession,
+    request: HttpRequest,
+    throttle: web::Data<LoginThrottle>,
+    session_settings: web::Data<SessionSettings>,
+    hashing: web::Data<PasswordHashing>,
 ) -> Result<HttpResponse, InternalError<LoginError>> {
+    let username = form.0.username;
     let credentials = Credentials {
-        username: form.0.username,
+        username: username.clone(),
         password: form.0.password,
     };
     tracing::Span::current().record("username", tracing::field::display(&credentials.username));
-    match validate_credentials(credentials, &pool).await {
+    let ip_address = client_ip(&request);
+
+    // We do not even look at the credentials of a locked out user: the
+    // response is the same for known and unknown usernames.
+    let attempt = begin_attempt(&throttle, &username, ip_address.as_deref())
+        .await
+        .map_err(login_redirect)?;
+
+    match validate_credentials(credentials, &hashing, &pool).await {
         Ok(user_id) => {
             tracing::Span::current().record("user_id", tracing::field::display(&user_id));
             session.renew();
-            session
-                .insert_user_id(user_id)
-                .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
+            let totp_secret = get_totp_secret(user_id, &pool)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+            if totp_secret.is_some() {
+                // The failed attempts are only cleared once the second
+                // factor has been verified as well.
+                throttle
+                    .withdraw(&attempt)
+                    .await
+                    .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+                session
+                    .insert_pending_user_id(user_id)
+                    .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
+                return Ok(HttpResponse::SeeOther()
+                    .insert_header((LOCATION, "/login/two-factor"))
+                    .finish());
+            }
+            throttle
+                .record_success(&attempt)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+            start_session(&session, user_id, &request, &session_settings, &pool)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
             Ok(HttpResponse::SeeOther()
                 .insert_header((LOCATION, "/admin/dashboard"))
                 .finish())
         }
         Err(e) => {
             let e = match e {
-                AuthError::InvalidCredentials(_) => LoginError::AuthError(e.into()),
-                AuthError::UnexpectedError(_) => LoginError::UnexpectedError(e.into()),
+                AuthError::InvalidCredentials(_) => {
+                    throttle
+                        .record_failure(&pool, &attempt)
+                        .await
+                        .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+                    LoginError::AuthError(e.into())
+                }
+                AuthError::UnexpectedError(_) => {
+                    // Our own failures must not count towards a lockout.
+                    if let Err(withdraw_error) = throttle.withdraw(&attempt).await {
+                        tracing::error!(
+                            error.cause_chain = ?withdraw_error,
+                            "Failed to withdraw a login attempt"
+                        );
+                    }
+                    LoginError::UnexpectedError(e.into())
+                }
             };
             Err(login_redirect(e))
         }
     }
 }
 
+// Count the attempt and sleep for the delay imposed by the previous
+// failed attempts, or fail if they caused a lockout.
+pub(super) async fn begin_attempt(
+    throttle: &LoginThrottle,
+    username: &str,
+    ip_address: Option<&str>,
+) -> Result<CountedAttempt, LoginError> {
+    match throttle.begin(username, ip_address).await? {
+        LoginAttempt::LockedOut { retry_after } => {
+            let minutes = retry_after.as_secs().div_ceil(60).max(1);
+            Err(LoginError::LockedOut(minutes))
+        }
+        LoginAttempt::Allowed { delay, attempt } => {
+            tokio::time::sleep(delay).await;
+            Ok(attempt)
+        }
+    }
+}
+
+// Register the session, then mark it as authenticated.
+pub(super) async fn start_session(
+    session: &TypedSession,
+    user_id: uuid::Uuid,
+    request: &HttpRequest,
+    settings: &SessionSettings,
+    pool: &PgPool,
+) -> Result<(), anyhow::Error> {
+    let session_id = register_session(user_id, request, settings, pool).await?;
+    session.insert_session_id(session_id)?;
+    session.insert_user_id(user_id)?;
+    Ok(())
+}
+
 // Redirect to the login page with an error message.
-fn login_redirect(e: LoginError) -> InternalError<LoginError> {
+pub(super) fn login_redirect(e: LoginError) -> InternalError<LoginError> {
     FlashMessage::error(e.to_string()).send();
     let response = HttpResponse::SeeOther()
         .insert_header((LOCATION, "/login"))
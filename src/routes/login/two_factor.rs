@@ -0,0 +1,179 @@
+use actix_session::SessionGetError;
+use actix_web::http::header::{ContentType, LOCATION};
+use actix_web::{HttpRequest, HttpResponse, error::InternalError, web};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use chrono::Utc;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use super::post::{LoginError, begin_attempt, login_redirect, start_session};
+use crate::anti_abuse::LoginThrottle;
+use crate::authentication::{get_totp_secret, use_recovery_code, use_totp_step, verify_totp_code};
+use crate::client_ip::client_ip;
+use crate::configuration::SessionSettings;
+use crate::routes::get_username;
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+
+#[utoipa::path(
+    get,
+    path = "/login/two-factor",
+    tag = "authentication",
+    responses(
+        (status = 200, description = "The authentication code form.", content_type = "text/html"),
+        (status = 303, description = "No login is waiting for a second factor: redirects to the login form."),
+    )
+)]
+pub async fn login_two_factor_form(
+    flash_messages: IncomingFlashMessages,
+    session: TypedSession,
+    session_settings: web::Data<SessionSettings>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if get_pending_user_id(&session, &session_settings)
+        .map_err(e500)?
+        .is_none()
+    {
+        return Ok(see_other("/login"));
+    }
+    let mut error_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(error_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Two-factor authentication</title>
+</head>
+<body>
+{error_html}
+    <form action="/login/two-factor" method="post">
+        <label>Authentication code
+            <input
+                type="text"
+                placeholder="Enter the code from your authenticator app"
+                name="code"
+                autocomplete="one-time-code"
+            >
+        </label>
+        <p>Lost your device? Enter one of your recovery codes instead.</p>
+    <button type="submit">Verify</button>
+    </form>
+</body>
+</html>"#,
+        )))
+}
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct TwoFactorFormData {
+    /// The current code of the authenticator app, or a recovery code.
+    code: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/login/two-factor",
+    tag = "authentication",
+    request_body(content = TwoFactorFormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Logged in: redirects to the dashboard. \
+            Otherwise redirects back to the code or the login form."),
+    )
+)]
+#[tracing::instrument(
+    skip(form, pool, session, request, throttle, session_settings),
+    fields(user_id=tracing::field::Empty)
+)]
+pub async fn login_two_factor(
+    form: web::Form<TwoFactorFormData>,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    request: HttpRequest,
+    throttle: web::Data<LoginThrottle>,
+    session_settings: web::Data<SessionSettings>,
+) -> Result<HttpResponse, InternalError<LoginError>> {
+    let unexpected = |e: anyhow::Error| login_redirect(LoginError::UnexpectedError(e));
+    let Some(user_id) =
+        get_pending_user_id(&session, &session_settings).map_err(|e| unexpected(e.into()))?
+    else {
+        return Ok(see_other("/login"));
+    };
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+    let username = get_username(user_id, &pool).await.map_err(unexpected)?;
+    let ip_address = client_ip(&request);
+
+    let attempt = match begin_attempt(&throttle, &username, ip_address.as_deref()).await {
+        Ok(attempt) => attempt,
+        Err(e) => {
+            // Start over from the password once the lockout is over
+            session.remove_pending_user_id();
+            return Err(login_redirect(e));
+        }
+    };
+
+    let Some(totp_secret) = get_totp_secret(user_id, &pool).await.map_err(unexpected)? else {
+        // Two-factor authentication was disabled in the meantime
+        session.remove_pending_user_id();
+        return Ok(see_other("/login"));
+    };
+    let is_valid = if form.code.trim().chars().all(|c| c.is_ascii_digit()) {
+        match verify_totp_code(&totp_secret, &username, &form.code).map_err(unexpected)? {
+            Some(step) => use_totp_step(user_id, step, &pool)
+                .await
+                .map_err(unexpected)?,
+            None => false,
+        }
+    } else {
+        use_recovery_code(user_id, &form.code, &pool)
+            .await
+            .map_err(unexpected)?
+    };
+    if !is_valid {
+        throttle
+            .record_failure(&pool, &attempt)
+            .await
+            .map_err(unexpected)?;
+        let e = LoginError::InvalidCode;
+        FlashMessage::error(e.to_string()).send();
+        let response = see_other("/login/two-factor");
+        return Err(InternalError::from_response(e, response));
+    }
+
+    throttle
+        .record_success(&attempt)
+        .await
+        .map_err(unexpected)?;
+    session.renew();
+    session.remove_pending_user_id();
+    start_session(&session, user_id, &request, &session_settings, &pool)
+        .await
+        .map_err(unexpected)?;
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/admin/dashboard"))
+        .finish())
+}
+
+// The user whose password was verified, unless it was too long ago to
+// still accept the second factor.
+fn get_pending_user_id(
+    session: &TypedSession,
+    session_settings: &SessionSettings,
+) -> Result<Option<Uuid>, SessionGetError> {
+    let Some(user_id) = session.get_pending_user_id()? else {
+        return Ok(None);
+    };
+    let has_expired = match session.get_pending_since()? {
+        Some(since) => Utc::now() - since > session_settings.two_factor_timeout(),
+        None => true,
+    };
+    if has_expired {
+        session.remove_pending_user_id();
+        FlashMessage::info("Your login has expired, please enter your password again.").send();
+        return Ok(None);
+    }
+    Ok(Some(user_id))
+}
@@ -1,13 +1,29 @@
 mod admin;
+mod api;
+mod content_negotiation;
+mod feeds;
 mod health_check;
 mod home;
+mod invitations;
+mod issues;
 mod login;
+mod openapi;
+mod password_reset;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_data;
 
 pub use admin::*;
+pub use api::*;
+pub use content_negotiation::*;
+pub use feeds::*;
 pub use health_check::*;
 pub use home::*;
+pub use invitations::*;
+pub use issues::*;
 pub use login::*;
+pub use openapi::*;
+pub use password_reset::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use subscriptions_data::*;
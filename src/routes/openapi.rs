@@ -0,0 +1,14 @@
+use actix_web::{HttpResponse, web};
+
+use crate::configuration::SessionSettings;
+use crate::openapi::ApiDoc;
+
+#[utoipa::path(
+    get,
+    path = "/api/openapi.json",
+    tag = "operations",
+    responses((status = 200, description = "This document.", content_type = "application/json"))
+)]
+pub async fn openapi_document(session_settings: web::Data<SessionSettings>) -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::document(&session_settings))
+}
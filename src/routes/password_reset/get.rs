@@ -0,0 +1,90 @@
+use std::fmt::Write;
+
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sqlx::PgPool;
+
+use super::get_pending_password_reset;
+use crate::html_templates::Templates;
+use crate::utils::e500;
+
+#[utoipa::path(
+    get,
+    path = "/password-reset",
+    tag = "authentication",
+    responses((status = 200, description = "The form to request a password reset link.", content_type = "text/html"))
+)]
+pub async fn request_password_reset_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Forgot your password?</title>
+</head>
+<body>
+{msg_html}
+    <p>Enter the email of your account and we will send you a link to reset your password.</p>
+    <form action="/password-reset" method="post">
+        <label>Email
+            <input
+                type="email"
+                placeholder="Enter your email"
+                name="email"
+            >
+        </label>
+    <button type="submit">Send reset link</button>
+    </form>
+    <p><a href="/login">&lt;- Back to login</a></p>
+</body>
+</html>"#,
+        ))
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Parameters {
+    reset_token: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/password-reset/confirm",
+    tag = "authentication",
+    params(Parameters),
+    responses(
+        (status = 200, description = "The form to choose a new password.", content_type = "text/html"),
+        (status = 401, description = "The reset link is unknown, expired or already used."),
+    )
+)]
+pub async fn password_reset_form(
+    parameters: web::Query<Parameters>,
+    flash_messages: IncomingFlashMessages,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if get_pending_password_reset(pool.get_ref(), &parameters.reset_token, false)
+        .await
+        .map_err(e500)?
+        .is_none()
+    {
+        // Unknown, expired or already used
+        return Ok(HttpResponse::Unauthorized().finish());
+    }
+    let mut msg_html = String::new();
+    for m in flash_messages.iter() {
+        writeln!(msg_html, "<p><i>{}</i></p>", m.content()).unwrap();
+    }
+
+    let html_body =
+        Templates::render_password_reset(&msg_html, &parameters.reset_token).map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(html_body))
+}
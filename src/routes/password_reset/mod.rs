@@ -0,0 +1,58 @@
+mod get;
+mod post;
+
+pub use get::{
+    __path_password_reset_form, __path_request_password_reset_form, password_reset_form,
+    request_password_reset_form,
+};
+pub use post::{
+    __path_request_password_reset, __path_reset_password, request_password_reset, reset_password,
+};
+
+use anyhow::Context;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::authentication::hash_token;
+
+#[tracing::instrument(name = "Get pending password reset", skip_all)]
+async fn get_pending_password_reset(
+    executor: impl PgExecutor<'_>,
+    reset_token: &str,
+    for_update: bool,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let token_hash = hash_token(reset_token);
+    let user_id = if for_update {
+        sqlx::query_scalar!(
+            r#"
+            SELECT t.user_id
+            FROM password_reset_tokens t
+            JOIN users u ON u.user_id = t.user_id
+            WHERE t.token_hash = $1
+                AND t.used_at IS NULL
+                AND t.expires_at > now()
+                AND u.disabled_at IS NULL
+            FOR UPDATE OF t
+            "#,
+            token_hash
+        )
+        .fetch_optional(executor)
+        .await
+    } else {
+        sqlx::query_scalar!(
+            r#"
+            SELECT t.user_id
+            FROM password_reset_tokens t
+            JOIN users u ON u.user_id = t.user_id
+            WHERE t.token_hash = $1
+                AND t.used_at IS NULL
+                AND t.expires_at > now()
+                AND u.disabled_at IS NULL
+            "#,
+            token_hash
+        )
+        .fetch_optional(executor)
+        .await
+    };
+    user_id.context("Failed to fetch the password reset token.")
+}
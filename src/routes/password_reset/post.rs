@@ -0,0 +1,266 @@
+use actix_web::{HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{Duration, Utc};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use tracing::Instrument;
+use uuid::Uuid;
+
+use super::get_pending_password_reset;
+use crate::anti_abuse::RateLimiter;
+use crate::authentication::{
+    PasswordHashing, PasswordPolicy, PasswordPolicyError, change_password, generate_token,
+    hash_token, revoke_sessions,
+};
+use crate::domain::SubscriberEmail;
+use crate::email_client::EmailClient;
+use crate::routes::get_username;
+use crate::startup::{ApplicationBaseUrl, PasswordResetRateLimit};
+use crate::utils::{e500, see_other};
+
+const PASSWORD_RESET_VALIDITY_MINUTES: i64 = 60;
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct RequestFormData {
+    email: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/password-reset",
+    tag = "authentication",
+    request_body(content = RequestFormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Redirects back to the form. \
+            The response is the same whether the email belongs to an account or not."),
+    )
+)]
+#[tracing::instrument(
+    name = "Request a password reset",
+    skip(form, pool, email_client, base_url, rate_limiter, rate_limit)
+)]
+pub async fn request_password_reset(
+    form: web::Form<RequestFormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    rate_limiter: web::Data<RateLimiter>,
+    rate_limit: web::Data<PasswordResetRateLimit>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let email = match SubscriberEmail::parse(form.0.email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(see_other("/password-reset"));
+        }
+    };
+    // The response is the same whether the email belongs to a user or not.
+    FlashMessage::info(
+        "If an account exists for this email, you will receive a link to reset your password.",
+    )
+    .send();
+
+    let email_key = email.as_ref().to_lowercase();
+    if !rate_limiter
+        .hit("password_resets_per_email", &email_key, rate_limit.0)
+        .await
+        .map_err(e500)?
+    {
+        tracing::warn!("Too many password reset requests for the same email");
+        return Ok(see_other("/password-reset"));
+    }
+    // The user is looked up and emailed after the response has been sent,
+    // so that it takes as long whether the email belongs to a user or not.
+    tokio::spawn(
+        async move {
+            if let Err(e) =
+                send_password_reset_link(&pool, &email_client, &email, &base_url.0).await
+            {
+                tracing::error!(error.cause_chain = ?e, "Failed to send a password reset link");
+            }
+        }
+        .instrument(tracing::Span::current()),
+    );
+    Ok(see_other("/password-reset"))
+}
+
+// Email a reset link if `email` belongs to a user.
+async fn send_password_reset_link(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    email: &SubscriberEmail,
+    base_url: &str,
+) -> Result<(), anyhow::Error> {
+    let Some(user_id) = get_user_id_by_email(pool, email).await? else {
+        return Ok(());
+    };
+    let reset_token = generate_token();
+    store_password_reset_token(pool, &reset_token, user_id).await?;
+    send_password_reset_email(email_client, email, base_url, &reset_token).await
+}
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct ResetFormData {
+    reset_token: String,
+    #[schema(value_type = String, format = Password)]
+    new_password: Secret<String>,
+    #[schema(value_type = String, format = Password)]
+    new_password_check: Secret<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/password-reset/confirm",
+    tag = "authentication",
+    request_body(content = ResetFormData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "The password is reset: redirects to the login form. \
+            Otherwise redirects back to the reset form, with the reason as a flash message."),
+        (status = 401, description = "The reset link is unknown, expired or already used."),
+    )
+)]
+pub async fn reset_password(
+    form: web::Form<ResetFormData>,
+    pool: web::Data<PgPool>,
+    hashing: web::Data<PasswordHashing>,
+    policy: web::Data<PasswordPolicy>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let ResetFormData {
+        reset_token,
+        new_password,
+        new_password_check,
+    } = form.0;
+    let form_url = format!(
+        "/password-reset/confirm?reset_token={}",
+        urlencoding::encode(&reset_token)
+    );
+    if new_password.expose_secret() != new_password_check.expose_secret() {
+        FlashMessage::error(
+            "You entered two different new passwords - the field values must match.",
+        )
+        .send();
+        return Ok(see_other(&form_url));
+    }
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")
+        .map_err(e500)?;
+    // Locking the token makes sure it is only used once.
+    let Some(user_id) = get_pending_password_reset(&mut *transaction, &reset_token, true)
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::Unauthorized().finish());
+    };
+    let username = get_username(user_id, &pool).await.map_err(e500)?;
+    match policy.check(&new_password, &username) {
+        Ok(()) => {}
+        Err(PasswordPolicyError::UnexpectedError(e)) => return Err(e500(e)),
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other(&form_url));
+        }
+    }
+    change_password(user_id, new_password, &hashing, &mut *transaction)
+        .await
+        .map_err(e500)?;
+    // Any other link sent to the user stops working as well.
+    sqlx::query!(
+        r#"
+        UPDATE password_reset_tokens
+        SET used_at = now()
+        WHERE user_id = $1 AND used_at IS NULL
+        "#,
+        user_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .context("Failed to mark the password reset tokens as used.")
+    .map_err(e500)?;
+    revoke_sessions(user_id, None, &mut *transaction)
+        .await
+        .map_err(e500)?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to reset a password")
+        .map_err(e500)?;
+
+    tracing::info!(%user_id, "A password was reset");
+    FlashMessage::info("Your password has been reset, you can now log in.").send();
+    Ok(see_other("/login"))
+}
+
+#[tracing::instrument(name = "Get user id by email", skip(pool, email))]
+async fn get_user_id_by_email(
+    pool: &PgPool,
+    email: &SubscriberEmail,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT user_id
+        FROM users
+        WHERE lower(email) = lower($1) AND disabled_at IS NULL
+        "#,
+        email.as_ref()
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to retrieve the user.")
+}
+
+#[tracing::instrument(name = "Store a password reset token", skip(pool, reset_token))]
+async fn store_password_reset_token(
+    pool: &PgPool,
+    reset_token: &str,
+    user_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    let created_at = Utc::now();
+    sqlx::query!(
+        r#"
+        INSERT INTO password_reset_tokens (token_hash, user_id, created_at, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        hash_token(reset_token),
+        user_id,
+        created_at,
+        created_at + Duration::minutes(PASSWORD_RESET_VALIDITY_MINUTES)
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store the password reset token.")?;
+    Ok(())
+}
+
+#[tracing::instrument(
+    name = "Send a password reset email",
+    skip(email_client, email, base_url, reset_token)
+)]
+async fn send_password_reset_email(
+    email_client: &EmailClient,
+    email: &SubscriberEmail,
+    base_url: &str,
+    reset_token: &str,
+) -> Result<(), anyhow::Error> {
+    let reset_link = format!(
+        "{}/password-reset/confirm?reset_token={}",
+        base_url, reset_token
+    );
+    let plain_body = format!(
+        "Somebody asked to reset the password of your account.\n\
+        Visit {reset_link} to choose a new password. \
+        The link expires in {PASSWORD_RESET_VALIDITY_MINUTES} minutes.\n\
+        If it was not you, you can ignore this email."
+    );
+    let html_body = format!(
+        "Somebody asked to reset the password of your account.<br />\
+        Click <a href=\"{reset_link}\">here</a> to choose a new password. \
+        The link expires in {PASSWORD_RESET_VALIDITY_MINUTES} minutes.<br />\
+        If it was not you, you can ignore this email."
+    );
+    email_client
+        .send_email(email, "Reset your password", &html_body, &plain_body)
+        .await
+}
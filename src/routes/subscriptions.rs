@@ -1,19 +1,25 @@
+use actix_web::Either;
 use actix_web::http::StatusCode;
-use actix_web::{HttpResponse, ResponseError, web};
+use actix_web::{HttpRequest, HttpResponse, ResponseError, web};
 use anyhow::Context;
 use chrono::Utc;
 use sqlx::{Executor, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
+use crate::anti_abuse::{Rejection, SubscriptionGuard};
+use crate::consent::{
+    ConsentEventType, ConsentEvidence, ConsentText, SubscriptionSources, record_consent_event,
+};
 use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionToken};
 use crate::email_client::EmailClient;
 use crate::html_templates::Templates;
+use crate::routes::{FieldError, JsonError, JsonErrorDetails, negotiate_error, wants_json};
 use crate::startup::ApplicationBaseUrl;
 
 #[derive(thiserror::Error)]
 pub enum SubscribeError {
     #[error("{0}")]
-    ValidationError(String),
+    ValidationError(FieldError),
     // Transparent delegates both `Display`'s and `source`'s implementation
     // to the type wrapped by `UnexpectedError`.
     #[error(transparent)]
@@ -35,6 +41,29 @@ impl ResponseError for SubscribeError {
     }
 }
 
+impl JsonErrorDetails for SubscribeError {
+    fn field(&self) -> Option<&'static str> {
+        match self {
+            SubscribeError::ValidationError(e) => Some(e.field),
+            SubscribeError::UnexpectedError(_) => None,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            SubscribeError::ValidationError(e) => e.code,
+            SubscribeError::UnexpectedError(_) => "unexpected_error",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            SubscribeError::ValidationError(e) => e.message.clone(),
+            SubscribeError::UnexpectedError(_) => "Something went wrong.".into(),
+        }
+    }
+}
+
 pub struct StoreTokenError(sqlx::Error);
 
 impl std::fmt::Debug for StoreTokenError {
@@ -71,18 +100,36 @@ pub fn error_chain_fmt(
     Ok(())
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+#[schema(as = SubscriptionForm)]
 pub struct FormData {
+    #[schema(example = "ursula_le_guin@gmail.com")]
     email: String,
+    #[schema(example = "le guin")]
     name: String,
+    // Identifies the signup form, as part of the consent evidence.
+    // It must be one of the configured `subscription_sources`.
+    #[schema(example = "subscription_form")]
+    source: Option<String>,
+    // The version of the consent text the signup form showed,
+    // as returned by `GET /subscriptions/consent`.
+    #[schema(example = "2026-10-18")]
+    consent_version: Option<String>,
+    // A honeypot: the field is hidden from humans, only bots fill it in.
+    #[serde(default)]
+    website: String,
+    // Filled in by the challenge widget, if a challenge is configured.
+    challenge_response: Option<String>,
 }
 
 impl TryFrom<FormData> for NewSubscriber {
-    type Error = String;
+    type Error = FieldError;
 
     fn try_from(value: FormData) -> Result<Self, Self::Error> {
-        let name = SubscriberName::parse(value.name)?;
-        let email = SubscriberEmail::parse(value.email)?;
+        let name = SubscriberName::parse(value.name)
+            .map_err(|e| FieldError::new("name", "invalid_name", e))?;
+        let email = SubscriberEmail::parse(value.email)
+            .map_err(|e| FieldError::new("email", "invalid_email", e))?;
 
         Ok(Self { email, name })
     }
@@ -154,43 +201,155 @@ pub async fn check_subscriber_exists(
     Ok(record.map(|r| (r.id, r.status)))
 }
 
+/// Accepts both form and JSON bodies, and answers in JSON to clients
+/// that sent JSON or asked for it.
+#[utoipa::path(
+    post,
+    path = "/subscriptions",
+    tag = "subscriptions",
+    request_body(
+        content(
+            (FormData = "application/x-www-form-urlencoded"),
+            (FormData = "application/json"),
+        ),
+    ),
+    responses(
+        (status = 200, description = "A confirmation email is on its way, unless the request looked like abuse."),
+        (status = 400, description = "The name, the email, the source or the consent version is invalid.", body = JsonError),
+        (status = 500, description = "The subscription could not be stored.", body = JsonError),
+    )
+)]
+pub async fn subscribe(
+    body: Result<Either<web::Json<FormData>, web::Form<FormData>>, actix_web::Error>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    request: HttpRequest,
+    subscription_guard: web::Data<SubscriptionGuard>,
+    subscription_sources: web::Data<SubscriptionSources>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let form = match body {
+        Ok(Either::Left(json)) => json.0,
+        Ok(Either::Right(form)) => form.0,
+        Err(e) => {
+            let e = FieldError::new("body", "invalid_body", e.to_string());
+            return Err(negotiate_error(
+                SubscribeError::ValidationError(e),
+                &request,
+            ));
+        }
+    };
+    add_subscriber(
+        form,
+        &pool,
+        &email_client,
+        &base_url.0,
+        &request,
+        &subscription_guard,
+        &subscription_sources,
+    )
+    .await
+    .map_err(|e| negotiate_error(e, &request))?;
+    // Rejected requests get the same response as accepted ones: a bot should
+    // not be able to tell which of its attempts went through.
+    if wants_json(&request) {
+        Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "Check your inbox to confirm your subscription."
+        })))
+    } else {
+        Ok(HttpResponse::Ok().finish())
+    }
+}
+
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(form, pool, email_client, base_url),
+    skip(
+        form,
+        pool,
+        email_client,
+        base_url,
+        request,
+        subscription_guard,
+        subscription_sources
+    ),
     fields(
     subscriber_email = %form.email,
     subscriber_name = %form.name
     )
 )]
-pub async fn subscribe(
-    form: web::Form<FormData>,
-    pool: web::Data<PgPool>,
-    email_client: web::Data<EmailClient>,
-    base_url: web::Data<ApplicationBaseUrl>,
-) -> Result<HttpResponse, SubscribeError> {
-    let new_subscriber: NewSubscriber =
-        form.0.try_into().map_err(SubscribeError::ValidationError)?;
-    let subscriber_details = check_subscriber_exists(&pool, &new_subscriber.email)
+async fn add_subscriber(
+    mut form: FormData,
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &str,
+    request: &HttpRequest,
+    subscription_guard: &SubscriptionGuard,
+    subscription_sources: &SubscriptionSources,
+) -> Result<(), SubscribeError> {
+    let source = match form.source.take() {
+        Some(source) if subscription_sources.contains(source.trim()) => source.trim().to_owned(),
+        Some(_) => {
+            return Err(SubscribeError::ValidationError(FieldError::new(
+                "source",
+                "invalid_source",
+                "The source is not a known signup form.",
+            )));
+        }
+        None => "subscription_form".into(),
+    };
+    let consent = form
+        .consent_version
+        .as_deref()
+        .and_then(ConsentText::find)
+        .ok_or_else(|| {
+            SubscribeError::ValidationError(FieldError::new(
+                "consent_version",
+                "invalid_consent_version",
+                "The consent text shown to the subscriber is unknown.",
+            ))
+        })?;
+    let evidence = ConsentEvidence::from_request(request, source);
+    let rejection = subscription_guard
+        .check_request(
+            evidence.ip_address.as_deref(),
+            &form.website,
+            form.challenge_response.as_deref(),
+        )
+        .await?;
+    if let Some(rejection) = rejection {
+        log_rejection(rejection, &evidence);
+        return Ok(());
+    }
+    let new_subscriber: NewSubscriber = form.try_into().map_err(SubscribeError::ValidationError)?;
+    if let Some(rejection) = subscription_guard
+        .check_email(&new_subscriber.email)
+        .await?
+    {
+        log_rejection(rejection, &evidence);
+        return Ok(());
+    }
+    let subscriber_details = check_subscriber_exists(pool, &new_subscriber.email)
         .await
         .context("Failed to acquire a Postgres connection from the pool")?;
     if let Some((subscriber_id, subscriber_status)) = subscriber_details {
         if subscriber_status == "pending_confirmation" {
             // We are not using `SubscriptionToken` because we trust the data source
-            let subscription_token = match fetch_subscription_token(&pool, subscriber_id)
+            let subscription_token = fetch_subscription_token(pool, subscriber_id)
                 .await
                 .context("Failed to fetch a subscription token from the database")?
-            {
-                Some(token) => token,
-                None => return Ok(HttpResponse::InternalServerError().finish()),
-            };
-            send_confirmation_email(
-                &email_client,
-                new_subscriber,
-                &base_url.0,
-                &subscription_token,
+                .context("A pending subscriber has no subscription token")?;
+            record_consent_event(
+                pool,
+                subscriber_id,
+                ConsentEventType::Subscribed,
+                &evidence,
+                Some(consent.text),
             )
             .await
-            .context("Failed to send a confirmation email")?;
+            .context("Failed to record the consent of a pending subscriber")?;
+            send_confirmation_email(email_client, new_subscriber, base_url, &subscription_token)
+                .await
+                .context("Failed to send a confirmation email")?;
         }
     } else {
         let mut transaction = pool
@@ -204,21 +363,50 @@ pub async fn subscribe(
         store_token(&mut transaction, subscriber_id, subscription_token.as_ref())
             .await
             .context("Failed to store the confirmation token for a new subscriber")?;
+        record_consent_event(
+            &mut *transaction,
+            subscriber_id,
+            ConsentEventType::Subscribed,
+            &evidence,
+            Some(consent.text),
+        )
+        .await
+        .context("Failed to record the consent of a new subscriber")?;
         transaction
             .commit()
             .await
             .context("Failed to commit SQL transaction to store a new subscriber")?;
         send_confirmation_email(
-            &email_client,
+            email_client,
             new_subscriber,
-            &base_url.0,
+            base_url,
             subscription_token.as_ref(),
         )
         .await
         .context("Failed to send a confirmation email")?;
     }
 
-    Ok(HttpResponse::Ok().finish())
+    Ok(())
+}
+
+/// The consent text signup forms should show, and send back the version of.
+#[utoipa::path(
+    get,
+    path = "/subscriptions/consent",
+    tag = "subscriptions",
+    responses((status = 200, description = "The current consent text.", body = ConsentText))
+)]
+pub async fn consent_text() -> HttpResponse {
+    HttpResponse::Ok().json(ConsentText::current())
+}
+
+fn log_rejection(rejection: Rejection, evidence: &ConsentEvidence) {
+    tracing::warn!(
+        reason = %rejection,
+        ip_address = ?evidence.ip_address,
+        user_agent = ?evidence.user_agent,
+        "Rejected a subscription request",
+    );
 }
 
 #[tracing::instrument(
@@ -1,17 +1,22 @@
 use actix_web::http::StatusCode;
-use actix_web::{HttpResponse, ResponseError, web};
+use actix_web::{HttpRequest, HttpResponse, ResponseError, web};
 use anyhow::Context;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::consent::{ConsentEventType, ConsentEvidence, record_consent_event};
 use crate::domain::SubscriptionToken;
 
-use crate::routes::error_chain_fmt;
+use crate::routes::{
+    FieldError, JsonError, JsonErrorDetails, error_chain_fmt, negotiate_error, wants_json,
+};
 
 #[derive(thiserror::Error)]
 pub enum ConfirmError {
     #[error("{0}")]
-    ValidationError(String),
+    ValidationError(FieldError),
+    #[error("The subscription token is unknown.")]
+    UnknownToken,
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -26,12 +31,40 @@ impl ResponseError for ConfirmError {
     fn status_code(&self) -> StatusCode {
         match self {
             ConfirmError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            ConfirmError::UnknownToken => StatusCode::UNAUTHORIZED,
             ConfirmError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
 
-#[derive(serde::Deserialize)]
+impl JsonErrorDetails for ConfirmError {
+    fn field(&self) -> Option<&'static str> {
+        match self {
+            ConfirmError::ValidationError(e) => Some(e.field),
+            ConfirmError::UnknownToken => Some("subscription_token"),
+            ConfirmError::UnexpectedError(_) => None,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ConfirmError::ValidationError(e) => e.code,
+            ConfirmError::UnknownToken => "unknown_token",
+            ConfirmError::UnexpectedError(_) => "unexpected_error",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            ConfirmError::ValidationError(e) => e.message.clone(),
+            ConfirmError::UnexpectedError(_) => "Something went wrong.".into(),
+            e => e.to_string(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct Parameters {
     subscription_token: String,
 }
@@ -44,45 +77,93 @@ impl TryFrom<String> for SubscriptionToken {
     }
 }
 
-#[tracing::instrument(name = "Confirm a pending subscriber", skip(parameters, pool))]
+/// Answers in JSON to clients that asked for it.
+#[utoipa::path(
+    get,
+    path = "/subscriptions/confirm",
+    tag = "subscriptions",
+    params(Parameters),
+    responses(
+        (status = 200, description = "The subscription is confirmed."),
+        (status = 400, description = "The token is missing or malformed.", body = JsonError),
+        (status = 401, description = "The token is unknown.", body = JsonError),
+        (status = 500, description = "The subscription could not be confirmed.", body = JsonError),
+    )
+)]
 pub async fn confirm(
-    parameters: web::Query<Parameters>,
+    parameters: Result<web::Query<Parameters>, actix_web::Error>,
     pool: web::Data<PgPool>,
-) -> Result<HttpResponse, ConfirmError> {
-    let incoming_token: SubscriptionToken = parameters
-        .0
-        .subscription_token
-        .try_into()
-        .map_err(ConfirmError::ValidationError)?;
-    let id = get_subscriber_id_from_token(&pool, incoming_token.as_ref())
-        .await
-        .context("Failed to get a subcriber id from the provided token")?;
-
-    Ok(match id {
-        None => HttpResponse::Unauthorized().finish(), // Non-existing token!
-        Some(subscriber_id) => {
-            if !check_subscriber_is_confirmed(&pool, subscriber_id)
-                .await
-                .context("Failed to check if subcriber is already confirmed")?
-            {
-                confirm_subscriber(&pool, subscriber_id)
-                    .await
-                    .context("Failed to confirm subscriber in the database")?;
-            }
-
-            HttpResponse::Ok().finish()
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    let parameters = match parameters {
+        Ok(parameters) => parameters.0,
+        Err(e) => {
+            let e = FieldError::new("subscription_token", "missing_token", e.to_string());
+            return Err(negotiate_error(ConfirmError::ValidationError(e), &request));
         }
-    })
+    };
+    confirm_subscription(parameters, &pool, &request)
+        .await
+        .map_err(|e| negotiate_error(e, &request))?;
+    if wants_json(&request) {
+        Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "confirmed" })))
+    } else {
+        Ok(HttpResponse::Ok().finish())
+    }
+}
+
+#[tracing::instrument(name = "Confirm a pending subscriber", skip(parameters, pool, request))]
+async fn confirm_subscription(
+    parameters: Parameters,
+    pool: &PgPool,
+    request: &HttpRequest,
+) -> Result<(), ConfirmError> {
+    let incoming_token: SubscriptionToken =
+        parameters.subscription_token.try_into().map_err(|e| {
+            ConfirmError::ValidationError(FieldError::new("subscription_token", "invalid_token", e))
+        })?;
+    let subscriber_id = get_subscriber_id_from_token(pool, incoming_token.as_ref())
+        .await
+        .context("Failed to get a subcriber id from the provided token")?
+        .ok_or(ConfirmError::UnknownToken)?;
+    if !check_subscriber_is_confirmed(pool, subscriber_id)
+        .await
+        .context("Failed to check if subcriber is already confirmed")?
+    {
+        let evidence = ConsentEvidence::from_request(request, "confirmation_link");
+        confirm_subscriber(pool, subscriber_id, &evidence)
+            .await
+            .context("Failed to confirm subscriber in the database")?;
+    }
+    Ok(())
 }
 
-#[tracing::instrument(name = "Mark subscriber as confirmed", skip(subscriber_id, pool))]
-pub async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
+#[tracing::instrument(
+    name = "Mark subscriber as confirmed",
+    skip(subscriber_id, pool, evidence)
+)]
+pub async fn confirm_subscriber(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    evidence: &ConsentEvidence,
+) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
     sqlx::query!(
         r#"UPDATE subscriptions SET status = 'confirmed' WHERE id = $1"#,
         subscriber_id,
     )
-    .execute(pool)
+    .execute(&mut *transaction)
+    .await?;
+    // The confirmation event carries the timestamp of the double opt-in.
+    record_consent_event(
+        &mut *transaction,
+        subscriber_id,
+        ConsentEventType::Confirmed,
+        evidence,
+        None,
+    )
     .await?;
+    transaction.commit().await?;
 
     Ok(())
 }
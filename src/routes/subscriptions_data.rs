@@ -0,0 +1,109 @@
+use actix_web::http::StatusCode;
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, ResponseError, web};
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::domain::SubscriptionToken;
+use crate::routes::error_chain_fmt;
+use crate::subscriber_data::{
+    erase_subscriber, get_subscriber_data, get_subscriber_email_from_token,
+};
+
+#[derive(thiserror::Error)]
+pub enum SubscriberDataError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for SubscriberDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for SubscriberDataError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            SubscriberDataError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            SubscriberDataError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, utoipa::ToSchema, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TokenData {
+    subscription_token: String,
+}
+
+async fn get_email(pool: &PgPool, token: String) -> Result<Option<String>, SubscriberDataError> {
+    let token: SubscriptionToken = token
+        .try_into()
+        .map_err(SubscriberDataError::ValidationError)?;
+    let email = get_subscriber_email_from_token(pool, token.as_ref())
+        .await
+        .context("Failed to get a subscriber email from the provided token")?;
+    Ok(email)
+}
+
+#[utoipa::path(
+    get,
+    path = "/subscriptions/data",
+    tag = "subscriptions",
+    params(TokenData),
+    responses(
+        (status = 200, description = "All the data held about the subscriber.", content_type = "application/json"),
+        (status = 400, description = "The token is malformed."),
+        (status = 401, description = "The token is unknown."),
+    )
+)]
+#[tracing::instrument(name = "Export a subscriber's own data", skip(parameters, pool))]
+pub async fn subscriber_data(
+    parameters: web::Query<TokenData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, SubscriberDataError> {
+    let Some(email) = get_email(&pool, parameters.0.subscription_token).await? else {
+        return Ok(HttpResponse::Unauthorized().finish());
+    };
+    match get_subscriber_data(&pool, &email).await? {
+        Some(bundle) => Ok(HttpResponse::Ok().json(bundle)),
+        None => Ok(HttpResponse::Unauthorized().finish()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/subscriptions/erase",
+    tag = "subscriptions",
+    request_body(content = TokenData, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "The subscriber's data is erased.", content_type = "text/html"),
+        (status = 400, description = "The token is malformed."),
+        (status = 401, description = "The token is unknown."),
+    )
+)]
+#[tracing::instrument(name = "Erase a subscriber's own data", skip(form, pool))]
+pub async fn erase_subscriber_data(
+    form: web::Form<TokenData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, SubscriberDataError> {
+    let Some(email) = get_email(&pool, form.0.subscription_token).await? else {
+        return Ok(HttpResponse::Unauthorized().finish());
+    };
+    erase_subscriber(&pool, &email).await?;
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Data erased</title>
+</head>
+<body>
+    <p>All the data we held about you has been erased.</p>
+</body>
+</html>"#,
+    ))
+}
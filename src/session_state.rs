@@ -2,19 +2,37 @@ use actix_session::SessionExt;
 use actix_session::{Session, SessionGetError, SessionInsertError};
 use actix_web::dev::Payload;
 use actix_web::{FromRequest, HttpRequest};
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
 use std::future::{Ready, ready};
 use uuid::Uuid;
 
+use crate::authentication::generate_token;
+
 pub struct TypedSession(Session);
 
 impl TypedSession {
     const USER_ID_KEY: &'static str = "user_id";
+    const SESSION_ID_KEY: &'static str = "session_id";
+    const AUTHENTICATED_AT_KEY: &'static str = "authenticated_at";
+    const LAST_ACTIVITY_KEY: &'static str = "last_activity_at";
+    const CSRF_TOKEN_KEY: &'static str = "csrf_token";
+    // Set once the password is verified, until the second factor is.
+    const PENDING_USER_ID_KEY: &'static str = "pending_two_factor_user_id";
+    const PENDING_SINCE_KEY: &'static str = "pending_two_factor_since";
+    const TOTP_ENROLLMENT_SECRET_KEY: &'static str = "totp_enrollment_secret";
+    // Shown once, on the page the user is redirected to after creating it.
+    const NEW_API_TOKEN_KEY: &'static str = "new_api_token";
 
     pub fn renew(&self) {
         self.0.renew();
     }
 
+    /// Also starts the clocks of the absolute lifetime and of the idle timeout.
     pub fn insert_user_id(&self, user_id: Uuid) -> Result<(), SessionInsertError> {
+        let now = Utc::now();
+        self.0.insert(Self::AUTHENTICATED_AT_KEY, now)?;
+        self.0.insert(Self::LAST_ACTIVITY_KEY, now)?;
         self.0.insert(Self::USER_ID_KEY, user_id)
     }
 
@@ -22,6 +40,92 @@ impl TypedSession {
         self.0.get(Self::USER_ID_KEY)
     }
 
+    pub fn get_authenticated_at(&self) -> Result<Option<DateTime<Utc>>, SessionGetError> {
+        self.0.get(Self::AUTHENTICATED_AT_KEY)
+    }
+
+    pub fn get_last_activity(&self) -> Result<Option<DateTime<Utc>>, SessionGetError> {
+        self.0.get(Self::LAST_ACTIVITY_KEY)
+    }
+
+    pub fn insert_last_activity(&self, at: DateTime<Utc>) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::LAST_ACTIVITY_KEY, at)
+    }
+
+    /// The token expected in the forms submitted with this session,
+    /// created the first time it is needed.
+    pub fn get_or_insert_csrf_token(&self) -> Result<String, anyhow::Error> {
+        if let Some(token) = self.0.get::<String>(Self::CSRF_TOKEN_KEY)? {
+            return Ok(token);
+        }
+        let token = generate_token();
+        self.0.insert(Self::CSRF_TOKEN_KEY, &token)?;
+        Ok(token)
+    }
+
+    /// The id of the session in `user_sessions`, used to revoke it.
+    pub fn insert_session_id(&self, session_id: Uuid) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::SESSION_ID_KEY, session_id)
+    }
+
+    pub fn get_session_id(&self) -> Result<Option<Uuid>, SessionGetError> {
+        self.0.get(Self::SESSION_ID_KEY)
+    }
+
+    /// Also records when the password was verified, for the second factor
+    /// to be entered shortly after.
+    pub fn insert_pending_user_id(&self, user_id: Uuid) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::PENDING_SINCE_KEY, Utc::now())?;
+        self.0.insert(Self::PENDING_USER_ID_KEY, user_id)
+    }
+
+    pub fn get_pending_user_id(&self) -> Result<Option<Uuid>, SessionGetError> {
+        self.0.get(Self::PENDING_USER_ID_KEY)
+    }
+
+    pub fn get_pending_since(&self) -> Result<Option<DateTime<Utc>>, SessionGetError> {
+        self.0.get(Self::PENDING_SINCE_KEY)
+    }
+
+    pub fn remove_pending_user_id(&self) {
+        self.0.remove(Self::PENDING_USER_ID_KEY);
+        self.0.remove(Self::PENDING_SINCE_KEY);
+    }
+
+    pub fn insert_totp_enrollment_secret(
+        &self,
+        secret: &Secret<String>,
+    ) -> Result<(), SessionInsertError> {
+        self.0
+            .insert(Self::TOTP_ENROLLMENT_SECRET_KEY, secret.expose_secret())
+    }
+
+    pub fn get_totp_enrollment_secret(&self) -> Result<Option<Secret<String>>, SessionGetError> {
+        Ok(self
+            .0
+            .get::<String>(Self::TOTP_ENROLLMENT_SECRET_KEY)?
+            .map(Secret::new))
+    }
+
+    pub fn remove_totp_enrollment_secret(&self) {
+        self.0.remove(Self::TOTP_ENROLLMENT_SECRET_KEY);
+    }
+
+    pub fn insert_new_api_token(
+        &self,
+        api_token: &Secret<String>,
+    ) -> Result<(), SessionInsertError> {
+        self.0
+            .insert(Self::NEW_API_TOKEN_KEY, api_token.expose_secret())
+    }
+
+    /// The token is removed from the session: it can only be read once.
+    pub fn take_new_api_token(&self) -> Result<Option<Secret<String>>, SessionGetError> {
+        let api_token = self.0.get::<String>(Self::NEW_API_TOKEN_KEY)?;
+        self.0.remove(Self::NEW_API_TOKEN_KEY);
+        Ok(api_token.map(Secret::new))
+    }
+
     pub fn log_out(self) {
         self.0.purge()
     }
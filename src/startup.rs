@@ -1,4 +1,3 @@
-use actix_session::SessionMiddleware;
 use actix_session::storage::RedisSessionStore;
 use actix_web::cookie::Key;
 use actix_web::dev::Server;
@@ -13,14 +12,44 @@ use sqlx::postgres::PgPoolOptions;
 use std::net::TcpListener;
 use tracing_actix_web::TracingLogger;
 
-use crate::authentication::reject_anonymous_users;
-use crate::configuration::{DatabaseSettings, Settings};
+use crate::anti_abuse::{LoginThrottle, RateLimit, RateLimiter, SubscriptionGuard};
+use crate::authentication::{
+    PasswordHashing, PasswordPolicy, reject_anonymous_users, reject_invalid_api_tokens,
+    require_editor, require_owner, verify_csrf_token,
+};
+use crate::client_ip::TrustedProxies;
+use crate::configuration::{
+    AntiAbuseSettings, ApplicationSettings, DatabaseSettings, IdempotencySettings, Settings,
+};
+use crate::consent::SubscriptionSources;
 use crate::email_client::EmailClient;
+use crate::idempotency::idempotent;
+use crate::routes::{
+    ApiError, create_api_token_admin, get_issue_delivery_status, list_api_tokens,
+    list_subscribers_api, publish_issue, revoke_api_token_admin,
+};
+use crate::routes::{
+    accept_invitation, accept_invitation_form, create_user_admin, delete_user, disable_user,
+    enable_user, invite_user, list_users,
+};
 use crate::routes::{
-    admin_dashboard, change_password, change_password_form, log_out, publish_newsletter_form,
+    admin_dashboard, change_password, change_password_form, erase_subscriber_data_admin,
+    export_newsletter_issues, export_subscriber_data, export_subscribers, import_subscribers,
+    import_subscribers_form, list_subscribers, log_out, login_lockouts, publish_newsletter_form,
+    subscriber_data_form, subscriber_details,
 };
 use crate::routes::{
-    confirm, health_check, home, login, login_form, publish_newsletter, subscribe,
+    atom_feed, confirm, consent_text, erase_subscriber_data, health_check, home,
+    idempotent_publish_newsletter, issue_page, list_issues, login, login_form, openapi_document,
+    publish_newsletter, rss_feed, subscribe, subscriber_data,
+};
+use crate::routes::{
+    change_email, change_email_form, list_sessions, log_out_other_sessions, log_out_session,
+    password_reset_form, request_password_reset, request_password_reset_form, reset_password,
+};
+use crate::routes::{
+    disable_two_factor_authentication, enable_two_factor_authentication, login_two_factor,
+    login_two_factor_form, two_factor_form,
 };
 
 pub struct Application {
@@ -41,9 +70,12 @@ impl Application {
             listener,
             connection_pool,
             email_client,
-            configuration.application.base_url,
-            configuration.application.hmac_secret,
+            configuration.application,
             configuration.redis_uri,
+            configuration.anti_abuse,
+            configuration.password_hashing.hashing()?,
+            configuration.password_policy.policy()?,
+            configuration.idempotency,
         )
         .await?;
 
@@ -72,48 +104,220 @@ pub struct ApplicationBaseUrl(pub String);
 #[derive(Clone)]
 pub struct HmacSecret(pub Secret<String>);
 
+pub struct PasswordResetRateLimit(pub RateLimit);
+
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
     listener: TcpListener,
     db_pool: PgPool,
     email_client: EmailClient,
-    base_url: String,
-    hmac_secret: Secret<String>,
+    application: ApplicationSettings,
     redis_uri: Secret<String>,
+    anti_abuse: AntiAbuseSettings,
+    password_hashing: PasswordHashing,
+    password_policy: PasswordPolicy,
+    idempotency: IdempotencySettings,
 ) -> Result<Server, anyhow::Error> {
+    let ApplicationSettings {
+        base_url,
+        hmac_secret,
+        session: session_settings,
+        cors: cors_settings,
+        trusted_proxies,
+        subscription_sources,
+        ..
+    } = application;
     let db_pool = Data::new(db_pool);
     let email_client = Data::new(email_client);
     let base_url = Data::new(ApplicationBaseUrl(base_url));
+    let trusted_proxies = Data::new(TrustedProxies(trusted_proxies));
+    let subscription_sources = Data::new(SubscriptionSources(subscription_sources));
     let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
     let redis_store = RedisSessionStore::new(redis_uri.expose_secret()).await?;
+    let rate_limiter =
+        RateLimiter::new(&redis_uri, anti_abuse.rate_limit_key_prefix.clone()).await?;
+    let subscription_guard = Data::new(SubscriptionGuard::new(rate_limiter.clone(), &anti_abuse));
+    let login_throttle = Data::new(LoginThrottle::new(
+        rate_limiter.clone(),
+        anti_abuse.login.clone(),
+    ));
+    let rate_limiter = Data::new(rate_limiter);
+    let password_reset_rate_limit =
+        Data::new(PasswordResetRateLimit(anti_abuse.password_resets_per_email));
+    let session_settings = Data::new(session_settings);
+    let password_hashing = Data::new(password_hashing);
+    let password_policy = Data::new(password_policy);
+    let idempotency = Data::new(idempotency);
     let server = HttpServer::new(move || {
         App::new()
             .wrap(message_framework.clone())
-            .wrap(SessionMiddleware::new(
-                redis_store.clone(),
-                secret_key.clone(),
-            ))
+            .wrap(session_settings.middleware(redis_store.clone(), secret_key.clone()))
             .wrap(TracingLogger::default())
             .route("/health_check", web::get().to(health_check))
-            .route("/subscriptions", web::post().to(subscribe))
-            .route("/subscriptions/confirm", web::get().to(confirm))
+            .route("/api/openapi.json", web::get().to(openapi_document))
+            .service(
+                web::resource("/subscriptions")
+                    .wrap(cors_settings.middleware(&base_url.0))
+                    .route(web::post().to(subscribe)),
+            )
+            .service(
+                web::resource("/subscriptions/consent")
+                    .wrap(cors_settings.middleware(&base_url.0))
+                    .route(web::get().to(consent_text)),
+            )
+            .service(
+                web::resource("/subscriptions/confirm")
+                    .wrap(cors_settings.middleware(&base_url.0))
+                    .route(web::get().to(confirm)),
+            )
+            .route("/subscriptions/data", web::get().to(subscriber_data))
+            .route(
+                "/subscriptions/erase",
+                web::post().to(erase_subscriber_data),
+            )
             .route("/", web::get().to(home))
+            .route("/issues", web::get().to(list_issues))
+            .route("/issues/{slug}", web::get().to(issue_page))
+            .route("/feed.rss", web::get().to(rss_feed))
+            .route("/feed.atom", web::get().to(atom_feed))
             .route("/login", web::get().to(login_form))
             .route("/login", web::post().to(login))
+            .route("/login/two-factor", web::get().to(login_two_factor_form))
+            .route("/login/two-factor", web::post().to(login_two_factor))
+            .route("/invitations/accept", web::get().to(accept_invitation_form))
+            .route("/invitations/accept", web::post().to(accept_invitation))
+            .route(
+                "/password-reset",
+                web::get().to(request_password_reset_form),
+            )
+            .route("/password-reset", web::post().to(request_password_reset))
+            .route(
+                "/password-reset/confirm",
+                web::get().to(password_reset_form),
+            )
+            .route("/password-reset/confirm", web::post().to(reset_password))
+            .service(
+                web::scope("/api/v1")
+                    .wrap(from_fn(reject_invalid_api_tokens))
+                    .app_data(
+                        web::JsonConfig::default()
+                            .error_handler(|e, _| ApiError::ValidationError(e.to_string()).into()),
+                    )
+                    .route(
+                        "/issues",
+                        web::post().to(publish_issue).wrap(from_fn(idempotent)),
+                    )
+                    .route(
+                        "/issues/{newsletter_issue_id}",
+                        web::get().to(get_issue_delivery_status),
+                    )
+                    .route("/subscribers", web::get().to(list_subscribers_api)),
+            )
             .service(
                 web::scope("/admin")
+                    .wrap(from_fn(verify_csrf_token))
                     .wrap(from_fn(reject_anonymous_users))
                     .route("/dashboard", web::get().to(admin_dashboard))
                     .route("/password", web::get().to(change_password_form))
                     .route("/password", web::post().to(change_password))
+                    .route("/email", web::get().to(change_email_form))
+                    .route("/email", web::post().to(change_email))
+                    .route("/sessions", web::get().to(list_sessions))
+                    .route(
+                        "/sessions/logout-others",
+                        web::post().to(log_out_other_sessions),
+                    )
+                    .route(
+                        "/sessions/{session_id}/logout",
+                        web::post().to(log_out_session),
+                    )
                     .route("/logout", web::post().to(log_out))
+                    .route("/api-tokens", web::get().to(list_api_tokens))
+                    .route("/api-tokens", web::post().to(create_api_token_admin))
+                    .route(
+                        "/api-tokens/{api_token_id}/revoke",
+                        web::post().to(revoke_api_token_admin),
+                    )
+                    .route(
+                        "/lockouts",
+                        web::get().to(login_lockouts).wrap(from_fn(require_owner)),
+                    )
+                    .route("/two-factor", web::get().to(two_factor_form))
+                    .route(
+                        "/two-factor/enable",
+                        web::post().to(enable_two_factor_authentication),
+                    )
+                    .route(
+                        "/two-factor/disable",
+                        web::post().to(disable_two_factor_authentication),
+                    )
                     .route("/newsletters", web::get().to(publish_newsletter_form))
-                    .route("/newsletters", web::post().to(publish_newsletter)),
+                    .route(
+                        "/newsletters",
+                        web::post()
+                            .to(publish_newsletter)
+                            .wrap(from_fn(idempotent_publish_newsletter))
+                            .wrap(from_fn(require_editor)),
+                    )
+                    .route(
+                        "/newsletters/export",
+                        web::get().to(export_newsletter_issues),
+                    )
+                    .route("/subscribers", web::get().to(list_subscribers))
+                    .route(
+                        "/subscribers/{subscriber_id:[0-9a-fA-F-]{36}}",
+                        web::get().to(subscriber_details),
+                    )
+                    .route("/subscribers/export", web::get().to(export_subscribers))
+                    .route("/subscribers/data", web::get().to(subscriber_data_form))
+                    .route(
+                        "/subscribers/data/export",
+                        web::get().to(export_subscriber_data),
+                    )
+                    .route(
+                        "/subscribers/data/erase",
+                        web::post()
+                            .to(erase_subscriber_data_admin)
+                            .wrap(from_fn(require_editor)),
+                    )
+                    .service(
+                        web::resource("/subscribers/import")
+                            // A CSV with thousands of subscribers does not fit
+                            // in the default 16KB form payload limit.
+                            .app_data(web::FormConfig::default().limit(10 * 1024 * 1024))
+                            .route(web::get().to(import_subscribers_form))
+                            .route(
+                                web::post()
+                                    .to(import_subscribers)
+                                    .wrap(from_fn(require_editor)),
+                            ),
+                    )
+                    .service(
+                        web::scope("/users")
+                            .wrap(from_fn(require_owner))
+                            .route("", web::get().to(list_users))
+                            .route("", web::post().to(create_user_admin))
+                            .route("/invite", web::post().to(invite_user))
+                            .route("/{user_id}/disable", web::post().to(disable_user))
+                            .route("/{user_id}/enable", web::post().to(enable_user))
+                            .route("/{user_id}/delete", web::post().to(delete_user)),
+                    ),
             )
             .app_data(db_pool.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
+            .app_data(trusted_proxies.clone())
+            .app_data(subscription_guard.clone())
+            .app_data(subscription_sources.clone())
+            .app_data(login_throttle.clone())
+            .app_data(rate_limiter.clone())
+            .app_data(password_reset_rate_limit.clone())
+            .app_data(session_settings.clone())
+            .app_data(password_hashing.clone())
+            .app_data(password_policy.clone())
+            .app_data(idempotency.clone())
             .app_data(Data::new(HmacSecret(hmac_secret.clone())))
     })
     .listen(listener)?
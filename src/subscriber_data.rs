@@ -0,0 +1,191 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool};
+use uuid::Uuid;
+
+use crate::consent::{ConsentEventRecord, get_consent_events};
+
+/// Everything we hold about a subscriber, as handed out to data-subject
+/// access requests.
+#[derive(serde::Serialize)]
+pub struct SubscriberDataBundle {
+    pub subscription: SubscriptionRecord,
+    pub subscription_tokens: Vec<String>,
+    pub consent_events: Vec<ConsentEventRecord>,
+    pub pending_deliveries: Vec<PendingDeliveryRecord>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct SubscriptionRecord {
+    pub id: Uuid,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: DateTime<Utc>,
+    pub tags: Vec<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct PendingDeliveryRecord {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+}
+
+#[tracing::instrument(name = "Collect subscriber data", skip(pool, email))]
+pub async fn get_subscriber_data(
+    pool: &PgPool,
+    email: &str,
+) -> Result<Option<SubscriberDataBundle>, anyhow::Error> {
+    let subscription = sqlx::query_as!(
+        SubscriptionRecord,
+        r#"
+        SELECT id, email, name, status, subscribed_at, tags
+        FROM subscriptions
+        WHERE email = $1
+        "#,
+        email
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to fetch the subscription.")?;
+    let Some(subscription) = subscription else {
+        return Ok(None);
+    };
+
+    let subscription_tokens = sqlx::query!(
+        r#"
+        SELECT subscription_token
+        FROM subscription_tokens
+        WHERE subscriber_id = $1
+        "#,
+        subscription.id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the subscription tokens.")?
+    .into_iter()
+    .map(|r| r.subscription_token)
+    .collect();
+
+    let consent_events = get_consent_events(pool, subscription.id)
+        .await
+        .context("Failed to fetch the consent events.")?;
+
+    let pending_deliveries = sqlx::query_as!(
+        PendingDeliveryRecord,
+        r#"
+        SELECT q.newsletter_issue_id, i.title
+        FROM issue_delivery_queue q
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        WHERE q.subscriber_email = $1
+        "#,
+        email
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch the pending deliveries.")?;
+
+    Ok(Some(SubscriberDataBundle {
+        subscription,
+        subscription_tokens,
+        consent_events,
+        pending_deliveries,
+    }))
+}
+
+/// Remove every row that references the subscriber.
+///
+/// Issue statistics are plain counters on `newsletter_issues`, so they are
+/// kept; deliveries still pending for the subscriber are counted as dropped.
+/// Returns `false` if there was no subscriber with the given email.
+#[tracing::instrument(name = "Erase subscriber data", skip(pool, email))]
+pub async fn erase_subscriber(pool: &PgPool, email: &str) -> Result<bool, anyhow::Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+    let subscriber = sqlx::query!(
+        r#"
+        SELECT id
+        FROM subscriptions
+        WHERE email = $1
+        FOR UPDATE
+        "#,
+        email
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .context("Failed to fetch the subscription.")?;
+    let Some(subscriber) = subscriber else {
+        return Ok(false);
+    };
+
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            WITH dropped AS (
+                DELETE FROM issue_delivery_queue
+                WHERE subscriber_email = $1
+                RETURNING newsletter_issue_id
+            )
+            UPDATE newsletter_issues i
+            SET dropped_count = dropped_count + d.count
+            FROM (
+                SELECT newsletter_issue_id, count(*)::INTEGER as count
+                FROM dropped
+                GROUP BY newsletter_issue_id
+            ) d
+            WHERE i.newsletter_issue_id = d.newsletter_issue_id
+            "#,
+            email
+        ))
+        .await
+        .context("Failed to delete the pending deliveries.")?;
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM subscription_tokens WHERE subscriber_id = $1",
+            subscriber.id
+        ))
+        .await
+        .context("Failed to delete the subscription tokens.")?;
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM subscription_consent_events WHERE subscriber_id = $1",
+            subscriber.id
+        ))
+        .await
+        .context("Failed to delete the consent events.")?;
+    transaction
+        .execute(sqlx::query!(
+            "DELETE FROM subscriptions WHERE id = $1",
+            subscriber.id
+        ))
+        .await
+        .context("Failed to delete the subscription.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to erase a subscriber")?;
+
+    Ok(true)
+}
+
+#[tracing::instrument(name = "Get subscriber email from token", skip_all)]
+pub async fn get_subscriber_email_from_token(
+    pool: &PgPool,
+    subscription_token: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        SELECT s.email
+        FROM subscription_tokens t
+        JOIN subscriptions s ON s.id = t.subscriber_id
+        WHERE t.subscription_token = $1
+        "#,
+        subscription_token,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(result.map(|r| r.email))
+}
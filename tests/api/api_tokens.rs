@@ -0,0 +1,435 @@
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{TestApp, TestUser, assert_is_redirect_to, spawn_app};
+
+async fn create_confirmed_subscriber(app: &TestApp) {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, $2, 'Le Guin', now(), 'confirmed')
+        "#,
+        Uuid::new_v4(),
+        format!("{}@example.com", Uuid::new_v4())
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+fn issue_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    })
+}
+
+async fn post_issue(app: &TestApp, api_token: &str, idempotency_key: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("{}/api/v1/issues", &app.address))
+        .bearer_auth(api_token)
+        .header("Idempotency-Key", idempotency_key)
+        .json(&issue_body())
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn get_api(app: &TestApp, api_token: &str, path: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}/api/v1{}", &app.address, path))
+        .bearer_auth(api_token)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn requests_without_a_valid_api_token_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for api_token in [None, Some("z2p_not-a-real-token")] {
+        // Act
+        let mut request =
+            reqwest::Client::new().get(format!("{}/api/v1/subscribers", &app.address));
+        if let Some(api_token) = api_token {
+            request = request.bearer_auth(api_token);
+        }
+        let response = request.send().await.unwrap();
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(response.headers()["WWW-Authenticate"], "Bearer");
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body["error"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn issues_can_be_published_and_tracked_through_the_api() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let api_token = app.create_api_token(&["issues:read", "issues:write"]).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Publish the issue
+    let response = post_issue(&app, &api_token, &Uuid::new_v4().to_string()).await;
+    assert_eq!(response.status().as_u16(), 202);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let issue_id = body["newsletter_issue_id"].as_str().unwrap().to_owned();
+
+    // Act - Part 2 - Check the delivery status
+    let status: serde_json::Value = get_api(&app, &api_token, &format!("/issues/{issue_id}"))
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["recipients_count"], 1);
+    assert_eq!(status["pending_count"], 1);
+
+    // Act - Part 3 - Deliver it
+    app.dispatch_all_pending_emails().await;
+    let status: serde_json::Value = get_api(&app, &api_token, &format!("/issues/{issue_id}"))
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["delivered_count"], 1);
+    assert_eq!(status["pending_count"], 0);
+    // Mock verifies on Drop that we have sent the newsletter email **once**
+}
+
+#[tokio::test]
+async fn publishing_through_the_api_is_idempotent() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let api_token = app.create_api_token(&["issues:write"]).await;
+    let idempotency_key = Uuid::new_v4().to_string();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = post_issue(&app, &api_token, &idempotency_key).await;
+    assert_eq!(response.status().as_u16(), 202);
+    let first_body = response.text().await.unwrap();
+    let response = post_issue(&app, &api_token, &idempotency_key).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    // Assert
+    assert_eq!(response.text().await.unwrap(), first_body);
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that we have sent the newsletter email **once**
+}
+
+#[tokio::test]
+async fn reusing_an_idempotency_key_with_a_different_issue_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let api_token = app.create_api_token(&["issues:write"]).await;
+    let idempotency_key = Uuid::new_v4().to_string();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let response = post_issue(&app, &api_token, &idempotency_key).await;
+    assert_eq!(response.status().as_u16(), 202);
+
+    // Act
+    let mut other_issue = issue_body();
+    other_issue["title"] = "Another title".into();
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/v1/issues", &app.address))
+        .bearer_auth(&api_token)
+        .header("Idempotency-Key", &idempotency_key)
+        .json(&other_issue)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 422);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body["error"],
+        "The idempotency key was already used with a different request."
+    );
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that we have sent the newsletter email **once**
+}
+
+#[tokio::test]
+async fn an_idempotency_key_used_on_another_route_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let api_token = app.create_api_token(&["issues:write"]).await;
+    let idempotency_key = Uuid::new_v4().to_string();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let response = app
+        .post_newsletters(&format!(
+            "title=Newsletter title\
+            &text_content=Newsletter body as plain text\
+            &html_content=<p>Newsletter body as HTML</p>\
+            &idempotency_key={idempotency_key}",
+        ))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act
+    let response = post_issue(&app, &api_token, &idempotency_key).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 422);
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that we have sent the newsletter email **once**
+}
+
+#[tokio::test]
+async fn publishing_requires_an_idempotency_key() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let api_token = app.create_api_token(&["issues:write"]).await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/v1/issues", &app.address))
+        .bearer_auth(&api_token)
+        .json(&issue_body())
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"], "Missing Idempotency-Key header.");
+}
+
+#[tokio::test]
+async fn issues_can_be_written_in_markdown_through_the_api() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let api_token = app.create_api_token(&["issues:write"]).await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/v1/issues", &app.address))
+        .bearer_auth(&api_token)
+        .header("Idempotency-Key", Uuid::new_v4().to_string())
+        .json(&serde_json::json!({
+            "title": "Newsletter title",
+            "markdown_content": "- One\n- Two",
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 202);
+    let issue = sqlx::query!("SELECT html_content, text_content FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(issue.html_content.contains("<li>One</li>"));
+    assert_eq!(issue.text_content, "- One\n- Two");
+}
+
+#[tokio::test]
+async fn issues_with_scripts_are_rejected_through_the_api() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let api_token = app.create_api_token(&["issues:write"]).await;
+    let mut issue = issue_body();
+    issue["html_content"] = "<p>Hello!</p><script>alert(1)</script>".into();
+
+    // Act
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/v1/issues", &app.address))
+        .bearer_auth(&api_token)
+        .header("Idempotency-Key", Uuid::new_v4().to_string())
+        .json(&issue)
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body["error"],
+        "The content cannot contain `script` elements."
+    );
+}
+
+#[tokio::test]
+async fn invalid_bodies_are_rejected_with_a_json_error() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let api_token = app.create_api_token(&["issues:write"]).await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/v1/issues", &app.address))
+        .bearer_auth(&api_token)
+        .header("Idempotency-Key", Uuid::new_v4().to_string())
+        .json(&serde_json::json!({ "title": "Newsletter title" }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(body["error"].is_string());
+}
+
+#[tokio::test]
+async fn tokens_can_only_use_their_scopes() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let api_token = app.create_api_token(&["subscribers:read"]).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = post_issue(&app, &api_token, &Uuid::new_v4().to_string()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 403);
+    let response = get_api(&app, &api_token, "/subscribers").await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn subscribers_can_be_listed_through_the_api() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let api_token = app.create_api_token(&["subscribers:read"]).await;
+
+    // Act
+    let response = get_api(&app, &api_token, "/subscribers").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["subscribers"].as_array().unwrap().len(), 1);
+    assert_eq!(body["subscribers"][0]["status"], "confirmed");
+    assert_eq!(body["has_next_page"], false);
+}
+
+#[tokio::test]
+async fn revoked_tokens_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let api_token = app.create_api_token(&["subscribers:read"]).await;
+    let api_token_id: Uuid = sqlx::query_scalar!(
+        "SELECT api_token_id FROM api_tokens WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let response = app
+        .post_api_tokens(&format!("/{api_token_id}/revoke"), &[])
+        .await;
+    assert_is_redirect_to(&response, "/admin/api-tokens");
+
+    // Assert
+    let html_page = app.get_api_tokens_html().await;
+    assert!(html_page.contains("<p><i>The API token has been revoked.</i></p>"));
+    let response = get_api(&app, &api_token, "/subscribers").await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn tokens_cannot_grant_more_than_the_role_of_their_user() {
+    // Arrange
+    let app = spawn_app().await;
+    let viewer = TestUser::generate();
+    viewer.store_with_role(&app.db_pool, "viewer").await;
+    viewer.login(&app).await;
+
+    // Act
+    let response = app
+        .post_api_tokens("", &[("name", "ci"), ("scope", "issues:write")])
+        .await;
+    assert_is_redirect_to(&response, "/admin/api-tokens");
+
+    // Assert
+    let html_page = app.get_api_tokens_html().await;
+    assert!(
+        html_page.contains("<p><i>The viewer role cannot grant the issues:write scope.</i></p>")
+    );
+    assert!(!html_page.contains("<code>z2p_"));
+}
+
+#[tokio::test]
+async fn a_new_token_is_only_shown_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - The page the user is redirected to
+    let api_token = app.create_api_token(&["subscribers:read"]).await;
+
+    // Act - Part 2 - Coming back later
+    let html_page = app.get_api_tokens_html().await;
+    assert!(!html_page.contains(&api_token));
+}
+
+#[tokio::test]
+async fn submitted_values_are_escaped_in_error_messages() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    app.post_api_tokens("", &[("name", "ci"), ("scope", "<b onmouseover=alert(1)>")])
+        .await;
+
+    // Assert
+    let html_page = app.get_api_tokens_html().await;
+    assert!(html_page.contains("&lt;b onmouseover=alert(1)&gt; is not a valid API token scope."));
+    assert!(!html_page.contains("<b onmouseover=alert(1)>"));
+}
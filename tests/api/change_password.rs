@@ -1,6 +1,7 @@
+use sha1::{Digest, Sha1};
 use uuid::Uuid;
 
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::helpers::{assert_is_redirect_to, spawn_app, spawn_app_with};
 
 #[tokio::test]
 async fn you_must_be_logged_in_to_see_the_change_password_form() {
@@ -122,7 +123,7 @@ async fn new_password_length_is_invalid() {
     // Act - Part 3
     let html_page = app.get_change_password_html().await;
     assert!(html_page.contains(
-        "<p><i>The new password's length is invalid - \
+        "<p><i>The password's length is invalid - \
         It has to be in the range of 12 to 128 characters</i></p>"
     ));
 }
@@ -171,3 +172,84 @@ async fn changing_password_works() {
     let response = app.post_login(&login_body).await;
     assert_is_redirect_to(&response, "/admin/dashboard");
 }
+
+#[tokio::test]
+async fn new_password_must_not_be_easy_to_guess() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_change_password(&serde_json::json!({
+        "current_password": &app.test_user.password,
+        "new_password": "password1234",
+        "new_password_check": "password1234",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    // Assert
+    let html_page = app.get_change_password_html().await;
+    assert!(html_page.contains(
+        "<p><i>The password is too easy to guess - try adding more words or characters.</i></p>"
+    ));
+}
+
+#[tokio::test]
+async fn new_password_must_not_contain_the_username() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let new_password = format!("{}-{}", app.test_user.username, Uuid::new_v4());
+
+    // Act
+    let response = app
+        .post_change_password(&serde_json::json!({
+        "current_password": &app.test_user.password,
+        "new_password": &new_password,
+        "new_password_check": &new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    // Assert
+    let html_page = app.get_change_password_html().await;
+    assert!(html_page.contains("<p><i>The password cannot contain the username.</i></p>"));
+}
+
+#[tokio::test]
+async fn new_password_must_not_be_breached() {
+    // Arrange
+    let new_password = Uuid::new_v4().to_string();
+    let breached_passwords_path =
+        std::env::temp_dir().join(format!("breached-{}.txt", Uuid::new_v4()));
+    std::fs::write(
+        &breached_passwords_path,
+        format!("{:X}:42\n", Sha1::digest(new_password.as_bytes())),
+    )
+    .unwrap();
+    let app = spawn_app_with(|c| {
+        c.password_policy.breached_passwords_path =
+            Some(breached_passwords_path.to_string_lossy().into_owned())
+    })
+    .await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_change_password(&serde_json::json!({
+        "current_password": &app.test_user.password,
+        "new_password": &new_password,
+        "new_password_check": &new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    // Assert
+    let html_page = app.get_change_password_html().await;
+    assert!(html_page.contains(
+        "<p><i>The password appears in a list of breached passwords - please choose another one.</i></p>"
+    ));
+    std::fs::remove_file(breached_passwords_path).unwrap();
+}
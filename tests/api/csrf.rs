@@ -0,0 +1,115 @@
+use uuid::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn admin_forms_without_a_csrf_token_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let new_password = Uuid::new_v4().to_string();
+
+    // Act - Part 1 - Submit a form without the token
+    let response = app
+        .api_client
+        .post(format!("{}/admin/password", &app.address))
+        .form(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(
+        "<p><i>Your request could not be verified. \
+        Please reload the page and try again.</i></p>"
+    ));
+
+    // Act - Part 3 - The password has not changed
+    app.post_logout().await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn a_wrong_csrf_token_redirects_back_to_the_form() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/admin/logout", &app.address))
+        .header("Referer", format!("{}/admin/sessions", &app.address))
+        .form(&serde_json::json!({ "csrf_token": "not-the-token" }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/sessions");
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn the_csrf_token_of_the_rendered_form_is_accepted() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let html_page = app.get_change_password_html().await;
+    let csrf_token = html_page
+        .split(r#"name="csrf_token" value=""#)
+        .nth(1)
+        .and_then(|s| s.split('"').next())
+        .expect("The form has no CSRF token.")
+        .to_owned();
+    let new_password = Uuid::new_v4().to_string();
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/admin/password", &app.address))
+        .form(&serde_json::json!({
+            "csrf_token": &csrf_token,
+            "current_password": &app.test_user.password,
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/password");
+    let html_page = app.get_change_password_html().await;
+    assert!(html_page.contains("<p><i>Your password has been changed.</i></p>"));
+}
+
+#[tokio::test]
+async fn the_csrf_token_is_bound_to_the_session() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let first_token = app.csrf_token().await;
+
+    // Act
+    app.post_logout().await;
+    app.test_user.login(&app).await;
+
+    // Assert
+    let second_token = app.csrf_token().await;
+    assert!(!first_token.is_empty());
+    assert_ne!(first_token, second_token);
+}
@@ -0,0 +1,190 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+async fn import_confirmed_subscribers(app: &TestApp, csv_content: &str) {
+    let response = app
+        .post_import_subscribers(&serde_json::json!({
+            "csv_content": csv_content,
+            "default_status": "confirmed",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_export_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_subscribers_export("csv").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_export_newsletter_issues() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_newsletter_issues_export("csv").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_are_exported_as_csv() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    import_confirmed_subscribers(
+        &app,
+        "email,name,tags\nursula@example.com,Ursula Le Guin,beta;vip\n",
+    )
+    .await;
+
+    // Act
+    let response = app.get_subscribers_export("csv").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Disposition"],
+        r#"attachment; filename="subscribers.csv""#
+    );
+    let body = response.text().await.unwrap();
+    let mut lines = body.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "id,email,name,status,subscribed_at,tags"
+    );
+    let row = lines.next().unwrap();
+    assert!(row.contains(",ursula@example.com,Ursula Le Guin,confirmed,"));
+    assert!(row.ends_with(",beta;vip"));
+    assert!(lines.next().is_none());
+}
+
+#[tokio::test]
+async fn values_that_look_like_formulas_are_not_exported_as_formulas() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    import_confirmed_subscribers(&app, "email,name\nursula@example.com,=1+2\n").await;
+
+    // Act - Part 1 - CSV
+    let response = app.get_subscribers_export("csv").await;
+
+    // Assert - Part 1
+    let body = response.text().await.unwrap();
+    let row = body.lines().nth(1).unwrap();
+    assert!(row.contains(",ursula@example.com,'=1+2,confirmed,"));
+
+    // Act - Part 2 - JSON is not interpreted by spreadsheets
+    let response = app.get_subscribers_export("json").await;
+
+    // Assert - Part 2
+    let subscribers: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(subscribers[0]["name"], "=1+2");
+}
+
+#[tokio::test]
+async fn subscribers_are_exported_as_json() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    import_confirmed_subscribers(
+        &app,
+        "email,name,tags\n\
+        ursula@example.com,Ursula Le Guin,beta;vip\n\
+        octavia@example.com,Octavia Butler,\n",
+    )
+    .await;
+
+    // Act
+    let response = app.get_subscribers_export("json").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let subscribers = body.as_array().unwrap();
+    assert_eq!(subscribers.len(), 2);
+    assert!(subscribers.iter().any(|s| {
+        s["email"] == "ursula@example.com"
+            && s["status"] == "confirmed"
+            && s["tags"] == serde_json::json!(["beta", "vip"])
+    }));
+    assert!(
+        subscribers
+            .iter()
+            .any(|s| { s["email"] == "octavia@example.com" && s["tags"] == serde_json::json!([]) })
+    );
+}
+
+#[tokio::test]
+async fn an_empty_json_export_is_a_valid_document() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_subscribers_export("json").await;
+
+    // Assert
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body, serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn newsletter_issues_are_exported_with_their_delivery_stats() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    import_confirmed_subscribers(
+        &app,
+        "email,name\nursula@example.com,Ursula Le Guin\noctavia@example.com,Octavia Butler\n",
+    )
+    .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(&format!(
+        "title=Hello!&html_content=<p>Hello!</p>&text_content=Hello!&idempotency_key={}",
+        uuid::Uuid::new_v4()
+    ))
+    .await;
+
+    // Act - Part 1 - Before delivery
+    let response = app.get_newsletter_issues_export("json").await;
+    let body: serde_json::Value = response.json().await.unwrap();
+    let issue = &body.as_array().unwrap()[0];
+    assert_eq!(issue["title"], "Hello!");
+    assert_eq!(issue["recipients_count"], 2);
+    assert_eq!(issue["pending_count"], 2);
+    assert_eq!(issue["delivered_count"], 0);
+
+    // Act - Part 2 - After delivery
+    app.dispatch_all_pending_emails().await;
+    let response = app.get_newsletter_issues_export("json").await;
+    let body: serde_json::Value = response.json().await.unwrap();
+    let issue = &body.as_array().unwrap()[0];
+    assert_eq!(issue["pending_count"], 0);
+    assert_eq!(issue["delivered_count"], 1);
+    assert_eq!(issue["failed_count"], 1);
+}
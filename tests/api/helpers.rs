@@ -5,7 +5,8 @@ use std::sync::LazyLock;
 use uuid::Uuid;
 use wiremock::MockServer;
 use zero2prod::{
-    configuration::{DatabaseSettings, get_configuration},
+    configuration::{DatabaseSettings, Settings, get_configuration},
+    consent::ConsentText,
     email_client::EmailClient,
     issue_delivery_worker::{ExecutionOutcome, try_execute_task},
     startup::{Application, get_connection_pool},
@@ -47,7 +48,7 @@ impl TestApp {
     pub async fn dispatch_all_pending_emails(&self) {
         loop {
             if let ExecutionOutcome::EmptyQueue =
-                try_execute_task(&self.db_pool, &self.email_client)
+                try_execute_task(&self.db_pool, &self.email_client, &self.address)
                     .await
                     .unwrap()
             {
@@ -56,6 +57,29 @@ impl TestApp {
         }
     }
 
+    /// The CSRF token of the current session, if logged in.
+    pub async fn csrf_token(&self) -> String {
+        self.csrf_token_for(&self.api_client).await
+    }
+
+    /// The CSRF token of the session of another client.
+    pub async fn csrf_token_for(&self, client: &reqwest::Client) -> String {
+        let html_page = client
+            .get(format!("{}/admin/dashboard", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap();
+        html_page
+            .split(r#"name="csrf_token" value=""#)
+            .nth(1)
+            .and_then(|s| s.split('"').next())
+            .unwrap_or_default()
+            .to_owned()
+    }
+
     // Our tests will only look at the HTML page, therefore
     // we do not expose the underlying reqwest::Response
     pub async fn get_login_html(&self) -> String {
@@ -83,7 +107,14 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    /// Like the signup form, sends back the version of the current consent
+    /// text unless the body already has one.
     pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
+        let body = if body.contains("consent_version=") {
+            body
+        } else {
+            format!("{body}&consent_version={}", ConsentText::current().version)
+        };
         self.api_client
             .post(&format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
@@ -93,6 +124,21 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_subscriptions_json(&self, body: &serde_json::Value) -> reqwest::Response {
+        let mut body = body.clone();
+        if let Some(fields) = body.as_object_mut() {
+            fields
+                .entry("consent_version")
+                .or_insert(ConsentText::current().version.into());
+        }
+        self.api_client
+            .post(format!("{}/subscriptions", &self.address))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub fn get_confirmation_links(&self, email_request: &wiremock::Request) -> ConfirmationLinks {
         let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
         // Extract the link from one of the request fields.
@@ -118,6 +164,7 @@ impl TestApp {
     pub async fn post_newsletters(&self, body: &str) -> reqwest::Response {
         self.api_client
             .post(&format!("{}/admin/newsletters", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(body.to_string())
             .send()
@@ -163,6 +210,7 @@ impl TestApp {
     {
         self.api_client
             .post(&format!("{}/admin/password", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
             .form(body)
             .send()
             .await
@@ -173,9 +221,303 @@ impl TestApp {
         self.get_change_password().await.text().await.unwrap()
     }
 
+    pub async fn get_import_subscribers(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers/import", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_import_subscribers_html(&self) -> String {
+        self.get_import_subscribers().await.text().await.unwrap()
+    }
+
+    pub async fn post_import_subscribers<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/subscribers/import", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscribers_export(&self, format: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers/export", &self.address))
+            .query(&[("format", format)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_newsletter_issues_export(&self, format: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/newsletters/export", &self.address))
+            .query(&[("format", format)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscriber_data_export(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers/data/export", &self.address))
+            .query(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscriber_data_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/subscribers/data", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_erase_subscriber_data(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/subscribers/data/erase", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscribers_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/subscribers", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_subscriber(&self, subscriber_id: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/subscribers/{}",
+                &self.address, subscriber_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_lockouts_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/lockouts", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_two_factor_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/two-factor", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_enable_two_factor(&self, code: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/two-factor/enable", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(&[("code", code)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_disable_two_factor(&self, current_password: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/two-factor/disable", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(&[("current_password", current_password)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_login_two_factor(&self, code: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/login/two-factor", &self.address))
+            .form(&[("code", code)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_login_two_factor_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/login/two-factor", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_users_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/users", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_users<Body>(&self, path: &str, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/users{}", &self.address, path))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_password_reset_request(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/password-reset", &self.address))
+            .form(&serde_json::json!({ "email": email }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_password_reset_request_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/password-reset", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_password_reset<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/password-reset/confirm", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_email_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/email", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_email<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/email", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_sessions_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_sessions(&self, path: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/sessions{}", &self.address, path))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_api_tokens_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/api-tokens", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_api_tokens(&self, path: &str, body: &[(&str, &str)]) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/api-tokens{}", &self.address, path))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Create an API token for the logged in user, scraped from the page it is shown on.
+    pub async fn create_api_token(&self, scopes: &[&str]) -> String {
+        let mut body = vec![("name", "test token")];
+        body.extend(scopes.iter().map(|scope| ("scope", *scope)));
+        let response = self.post_api_tokens("", &body).await;
+        assert_is_redirect_to(&response, "/admin/api-tokens");
+        let html_page = self.get_api_tokens_html().await;
+        let start = html_page
+            .find("<code>z2p_")
+            .expect("No API token was created.")
+            + 6;
+        let end = start + html_page[start..].find("</code>").unwrap();
+        html_page[start..end].to_owned()
+    }
+
     pub async fn post_logout(&self) -> reqwest::Response {
         self.api_client
             .post(&format!("{}/admin/logout", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
             .send()
             .await
             .expect("Failed to execute request.")
@@ -198,6 +540,10 @@ impl TestUser {
     }
 
     async fn store(&self, pool: &PgPool) {
+        self.store_with_role(pool, "owner").await
+    }
+
+    pub async fn store_with_role(&self, pool: &PgPool, role: &str) {
         let salt = SaltString::generate(&mut rand::thread_rng());
         // We don't care about the exact Argon2 parameters here
         // given that it's for testing purposes!
@@ -211,11 +557,12 @@ impl TestUser {
         .to_string();
 
         sqlx::query!(
-            "INSERT INTO users (user_id, username, password_hash)
-            VALUES ($1, $2, $3)",
+            "INSERT INTO users (user_id, username, password_hash, role)
+            VALUES ($1, $2, $3, $4)",
             self.user_id,
             self.username,
             password_hash,
+            role,
         )
         .execute(pool)
         .await
@@ -232,6 +579,11 @@ impl TestUser {
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawn the app after adjusting its configuration with `configure`.
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     LazyLock::force(&TRACING);
 
     // Launch a mock server to stand in for Postmark's API
@@ -246,6 +598,9 @@ pub async fn spawn_app() -> TestApp {
         c.application.port = 0;
         // Use the mock server as email API
         c.email_client.base_url = email_server.uri();
+        // Rate limit counters are shared through Redis
+        c.anti_abuse.rate_limit_key_prefix = Uuid::new_v4().to_string();
+        configure(&mut c);
         c
     };
 
@@ -0,0 +1,245 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_import_subscribers_form() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_import_subscribers().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_import_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_import_subscribers(&serde_json::json!({
+            "csv_content": "email,name\nursula@example.com,Ursula Le Guin",
+            "default_status": "confirmed",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn imported_pending_subscribers_receive_a_confirmation_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_import_subscribers(&serde_json::json!({
+            "csv_content": "email,name\n\
+                ursula@example.com,Ursula Le Guin\n\
+                octavia@example.com,Octavia Butler\n",
+            "default_status": "pending_confirmation",
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("Imported 2 subscriber(s)."));
+    assert!(html_page.contains("Sending 2 confirmation email(s)."));
+    // The emails are sent by the background worker
+    app.dispatch_all_pending_emails().await;
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscriptions.");
+    assert_eq!(saved.len(), 2);
+    assert!(saved.iter().all(|s| s.status == "pending_confirmation"));
+}
+
+#[tokio::test]
+async fn subscribers_can_be_imported_as_already_confirmed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_import_subscribers(&serde_json::json!({
+            "csv_content": "email,name,tags\nursula@example.com,Ursula Le Guin,beta;vip\n",
+            "default_status": "confirmed",
+        }))
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+
+    let saved = sqlx::query!("SELECT email, status, tags FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.email, "ursula@example.com");
+    assert_eq!(saved.status, "confirmed");
+    assert_eq!(saved.tags, vec!["beta", "vip"]);
+}
+
+#[tokio::test]
+async fn a_row_status_overrides_the_default_status() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_import_subscribers(&serde_json::json!({
+        "csv_content": "email,name,status\n\
+            ursula@example.com,Ursula Le Guin,\n\
+            octavia@example.com,Octavia Butler,pending_confirmation\n",
+        "default_status": "confirmed",
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let saved = sqlx::query!("SELECT email, status FROM subscriptions ORDER BY email")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscriptions.");
+    assert_eq!(saved[0].email, "octavia@example.com");
+    assert_eq!(saved[0].status, "pending_confirmation");
+    assert_eq!(saved[1].email, "ursula@example.com");
+    assert_eq!(saved[1].status, "confirmed");
+}
+
+#[tokio::test]
+async fn duplicate_subscribers_are_skipped() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.post_import_subscribers(&serde_json::json!({
+        "csv_content": "email,name\nursula@example.com,Ursula Le Guin\n",
+        "default_status": "confirmed",
+    }))
+    .await;
+
+    // Act
+    let response = app
+        .post_import_subscribers(&serde_json::json!({
+            "csv_content": "email,name\n\
+                ursula@example.com,Ursula K. Le Guin\n\
+                octavia@example.com,Octavia Butler\n\
+                octavia@example.com,Octavia E. Butler\n",
+            "default_status": "confirmed",
+        }))
+        .await;
+
+    // Assert
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("Imported 1 subscriber(s)."));
+    assert!(html_page.contains("Skipped 2 duplicate(s)"));
+    assert!(html_page.contains("Row 2: ursula@example.com is already subscribed."));
+    assert!(html_page.contains("Row 4: octavia@example.com is already subscribed."));
+
+    let saved = sqlx::query!("SELECT name FROM subscriptions ORDER BY email")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscriptions.");
+    assert_eq!(saved.len(), 2);
+    assert_eq!(saved[0].name, "Octavia Butler");
+    assert_eq!(saved[1].name, "Ursula Le Guin");
+}
+
+#[tokio::test]
+async fn invalid_rows_are_reported_and_valid_rows_are_imported() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_import_subscribers(&serde_json::json!({
+            "csv_content": "email,name\n\
+                not-an-email,Ursula Le Guin\n\
+                octavia@example.com,Octavia Butler\n\
+                ted@example.com,\n",
+            "default_status": "confirmed",
+        }))
+        .await;
+
+    // Assert
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("Imported 1 subscriber(s)."));
+    assert!(html_page.contains("Found 2 row(s) with errors"));
+    assert!(html_page.contains("Row 2: not-an-email is not a valid subscriber email."));
+    assert!(html_page.contains("Row 4:  is not a valid subscriber name."));
+}
+
+#[tokio::test]
+async fn import_shows_error_on_invalid_csv_header() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_import_subscribers(&serde_json::json!({
+            "csv_content": "address,full_name\nursula@example.com,Ursula Le Guin\n",
+            "default_status": "confirmed",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/subscribers/import");
+
+    let html_page = app.get_import_subscribers_html().await;
+    assert!(html_page.contains("<p><i>The CSV header is missing an `email` column.</i></p>"));
+}
+
+#[tokio::test]
+async fn submitted_values_are_escaped_in_error_messages() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_import_subscribers(&serde_json::json!({
+            "csv_content": "email,name\nursula@example.com,Ursula Le Guin\n",
+            "default_status": "<b onmouseover=alert(1)>",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/subscribers/import");
+    let html_page = app.get_import_subscribers_html().await;
+    assert!(!html_page.contains("<b onmouseover"));
+    assert!(html_page.contains("&lt;b onmouseover=alert(1)&gt; is not a valid subscriber status."));
+}
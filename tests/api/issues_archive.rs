@@ -0,0 +1,320 @@
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+async fn create_confirmed_subscriber(app: &TestApp) {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, $2, 'Le Guin', now(), 'confirmed')
+        "#,
+        Uuid::new_v4(),
+        format!("{}@example.com", Uuid::new_v4())
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+async fn publish_issue(app: &TestApp, title: &str, public: bool) {
+    let mut body = format!(
+        "title={title}\
+        &text_content=Newsletter body as plain text\
+        &html_content=<p>Newsletter body as HTML</p>\
+        &idempotency_key={}",
+        Uuid::new_v4()
+    );
+    if public {
+        body.push_str("&public=on");
+    }
+    let response = app.post_newsletters(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+}
+
+async fn get(app: &TestApp, path: &str) -> reqwest::Response {
+    app.api_client
+        .get(format!("{}{}", &app.address, path))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn public_issues_are_listed_in_the_archive() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "A public issue", true).await;
+    publish_issue(&app, "A private issue", false).await;
+
+    // Act
+    let html_page = get(&app, "/issues").await.text().await.unwrap();
+
+    // Assert
+    assert!(html_page.contains(r#"<a href="/issues/a-public-issue">A public issue</a>"#));
+    assert!(!html_page.contains("A private issue"));
+}
+
+#[tokio::test]
+async fn a_public_issue_has_its_own_page() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "A public issue", true).await;
+
+    // Act
+    let response = get(&app, "/issues/a-public-issue").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<h1>A public issue</h1>"));
+    assert!(html_page.contains("<p>Newsletter body as HTML</p>"));
+}
+
+#[tokio::test]
+async fn a_private_issue_has_no_page() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "A private issue", false).await;
+
+    // Act
+    let response = get(&app, "/issues/a-private-issue").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn issues_with_the_same_title_get_different_pages() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    publish_issue(&app, "Weekly", true).await;
+    publish_issue(&app, "Weekly", true).await;
+
+    // Assert
+    for path in ["/issues/weekly", "/issues/weekly-2"] {
+        assert_eq!(get(&app, path).await.status().as_u16(), 200);
+    }
+}
+
+#[tokio::test]
+async fn the_archive_is_paginated() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    for i in 0..21 {
+        publish_issue(&app, &format!("Issue {i}"), true).await;
+    }
+
+    // Act
+    let first_page = get(&app, "/issues").await.text().await.unwrap();
+    let second_page = get(&app, "/issues?page=2").await.text().await.unwrap();
+
+    // Assert
+    assert!(first_page.contains(r#"<a href="/issues?page=2">Older &gt;</a>"#));
+    assert_eq!(first_page.matches("<li>").count(), 20);
+    assert!(second_page.contains(r#"<a href="/issues?page=1">&lt; Newer</a>"#));
+    assert_eq!(second_page.matches("<li>").count(), 1);
+}
+
+#[tokio::test]
+async fn pages_past_the_last_one_are_empty() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = get(&app, &format!("/issues?page={}", i64::MAX)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(!response.text().await.unwrap().contains("<li>"));
+}
+
+#[tokio::test]
+async fn the_home_page_links_to_the_archive() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let html_page = get(&app, "/").await.text().await.unwrap();
+
+    // Assert
+    assert!(html_page.contains(r#"<a href="/issues">"#));
+}
+
+#[tokio::test]
+async fn emails_of_public_issues_link_to_their_archive_page() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    publish_issue(&app, "A public issue", true).await;
+    publish_issue(&app, "A private issue", false).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let link = format!("{}/issues/a-public-issue", app.address);
+    for request in app.email_server.received_requests().await.unwrap() {
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        let is_public = body["Subject"] == "A public issue";
+        assert_eq!(
+            body["HtmlBody"].as_str().unwrap().contains(&link),
+            is_public
+        );
+        assert_eq!(
+            body["TextBody"].as_str().unwrap().contains(&link),
+            is_public
+        );
+    }
+}
+
+#[tokio::test]
+async fn the_rss_feed_lists_public_issues() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "A public issue", true).await;
+    publish_issue(&app, "A private issue", false).await;
+
+    // Act
+    let response = get(&app, "/feed.rss").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/rss+xml; charset=utf-8"
+    );
+    let feed = response.text().await.unwrap();
+    assert!(feed.contains("<title>A public issue</title>"));
+    assert!(feed.contains("/issues/a-public-issue</link>"));
+    assert!(feed.contains("&lt;p&gt;Newsletter body as HTML&lt;/p&gt;"));
+    assert!(!feed.contains("A private issue"));
+    let pub_date = feed
+        .split("<pubDate>")
+        .nth(1)
+        .and_then(|rest| rest.split("</pubDate>").next())
+        .unwrap();
+    let published_at = chrono::DateTime::parse_from_rfc2822(pub_date).unwrap();
+    assert!(chrono::Utc::now() - published_at.to_utc() < chrono::Duration::minutes(1));
+}
+
+#[tokio::test]
+async fn the_atom_feed_lists_public_issues() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "A public issue", true).await;
+    publish_issue(&app, "A private issue", false).await;
+
+    // Act
+    let response = get(&app, "/feed.atom").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/atom+xml; charset=utf-8"
+    );
+    let feed = response.text().await.unwrap();
+    assert!(feed.contains("<title>A public issue</title>"));
+    assert!(feed.contains(r#"/issues/a-public-issue"/>"#));
+    assert!(!feed.contains("A private issue"));
+}
+
+#[tokio::test]
+async fn a_feed_is_not_sent_again_if_its_etag_matches() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "A public issue", true).await;
+    let response = get(&app, "/feed.rss").await;
+    let etag = response.headers()["ETag"].clone();
+
+    // Act - Part 1 - The feed did not change
+    let response = app
+        .api_client
+        .get(format!("{}/feed.rss", &app.address))
+        .header("If-None-Match", etag.clone())
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert - Part 1
+    assert_eq!(response.status().as_u16(), 304);
+    assert!(response.text().await.unwrap().is_empty());
+
+    // Act - Part 2 - A new issue changes it
+    publish_issue(&app, "Another public issue", true).await;
+    let response = app
+        .api_client
+        .get(format!("{}/feed.rss", &app.address))
+        .header("If-None-Match", etag)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert - Part 2
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn a_feed_is_not_sent_again_if_it_was_not_modified_since() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "A public issue", true).await;
+    let response = get(&app, "/feed.atom").await;
+    let last_modified = response.headers()["Last-Modified"].clone();
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/feed.atom", &app.address))
+        .header("If-Modified-Since", last_modified)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 304);
+}
+
+#[tokio::test]
+async fn the_home_page_advertises_the_feeds() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let html_page = get(&app, "/").await.text().await.unwrap();
+
+    // Assert
+    assert!(html_page.contains(r#"type="application/rss+xml""#));
+    assert!(html_page.contains(r#"href="/feed.rss""#));
+    assert!(html_page.contains(r#"type="application/atom+xml""#));
+    assert!(html_page.contains(r#"href="/feed.atom""#));
+}
@@ -1,4 +1,4 @@
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app, spawn_app_with};
 
 #[tokio::test]
 async fn an_error_flash_message_is_set_on_failure() {
@@ -41,3 +41,222 @@ async fn redirect_to_admin_dashboard_after_login_success() {
     let html_page = app.get_admin_dashboard_html().await;
     assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
 }
+
+#[tokio::test]
+async fn a_username_is_locked_out_after_too_many_failures() {
+    // Arrange
+    let app = spawn_app().await;
+    // The limit in the base configuration
+    for _ in 0..5 {
+        let response = app
+            .post_login(&serde_json::json!({
+                "username": &app.test_user.username,
+                "password": "wrong-password"
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/login");
+    }
+
+    // Act - Part 1 - The right password is not even checked
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts. Try again in 15 minute(s)."));
+
+    // Act - Part 2 - The lockout is logged
+    let lockouts = sqlx::query!("SELECT scope, locked_key, failed_attempts FROM login_lockouts")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch lockouts.");
+    assert_eq!(lockouts.len(), 1);
+    assert_eq!(lockouts[0].scope, "username");
+    assert_eq!(lockouts[0].locked_key, app.test_user.username);
+    assert_eq!(lockouts[0].failed_attempts, 5);
+}
+
+#[tokio::test]
+async fn our_own_errors_do_not_count_towards_a_lockout() {
+    // Arrange
+    let app = spawn_app().await;
+    // Sabotage the database
+    sqlx::query!("ALTER TABLE users RENAME TO users_sabotaged")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    // The limit in the base configuration
+    for _ in 0..5 {
+        let response = app
+            .post_login(&serde_json::json!({
+                "username": &app.test_user.username,
+                "password": &app.test_user.password
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/login");
+    }
+    sqlx::query!("ALTER TABLE users_sabotaged RENAME TO users")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn concurrent_failures_cannot_exceed_the_limit() {
+    // Arrange
+    let app = spawn_app().await;
+    let login_body = serde_json::json!({
+        "username": &app.test_user.username,
+        "password": "wrong-password"
+    });
+    // One client per attempt, each with its own flash messages
+    let clients: Vec<reqwest::Client> = (0..10)
+        .map(|_| {
+            reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .cookie_store(true)
+                .build()
+                .unwrap()
+        })
+        .collect();
+
+    // Act
+    let responses = futures_util::future::join_all(clients.iter().map(|client| {
+        client
+            .post(format!("{}/login", &app.address))
+            .form(&login_body)
+            .send()
+    }))
+    .await;
+
+    // Assert
+    let mut checked = 0;
+    let mut locked_out = 0;
+    for (client, response) in clients.iter().zip(responses) {
+        assert_is_redirect_to(&response.unwrap(), "/login");
+        let html_page = client
+            .get(format!("{}/login", &app.address))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        if html_page.contains("Authentication failed") {
+            checked += 1;
+        } else if html_page.contains("Too many failed login attempts.") {
+            locked_out += 1;
+        }
+    }
+    // The limit in the base configuration
+    assert_eq!((checked, locked_out), (5, 5));
+}
+
+#[tokio::test]
+async fn unknown_usernames_are_locked_out_like_known_ones() {
+    // Arrange
+    let app = spawn_app().await;
+    let login_body = serde_json::json!({
+        "username": "random-username",
+        "password": "random-password"
+    });
+    for _ in 0..5 {
+        app.post_login(&login_body).await;
+    }
+
+    // Act
+    let response = app.post_login(&login_body).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Too many failed login attempts."));
+}
+
+#[tokio::test]
+async fn lockouts_are_listed_in_the_admin_area() {
+    // Arrange
+    let app = spawn_app().await;
+    for _ in 0..5 {
+        app.post_login(&serde_json::json!({
+            "username": "random-username",
+            "password": "random-password"
+        }))
+        .await;
+    }
+    // Locking out a username does not lock out the other users
+    app.test_user.login(&app).await;
+
+    // Act
+    let html_page = app.get_lockouts_html().await;
+
+    // Assert
+    assert!(html_page.contains("username: random-username"));
+}
+
+fn login_body(app: &TestApp) -> serde_json::Value {
+    serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password
+    })
+}
+
+async fn stored_password_hash(app: &TestApp) -> String {
+    sqlx::query_scalar!(
+        "SELECT password_hash FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn logging_in_upgrades_a_weaker_password_hash() {
+    // Arrange - the test user is stored with m=15000
+    let app = spawn_app_with(|c| c.password_hashing.memory_cost_kib = 19456).await;
+
+    // Act
+    let response = app.post_login(&login_body(&app)).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let password_hash = stored_password_hash(&app).await;
+    assert!(password_hash.starts_with("$argon2id$v=19$m=19456,t=2,p=1$"));
+
+    // The upgraded hash still verifies
+    let response = app.post_logout().await;
+    assert_is_redirect_to(&response, "/login");
+    let response = app.post_login(&login_body(&app)).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn logging_in_keeps_a_stronger_password_hash() {
+    // Arrange
+    let app = spawn_app_with(|c| c.password_hashing.memory_cost_kib = 8192).await;
+    let password_hash = stored_password_hash(&app).await;
+
+    // Act
+    let response = app.post_login(&login_body(&app)).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    assert_eq!(stored_password_hash(&app).await, password_hash);
+}
@@ -1,8 +1,20 @@
 mod admin_dashboard;
+mod api_tokens;
 mod change_password;
+mod csrf;
+mod export;
 mod health_check;
 mod helpers;
+mod import_subscribers;
+mod issues_archive;
 mod login;
 mod newsletter;
+mod openapi;
+mod password_reset;
+mod sessions;
+mod subscriber_consent;
+mod subscriber_data;
 mod subscriptions;
 mod subscriptions_confirm;
+mod two_factor;
+mod users;
@@ -5,8 +5,11 @@ use fake::faker::internet::en::SafeEmail;
 use fake::faker::name::en::Name;
 use wiremock::matchers::{any, method, path};
 use wiremock::{Mock, ResponseTemplate};
+use zero2prod::idempotency::delete_expired_keys;
 
-use crate::helpers::{ConfirmationLinks, TestApp, assert_is_redirect_to, spawn_app};
+use crate::helpers::{
+    ConfirmationLinks, TestApp, assert_is_redirect_to, spawn_app, spawn_app_with,
+};
 
 #[tokio::test]
 async fn you_must_be_logged_in_to_publish_a_newsletter() {
@@ -292,6 +295,148 @@ async fn newsletter_creation_is_idempotent() {
     // Mock verifies on Drop that we have sent the newsletter email **once**
 }
 
+#[tokio::test]
+async fn newsletters_can_be_written_in_markdown() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act - The editor sends its content even when Markdown is used
+    let markdown = "Some **bold** text and a [link](https://example.com).";
+    let body = serde_urlencoded::to_string([
+        ("title", "Newsletter title"),
+        ("html_content", "<p></p>"),
+        ("text_content", ""),
+        ("markdown_content", markdown),
+        ("idempotency_key", &uuid::Uuid::new_v4().to_string()),
+    ])
+    .unwrap();
+    let response = app.post_newsletters(&body).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let issue =
+        sqlx::query!("SELECT html_content, text_content, markdown_content FROM newsletter_issues")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert!(issue.html_content.contains("<h1"));
+    assert!(issue.html_content.contains("<strong>bold</strong>"));
+    assert_eq!(
+        issue.text_content,
+        "Some bold text and a link (https://example.com)."
+    );
+    assert_eq!(issue.markdown_content.as_deref(), Some(markdown));
+}
+
+#[tokio::test]
+async fn newsletters_with_scripts_are_refused_with_warnings() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Submit newsletter form
+    let body = serde_urlencoded::to_string([
+        ("title", "Newsletter title"),
+        (
+            "html_content",
+            r#"<p onclick="alert(1)">Hello!</p><script>alert(2)</script>"#,
+        ),
+        ("text_content", "Hello!"),
+        ("idempotency_key", &uuid::Uuid::new_v4().to_string()),
+    ])
+    .unwrap();
+    let response = app.post_newsletters(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app.get_newsletters_html().await;
+    assert!(
+        html_page
+            .contains("<p><i>The content cannot contain event handlers such as `onclick`.</i></p>")
+    );
+    assert!(html_page.contains("<p><i>The content cannot contain `script` elements.</i></p>"));
+
+    // Assert
+    let issues = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(issues.is_empty());
+}
+
+#[tokio::test]
+async fn the_styles_of_newsletters_are_inlined() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let body = serde_urlencoded::to_string([
+        ("title", "Newsletter title"),
+        (
+            "html_content",
+            "<style>p { color: red }</style><p>Hello!</p>",
+        ),
+        ("text_content", "Hello!"),
+        ("idempotency_key", &uuid::Uuid::new_v4().to_string()),
+    ])
+    .unwrap();
+    let response = app.post_newsletters(&body).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let issue = sqlx::query!("SELECT html_content FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.html_content, r#"<p style="color:red">Hello!</p>"#);
+}
+
+#[tokio::test]
+async fn reusing_an_idempotency_key_with_a_different_issue_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let idempotency_key = uuid::Uuid::new_v4();
+    let response = app
+        .post_newsletters(&format!(
+            "title=Newsletter title\
+            &text_content=Newsletter body as plain text\
+            &html_content=<p>Newsletter body as HTML</p>\
+            &idempotency_key={idempotency_key}",
+        ))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act - Submit a different issue with the same key
+    let response = app
+        .post_newsletters(&format!(
+            "title=Another title\
+            &text_content=Newsletter body as plain text\
+            &html_content=<p>Newsletter body as HTML</p>\
+            &idempotency_key={idempotency_key}",
+        ))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 422);
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that we have sent the newsletter email **once**
+}
+
 #[tokio::test]
 async fn concurrent_form_submission_is_handled_gracefully() {
     // Arrange
@@ -329,3 +474,167 @@ async fn concurrent_form_submission_is_handled_gracefully() {
     app.dispatch_all_pending_emails().await;
     // Mock verifies on Drop that we have sent the newsletter email **once**
 }
+
+#[tokio::test]
+async fn a_request_with_an_in_flight_idempotency_key_gets_a_409() {
+    // Arrange
+    let app = spawn_app_with(|c| c.idempotency.in_flight_timeout_milliseconds = 200).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Hold the key as a request that is still processing would
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
+    let mut in_flight = app.db_pool.begin().await.unwrap();
+    sqlx::query!(
+        "INSERT INTO idempotency (user_id, idempotency_key, created_at) VALUES ($1, $2, now())",
+        app.test_user.user_id,
+        idempotency_key
+    )
+    .execute(&mut *in_flight)
+    .await
+    .unwrap();
+
+    // Act
+    let response = app
+        .post_newsletters(&format!(
+            "title=Newsletter title\
+            &text_content=Newsletter body as plain text\
+            &html_content=<p>Newsletter body as HTML</p>\
+            &idempotency_key={idempotency_key}",
+        ))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 409);
+    in_flight.rollback().await.unwrap();
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn a_key_left_behind_by_an_unfinished_request_is_recovered() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
+    sqlx::query!(
+        "INSERT INTO idempotency (user_id, idempotency_key, created_at) VALUES ($1, $2, now())",
+        app.test_user.user_id,
+        idempotency_key
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let response = app
+        .post_newsletters(&format!(
+            "title=Newsletter title\
+            &text_content=Newsletter body as plain text\
+            &html_content=<p>Newsletter body as HTML</p>\
+            &idempotency_key={idempotency_key}",
+        ))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that we have sent the newsletter email **once**
+}
+
+#[tokio::test]
+async fn an_expired_idempotency_key_is_treated_as_new() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = format!(
+        "title=Newsletter title\
+        &text_content=Newsletter body as plain text\
+        &html_content=<p>Newsletter body as HTML</p>\
+        &idempotency_key={}",
+        uuid::Uuid::new_v4()
+    );
+    let response = app.post_newsletters(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Act - Age the saved response past the retention window and submit again
+    sqlx::query!("UPDATE idempotency SET created_at = now() - interval '2 days'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    let response = app.post_newsletters(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that we have sent the newsletter email **twice**
+}
+
+#[tokio::test]
+async fn expired_idempotency_keys_are_deleted() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let expired_key = uuid::Uuid::new_v4().to_string();
+    for idempotency_key in [expired_key.clone(), uuid::Uuid::new_v4().to_string()] {
+        let newsletter_request_body = format!(
+            "title=Newsletter title\
+            &text_content=Newsletter body as plain text\
+            &html_content=<p>Newsletter body as HTML</p>\
+            &idempotency_key={idempotency_key}",
+        );
+        app.post_newsletters(&newsletter_request_body).await;
+    }
+    sqlx::query!(
+        "UPDATE idempotency SET created_at = now() - interval '2 days' WHERE idempotency_key = $1",
+        expired_key
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let deleted = delete_expired_keys(&app.db_pool, chrono::Duration::days(1))
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(deleted, 1);
+    let remaining = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM idempotency"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 1);
+}
@@ -0,0 +1,26 @@
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+async fn the_openapi_document_describes_the_routes() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/api/openapi.json", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let document: serde_json::Value = response.json().await.unwrap();
+    assert!(document["openapi"].as_str().unwrap().starts_with("3."));
+    let subscribe = &document["paths"]["/subscriptions"]["post"];
+    assert!(subscribe["requestBody"]["content"]["application/json"].is_object());
+    assert_eq!(
+        document["components"]["securitySchemes"]["api_token"]["scheme"],
+        "bearer"
+    );
+}
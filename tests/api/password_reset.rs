@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+async fn set_test_user_email(app: &TestApp, email: &str) {
+    sqlx::query!(
+        "UPDATE users SET email = $1 WHERE user_id = $2",
+        email,
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+}
+
+// Request a reset for the email of the test user and return the link.
+async fn request_reset_link(app: &TestApp) -> reqwest::Url {
+    set_test_user_email(app, "ursula@example.com").await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let response = app.post_password_reset_request("ursula@example.com").await;
+    assert_is_redirect_to(&response, "/password-reset");
+    // The email is sent after the response
+    let email_request = loop {
+        if let Some(email_request) = app.email_server.received_requests().await.unwrap().pop() {
+            break email_request;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    };
+    app.get_confirmation_links(&email_request).html
+}
+
+fn reset_token(reset_link: &reqwest::Url) -> String {
+    reset_link
+        .query_pairs()
+        .find(|(k, _)| k == "reset_token")
+        .unwrap()
+        .1
+        .into_owned()
+}
+
+#[tokio::test]
+async fn the_login_page_links_to_the_password_reset_form() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let html_page = app.get_login_html().await;
+
+    // Assert
+    assert!(html_page.contains(r#"<a href="/password-reset">Forgot your password?</a>"#));
+}
+
+#[tokio::test]
+async fn unknown_emails_get_the_same_response_without_an_email() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_password_reset_request("nobody@example.com").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/password-reset");
+    let html_page = app.get_password_reset_request_html().await;
+    assert!(html_page.contains(
+        "<p><i>If an account exists for this email, \
+        you will receive a link to reset your password.</i></p>"
+    ));
+}
+
+#[tokio::test]
+async fn the_reset_form_enforces_the_password_length() {
+    // Arrange
+    let app = spawn_app().await;
+    let reset_link = request_reset_link(&app).await;
+    let reset_token = reset_token(&reset_link);
+
+    // Act
+    let response = app
+        .post_password_reset(&serde_json::json!({
+            "reset_token": &reset_token,
+            "new_password": "too-short",
+            "new_password_check": "too-short",
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(
+        response.headers().get("Location").unwrap(),
+        &format!("/password-reset/confirm?reset_token={reset_token}")
+    );
+    // The flash message cookie is bound to the address of the app
+    let html_page = app
+        .api_client
+        .get(format!(
+            "{}{}?{}",
+            &app.address,
+            reset_link.path(),
+            reset_link.query().unwrap()
+        ))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains(
+        "<p><i>The password's length is invalid - \
+        It has to be in the range of 12 to 128 characters</i></p>"
+    ));
+}
+
+#[tokio::test]
+async fn a_password_reset_logs_out_every_session_and_the_token_works_once() {
+    // Arrange
+    let app = spawn_app().await;
+    // A session started before the reset, e.g. by an attacker
+    app.test_user.login(&app).await;
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let reset_link = request_reset_link(&app).await;
+    let new_password = Uuid::new_v4().to_string();
+    let reset_body = serde_json::json!({
+        "reset_token": reset_token(&reset_link),
+        "new_password": &new_password,
+        "new_password_check": &new_password,
+    });
+    let reset = || app.post_password_reset(&reset_body);
+
+    // Act - Part 1 - Reset the password
+    let response = reset().await;
+    assert_is_redirect_to(&response, "/login");
+
+    // Act - Part 2 - The old session is no longer valid
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+
+    // Act - Part 3 - The new password works
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Act - Part 4 - The token cannot be used again
+    let response = reset().await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn expired_reset_tokens_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let reset_link = request_reset_link(&app).await;
+    sqlx::query!("UPDATE password_reset_tokens SET expires_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let response = app.api_client.get(reset_link).send().await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn submitted_emails_are_escaped_in_error_messages() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    app.post_email(&serde_json::json!({
+        "current_password": &app.test_user.password,
+        "email": "<b onmouseover=alert(1)>",
+    }))
+    .await;
+
+    // Assert
+    let html_page = app.get_email_html().await;
+    assert!(html_page.contains("&lt;b onmouseover=alert(1)&gt; is not a valid subscriber email."));
+    assert!(!html_page.contains("<b onmouseover=alert(1)>"));
+}
+
+#[tokio::test]
+async fn changing_the_email_requires_the_current_password() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Wrong password
+    let response = app
+        .post_email(&serde_json::json!({
+            "current_password": Uuid::new_v4().to_string(),
+            "email": "ursula@example.com",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/email");
+    let html_page = app.get_email_html().await;
+    assert!(html_page.contains("<p><i>The current password is incorrect.</i></p>"));
+
+    // Act - Part 2 - Right password
+    let response = app
+        .post_email(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "email": "ursula@example.com",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/email");
+    let html_page = app.get_email_html().await;
+    assert!(html_page.contains("Your email is ursula@example.com."));
+}
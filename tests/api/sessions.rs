@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use uuid::Uuid;
+use zero2prod::configuration::{CookieSameSite, SessionMode};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app, spawn_app_with};
+
+/// Log the test user in from another browser.
+async fn login_from_another_client(app: &TestApp) -> reqwest::Client {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .user_agent("another-browser")
+        .build()
+        .unwrap();
+    let response = client
+        .post(format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    client
+}
+
+async fn get_dashboard(app: &TestApp, client: &reqwest::Client) -> reqwest::Response {
+    client
+        .get(format!("{}/admin/dashboard", &app.address))
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_your_sessions() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/admin/sessions", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_sessions_page_lists_every_session_of_the_user() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    login_from_another_client(&app).await;
+
+    // Act
+    let html_page = app.get_sessions_html().await;
+
+    // Assert
+    assert!(html_page.contains("This session"));
+    assert!(html_page.contains("another-browser"));
+    assert!(html_page.contains("127.0.0.1"));
+    assert_eq!(
+        html_page
+            .matches(r#"<button type="submit">Log out</button>"#)
+            .count(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn logging_out_everywhere_else_keeps_the_current_session() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let other_client = login_from_another_client(&app).await;
+
+    // Act
+    let response = app.post_sessions("/logout-others").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/sessions");
+    let html_page = app.get_sessions_html().await;
+    assert!(
+        html_page.contains("<p><i>You have been logged out of all your other sessions.</i></p>")
+    );
+    assert!(!html_page.contains("another-browser"));
+    let response = get_dashboard(&app, &other_client).await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_single_session_can_be_logged_out() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let other_client = login_from_another_client(&app).await;
+    let session_id = sqlx::query_scalar!(
+        "SELECT session_id FROM user_sessions WHERE user_agent = 'another-browser'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let response = app.post_sessions(&format!("/{session_id}/logout")).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/sessions");
+    let response = get_dashboard(&app, &other_client).await;
+    assert_is_redirect_to(&response, "/login");
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn changing_the_password_logs_out_the_other_sessions() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let other_client = login_from_another_client(&app).await;
+    let new_password = Uuid::new_v4().to_string();
+
+    // Act
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/password");
+    let response = get_dashboard(&app, &other_client).await;
+    assert_is_redirect_to(&response, "/login");
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn logging_out_removes_the_session() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    app.post_logout().await;
+
+    // Assert
+    let count = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM user_sessions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn idle_sessions_expire() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.session.idle_timeout_seconds = 1).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let response = app.get_admin_dashboard().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("<p><i>Your session has expired, please log in again.</i></p>"));
+}
+
+#[tokio::test]
+async fn requests_keep_the_session_alive_until_its_absolute_lifetime() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.application.session.idle_timeout_seconds = 2;
+        c.application.session.absolute_lifetime_seconds = 4;
+    })
+    .await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Activity resets the idle timeout
+    for _ in 0..2 {
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let response = app.get_admin_dashboard().await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    // Act - Part 2 - But not the absolute lifetime
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_session_cookie_follows_the_configuration() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.application.session.cookie_name = "admin-session".into();
+        c.application.session.cookie_same_site = CookieSameSite::Strict;
+        c.application.session.mode = SessionMode::Persistent;
+    })
+    .await;
+
+    // Act
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+
+    // Assert
+    let cookie = response
+        .headers()
+        .get_all("Set-Cookie")
+        .iter()
+        .map(|h| h.to_str().unwrap())
+        .find(|c| c.starts_with("admin-session="))
+        .expect("The session cookie is missing.");
+    assert!(cookie.contains("SameSite=Strict"));
+    assert!(cookie.contains("Secure"));
+    assert!(cookie.contains("Max-Age=86400"));
+}
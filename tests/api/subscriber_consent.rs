@@ -0,0 +1,262 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::consent::ConsentText;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+const USER_AGENT: &str = "zero2prod-tests/1.0";
+
+#[tokio::test]
+async fn subscribing_and_confirming_record_consent_events() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.api_client
+        .post(format!("{}/subscriptions", &app.address))
+        .header("User-Agent", USER_AGENT)
+        .form(&[
+            ("name", "le guin"),
+            ("email", "ursula_le_guin@gmail.com"),
+            ("consent_version", ConsentText::current().version),
+        ])
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .error_for_status()
+        .unwrap();
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    app.api_client
+        .get(confirmation_links.html)
+        .header("User-Agent", USER_AGENT)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let events = sqlx::query!(
+        r#"
+        SELECT event_type, source, ip_address, user_agent, consent_text
+        FROM subscription_consent_events
+        ORDER BY recorded_at
+        "#
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch consent events.");
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].event_type, "subscribed");
+    assert_eq!(events[0].source, "subscription_form");
+    assert_eq!(events[0].ip_address.as_deref(), Some("127.0.0.1"));
+    assert_eq!(events[0].user_agent.as_deref(), Some(USER_AGENT));
+    assert_eq!(
+        events[0].consent_text.as_deref(),
+        Some(ConsentText::current().text)
+    );
+    assert_eq!(events[1].event_type, "confirmed");
+    assert_eq!(events[1].source, "confirmation_link");
+    assert_eq!(events[1].user_agent.as_deref(), Some(USER_AGENT));
+}
+
+#[tokio::test]
+async fn subscriptions_without_a_known_consent_version_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = vec![
+        (
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&consent_version=",
+            "an empty version",
+        ),
+        (
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&consent_version=1999-01-01",
+            "an unknown version",
+        ),
+    ];
+
+    for (body, description) in test_cases {
+        // Act
+        let response = app.post_subscriptions(body.into()).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request when the payload had {}.",
+            description
+        );
+    }
+    let n_events =
+        sqlx::query_scalar!(r#"SELECT count(*) as "count!" FROM subscription_consent_events"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(n_events, 0);
+}
+
+#[tokio::test]
+async fn subscriptions_from_an_unknown_source_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_subscriptions_json(&serde_json::json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com",
+            "source": "forged\nsource",
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["field"], "source");
+    assert_eq!(error["code"], "invalid_source");
+}
+
+#[tokio::test]
+async fn signup_forms_can_fetch_the_consent_text() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act - Part 1 - JSON clients
+    let response = app
+        .api_client
+        .get(format!("{}/subscriptions/consent", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200);
+    let consent: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(consent["version"], ConsentText::current().version);
+    assert_eq!(consent["text"], ConsentText::current().text);
+
+    // Act - Part 2 - The signup form on the home page
+    let html_page = app
+        .api_client
+        .get(format!("{}/", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains(ConsentText::current().text));
+    assert!(html_page.contains(&format!(
+        r#"name="consent_version" value="{}""#,
+        ConsentText::current().version
+    )));
+}
+
+#[tokio::test]
+async fn consent_events_cannot_be_updated() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    // Act
+    let result = sqlx::query!("UPDATE subscription_consent_events SET source = 'forged'")
+        .execute(&app.db_pool)
+        .await;
+
+    // Assert
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/admin/subscribers", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_subscriber_page_shows_the_consent_history() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .id;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - The list links to the subscriber
+    let html_page = app.get_subscribers_html().await;
+    assert!(html_page.contains(&format!("/admin/subscribers/{subscriber_id}")));
+    assert!(html_page.contains("ursula_le_guin@gmail.com"));
+
+    // Act - Part 2 - The subscriber page shows the consent events
+    let response = app.get_subscriber(&subscriber_id.to_string()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("subscribed"));
+    assert!(html_page.contains("subscription_form"));
+    assert!(html_page.contains("127.0.0.1"));
+}
+
+#[tokio::test]
+async fn pages_past_the_last_one_are_empty() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!(
+            "{}/admin/subscribers?page={}",
+            &app.address,
+            i64::MAX
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn an_unknown_subscriber_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_subscriber(&uuid::Uuid::new_v4().to_string()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
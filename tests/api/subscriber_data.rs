@@ -0,0 +1,229 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app};
+
+/// Subscribe through the public API and return the subscription token
+/// sent in the confirmation email.
+async fn subscribe(app: &TestApp, email: &str) -> String {
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions(format!(
+        "name=le%20guin&email={}",
+        urlencoding::encode(email)
+    ))
+    .await
+    .error_for_status()
+    .unwrap();
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_link = app.get_confirmation_links(email_request).html;
+    confirmation_link
+        .query_pairs()
+        .find(|(k, _)| k == "subscription_token")
+        .unwrap()
+        .1
+        .into_owned()
+}
+
+async fn get_own_data(app: &TestApp, subscription_token: &str) -> reqwest::Response {
+    app.api_client
+        .get(format!("{}/subscriptions/data", &app.address))
+        .query(&[("subscription_token", subscription_token)])
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+async fn post_erase_own_data(app: &TestApp, subscription_token: &str) -> reqwest::Response {
+    app.api_client
+        .post(format!("{}/subscriptions/erase", &app.address))
+        .form(&[("subscription_token", subscription_token)])
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_export_a_subscribers_data() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_subscriber_data_export("ursula@example.com").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_erase_a_subscribers_data() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_erase_subscriber_data("ursula@example.com").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn admins_can_export_everything_held_about_a_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscription_token = subscribe(&app, "ursula@example.com").await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_subscriber_data_export("ursula@example.com").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let bundle: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(bundle["subscription"]["email"], "ursula@example.com");
+    assert_eq!(bundle["subscription"]["status"], "pending_confirmation");
+    assert_eq!(
+        bundle["subscription_tokens"],
+        serde_json::json!([subscription_token])
+    );
+    assert_eq!(bundle["pending_deliveries"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn exporting_an_unknown_subscriber_shows_an_error() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_subscriber_data_export("ursula@example.com").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/subscribers/data");
+    let html_page = app.get_subscriber_data_html().await;
+    assert!(html_page.contains("<p><i>There is no subscriber with the given email.</i></p>"));
+}
+
+#[tokio::test]
+async fn erasure_removes_every_trace_but_keeps_issue_statistics() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscription_token = subscribe(&app, "ursula@example.com").await;
+    app.api_client
+        .get(format!("{}/subscriptions/confirm", &app.address))
+        .query(&[("subscription_token", &subscription_token)])
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    app.test_user.login(&app).await;
+    app.post_newsletters(&format!(
+        "title=Hello!&html_content=<p>Hello!</p>&text_content=Hello!&idempotency_key={}",
+        uuid::Uuid::new_v4()
+    ))
+    .await;
+
+    // Act
+    let response = app.post_erase_subscriber_data("ursula@example.com").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/subscribers/data");
+    let html_page = app.get_subscriber_data_html().await;
+    assert!(html_page.contains("<p><i>The subscriber's data has been erased.</i></p>"));
+
+    let n_subscriptions = sqlx::query_scalar!(r#"SELECT count(*) as "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    let n_tokens = sqlx::query_scalar!(r#"SELECT count(*) as "count!" FROM subscription_tokens"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    let n_deliveries =
+        sqlx::query_scalar!(r#"SELECT count(*) as "count!" FROM issue_delivery_queue"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!((n_subscriptions, n_tokens, n_deliveries), (0, 0, 0));
+
+    let issue = sqlx::query!("SELECT recipients_count, dropped_count FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!((issue.recipients_count, issue.dropped_count), (1, 1));
+}
+
+#[tokio::test]
+async fn subscribers_can_download_their_own_data() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscription_token = subscribe(&app, "ursula@example.com").await;
+
+    // Act
+    let response = get_own_data(&app, &subscription_token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let bundle: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(bundle["subscription"]["email"], "ursula@example.com");
+}
+
+#[tokio::test]
+async fn subscribers_can_erase_their_own_data() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscription_token = subscribe(&app, "ursula@example.com").await;
+
+    // Act
+    let response = post_erase_own_data(&app, &subscription_token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let n_subscriptions = sqlx::query_scalar!(r#"SELECT count(*) as "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(n_subscriptions, 0);
+
+    // The token is gone with the rest of the data
+    let response = get_own_data(&app, &subscription_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn an_unknown_token_is_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscription_token = "a".repeat(25);
+
+    // Act
+    let data_response = get_own_data(&app, &subscription_token).await;
+    let erase_response = post_erase_own_data(&app, &subscription_token).await;
+
+    // Assert
+    assert_eq!(data_response.status().as_u16(), 401);
+    assert_eq!(erase_response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn a_malformed_token_is_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = get_own_data(&app, "not-a-token").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
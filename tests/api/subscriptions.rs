@@ -1,6 +1,7 @@
-use crate::helpers::spawn_app;
+use crate::helpers::{spawn_app, spawn_app_with};
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, ResponseTemplate};
+use zero2prod::consent::ConsentText;
 
 #[tokio::test]
 async fn subscribe_returns_a_200_for_valid_form_data() {
@@ -166,7 +167,7 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     let app = spawn_app().await;
     let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
     // Sabotage the database
-    sqlx::query!("ALTER TABLE subscription_tokens DROP COLUMN subscription_token")
+    sqlx::query!("ALTER TABLE subscription_tokens DROP COLUMN subscription_token CASCADE")
         .execute(&app.db_pool)
         .await
         .unwrap();
@@ -177,3 +178,273 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     // Assert
     assert_eq!(response.status().as_u16(), 500);
 }
+
+#[tokio::test]
+async fn subscribe_ignores_requests_that_fill_in_the_honeypot() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com&website=http%3A%2F%2Fspam.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscriptions.");
+    assert!(saved.is_empty());
+}
+
+#[tokio::test]
+async fn subscribe_limits_the_confirmation_emails_sent_to_an_address() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        // The limit in the base configuration
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    for _ in 0..5 {
+        // Act
+        let response = app.post_subscriptions(body.into()).await;
+
+        // Assert
+        assert_eq!(200, response.status().as_u16());
+    }
+}
+
+#[tokio::test]
+async fn subscribe_limits_the_requests_from_an_ip_address() {
+    // Arrange
+    let app = spawn_app().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    for i in 0..12 {
+        let body = format!("name=le%20guin&email=ursula_{i}%40gmail.com");
+        let response = app.post_subscriptions(body).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    // Assert
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscriptions.");
+    // The limit in the base configuration
+    assert_eq!(saved.len(), 10);
+}
+
+#[tokio::test]
+async fn a_forwarded_ip_address_does_not_bypass_the_limit() {
+    // Arrange
+    let app = spawn_app().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act - A new forwarded address for each request
+    for i in 0..12 {
+        let response = app
+            .api_client
+            .post(format!("{}/subscriptions", &app.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("X-Forwarded-For", format!("198.51.100.{i}"))
+            .body(format!(
+                "name=le%20guin&email=ursula_{i}%40gmail.com&consent_version={}",
+                ConsentText::current().version
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    // Assert
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscriptions.");
+    assert_eq!(saved.len(), 10);
+}
+
+#[tokio::test]
+async fn the_ip_address_forwarded_by_a_trusted_proxy_is_limited() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.application.trusted_proxies = vec!["127.0.0.1".parse().unwrap()];
+    })
+    .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Two clients behind the same proxy
+    for i in 0..12 {
+        let response = app
+            .api_client
+            .post(format!("{}/subscriptions", &app.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("X-Forwarded-For", format!("198.51.100.{}", i % 2))
+            .body(format!(
+                "name=le%20guin&email=ursula_{i}%40gmail.com&consent_version={}",
+                ConsentText::current().version
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    // Assert
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscriptions.");
+    assert_eq!(saved.len(), 12);
+}
+
+#[tokio::test]
+async fn subscribe_accepts_json_bodies() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_subscriptions_json(&serde_json::json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com"
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(body["message"].is_string());
+    let saved = sqlx::query!("SELECT email, status FROM subscriptions",)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn subscribe_returns_structured_errors_to_json_clients() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = vec![
+        (
+            serde_json::json!({"name": "Ursula", "email": "definitely-not-an-email"}),
+            "email",
+            "invalid_email",
+        ),
+        (
+            serde_json::json!({"name": "", "email": "ursula_le_guin@gmail.com"}),
+            "name",
+            "invalid_name",
+        ),
+        (
+            serde_json::json!({"name": "Ursula"}),
+            "body",
+            "invalid_body",
+        ),
+    ];
+
+    for (body, field, code) in test_cases {
+        // Act
+        let response = app.post_subscriptions_json(&body).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 400, "The payload was {body}.");
+        let error: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(error["field"], field);
+        assert_eq!(error["code"], code);
+        assert!(error["message"].is_string());
+    }
+}
+
+#[tokio::test]
+async fn form_clients_still_get_plain_text_errors() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_subscriptions("name=Ursula&email=definitely-not-an-email".into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "definitely-not-an-email is not a valid subscriber email."
+    );
+}
+
+#[tokio::test]
+async fn cross_origin_requests_are_only_allowed_from_configured_origins() {
+    // Arrange
+    let app = spawn_app_with(|c| {
+        c.application.cors.allowed_origins = vec!["https://signup.example.com".into()]
+    })
+    .await;
+    let preflight = |origin: &'static str| {
+        reqwest::Client::new()
+            .request(
+                reqwest::Method::OPTIONS,
+                format!("{}/subscriptions", &app.address),
+            )
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", "POST")
+            .header("Access-Control-Request-Headers", "content-type")
+            .send()
+    };
+
+    // Act - Part 1 - An allowed origin
+    let response = preflight("https://signup.example.com").await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Access-Control-Allow-Origin"],
+        "https://signup.example.com"
+    );
+
+    // Act - Part 2 - Any other origin
+    let response = preflight("https://evil.example.com").await.unwrap();
+    assert!(
+        !response
+            .headers()
+            .contains_key("Access-Control-Allow-Origin")
+    );
+}
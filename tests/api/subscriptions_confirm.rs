@@ -54,6 +54,30 @@ async fn confirmations_with_non_existing_token_are_rejected_with_a_401() {
     assert_eq!(response.status().as_u16(), 401);
 }
 
+#[tokio::test]
+async fn json_clients_get_structured_confirmation_errors() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/subscriptions/confirm?subscription_token=qtRU7OFzB5y6QFTN1D4WquVoU",
+            app.address
+        ))
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["field"], "subscription_token");
+    assert_eq!(error["code"], "unknown_token");
+    assert_eq!(error["message"], "The subscription token is unknown.");
+}
+
 #[tokio::test]
 async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
     // Arrange
@@ -0,0 +1,243 @@
+use std::time::Duration;
+
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, spawn_app, spawn_app_with};
+
+fn current_code(secret: &str) -> String {
+    let secret = Secret::Encoded(secret.to_owned()).to_bytes().unwrap();
+    TOTP::new(Algorithm::SHA1, 6, 1, 30, secret, None, "".into())
+        .unwrap()
+        .generate_current()
+        .unwrap()
+}
+
+/// Log in, enroll the test user and log out again.
+/// Returns the TOTP secret and the recovery codes.
+async fn enroll(app: &TestApp) -> (String, Vec<String>) {
+    app.test_user.login(app).await;
+    let html_page = app.get_two_factor_html().await;
+    let secret = html_page
+        .split("Secret: <code>")
+        .nth(1)
+        .unwrap()
+        .split("</code>")
+        .next()
+        .unwrap()
+        .to_owned();
+    let html_page = app
+        .post_enable_two_factor(&current_code(&secret))
+        .await
+        .text()
+        .await
+        .unwrap();
+    let recovery_codes = html_page
+        .split("<li><code>")
+        .skip(1)
+        .map(|s| s.split("</code>").next().unwrap().to_owned())
+        .collect();
+    app.post_logout().await;
+    (secret, recovery_codes)
+}
+
+async fn post_password(app: &TestApp) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": &app.test_user.username,
+        "password": &app.test_user.password
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_set_up_two_factor_authentication() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_enable_two_factor("123456").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn enrollment_stores_the_secret_and_hashed_recovery_codes() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let (secret, recovery_codes) = enroll(&app).await;
+
+    // Assert
+    assert_eq!(recovery_codes.len(), 10);
+    let user = sqlx::query!(
+        "SELECT totp_secret FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(user.totp_secret, Some(secret));
+    let stored = sqlx::query!("SELECT code_hash FROM recovery_codes")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 10);
+    assert!(
+        stored
+            .iter()
+            .all(|r| !recovery_codes.contains(&r.code_hash))
+    );
+}
+
+#[tokio::test]
+async fn enrollment_fails_with_a_wrong_code() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.get_two_factor_html().await;
+
+    // Act
+    let response = app.post_enable_two_factor("000000").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/two-factor");
+    let html_page = app.get_two_factor_html().await;
+    assert!(html_page.contains("<p><i>The authentication code is invalid.</i></p>"));
+    let user = sqlx::query!(
+        "SELECT totp_secret FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert!(user.totp_secret.is_none());
+}
+
+#[tokio::test]
+async fn the_password_alone_does_not_log_in_an_enrolled_user() {
+    // Arrange
+    let app = spawn_app().await;
+    let (secret, _) = enroll(&app).await;
+
+    // Act - Part 1 - Password
+    let response = post_password(&app).await;
+    assert_is_redirect_to(&response, "/login/two-factor");
+
+    // Act - Part 2 - The admin area is still off limits
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+
+    // Act - Part 3 - Authentication code
+    let response = app.post_login_two_factor(&current_code(&secret)).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
+}
+
+#[tokio::test]
+async fn a_wrong_code_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    enroll(&app).await;
+    post_password(&app).await;
+
+    // Act
+    let response = app.post_login_two_factor("000000").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login/two-factor");
+    let html_page = app.get_login_two_factor_html().await;
+    assert!(html_page.contains("<p><i>The authentication code is invalid.</i></p>"));
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_code_cannot_be_used_twice() {
+    // Arrange
+    let app = spawn_app().await;
+    let (secret, _) = enroll(&app).await;
+    let code = current_code(&secret);
+
+    // Act - Part 1 - First use
+    post_password(&app).await;
+    let response = app.post_login_two_factor(&code).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    app.post_logout().await;
+
+    // Act - Part 2 - Replay, while the code is still valid
+    post_password(&app).await;
+    let response = app.post_login_two_factor(&code).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login/two-factor");
+    let html_page = app.get_login_two_factor_html().await;
+    assert!(html_page.contains("<p><i>The authentication code is invalid.</i></p>"));
+}
+
+#[tokio::test]
+async fn the_code_must_follow_the_password_shortly() {
+    // Arrange
+    let app = spawn_app_with(|c| c.application.session.two_factor_timeout_seconds = 1).await;
+    let (secret, _) = enroll(&app).await;
+    post_password(&app).await;
+
+    // Act
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let response = app.post_login_two_factor(&current_code(&secret)).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(
+        html_page
+            .contains("<p><i>Your login has expired, please enter your password again.</i></p>")
+    );
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_recovery_code_can_only_be_used_once() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, recovery_codes) = enroll(&app).await;
+
+    // Act - Part 1 - First use
+    post_password(&app).await;
+    let response = app.post_login_two_factor(&recovery_codes[0]).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    app.post_logout().await;
+
+    // Act - Part 2 - Second use
+    post_password(&app).await;
+    let response = app.post_login_two_factor(&recovery_codes[0]).await;
+    assert_is_redirect_to(&response, "/login/two-factor");
+}
+
+#[tokio::test]
+async fn disabling_two_factor_authentication_requires_the_password() {
+    // Arrange
+    let app = spawn_app().await;
+    let (secret, _) = enroll(&app).await;
+    post_password(&app).await;
+    app.post_login_two_factor(&current_code(&secret)).await;
+
+    // Act - Part 1 - Wrong password
+    let response = app.post_disable_two_factor("wrong-password").await;
+    assert_is_redirect_to(&response, "/admin/two-factor");
+    let html_page = app.get_two_factor_html().await;
+    assert!(html_page.contains("<p><i>The current password is incorrect.</i></p>"));
+
+    // Act - Part 2 - Right password
+    let response = app.post_disable_two_factor(&app.test_user.password).await;
+    assert_is_redirect_to(&response, "/admin/two-factor");
+    let html_page = app.get_two_factor_html().await;
+    assert!(html_page.contains("<p><i>Two-factor authentication has been disabled.</i></p>"));
+
+    // Assert
+    app.post_logout().await;
+    let response = post_password(&app).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
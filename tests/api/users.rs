@@ -0,0 +1,294 @@
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{TestApp, TestUser, assert_is_redirect_to, spawn_app};
+
+/// Store a new user with the given role and log in as them.
+async fn login_as(app: &TestApp, role: &str) -> TestUser {
+    let user = TestUser::generate();
+    user.store_with_role(&app.db_pool, role).await;
+    user.login(app).await;
+    user
+}
+
+#[tokio::test]
+async fn you_must_be_an_owner_to_manage_users() {
+    // Arrange
+    let app = spawn_app().await;
+    login_as(&app, "editor").await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/admin/users", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 403);
+}
+
+#[tokio::test]
+async fn viewers_cannot_publish_newsletters() {
+    // Arrange
+    let app = spawn_app().await;
+    login_as(&app, "viewer").await;
+
+    // Act
+    let response = app
+        .post_newsletters(&format!(
+            "title=Hello!&html_content=<p>Hello!</p>&text_content=Hello!&idempotency_key={}",
+            uuid::Uuid::new_v4()
+        ))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 403);
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains("You are logged in as viewer."));
+    assert!(!html_page.contains("/admin/users"));
+}
+
+#[tokio::test]
+async fn an_owner_can_create_a_user_who_can_then_log_in() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Create the user
+    let response = app
+        .post_users(
+            "",
+            &serde_json::json!({
+                "username": "octavia",
+                "password": "a-long-enough-password",
+                "role": "editor",
+            }),
+        )
+        .await;
+    assert_is_redirect_to(&response, "/admin/users");
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains("<p><i>The user octavia has been created.</i></p>"));
+
+    // Act - Part 2 - Log in as the new user
+    app.post_logout().await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": "octavia",
+            "password": "a-long-enough-password"
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains("You are logged in as editor."));
+}
+
+#[tokio::test]
+async fn usernames_must_be_unique() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    app.post_users(
+        "",
+        &serde_json::json!({
+            "username": &app.test_user.username,
+            "password": "a-long-enough-password",
+            "role": "viewer",
+        }),
+    )
+    .await;
+
+    // Assert
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains(&format!(
+        "The username {} is already taken.",
+        app.test_user.username
+    )));
+}
+
+#[tokio::test]
+async fn submitted_values_are_escaped_in_error_messages() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    app.post_users(
+        "",
+        &serde_json::json!({
+            "username": "ursula",
+            "password": "a-long-enough-password",
+            "role": "<b onmouseover=alert(1)>",
+        }),
+    )
+    .await;
+
+    // Assert
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains("&lt;b onmouseover=alert(1)&gt; is not a valid role."));
+    assert!(!html_page.contains("<b onmouseover=alert(1)>"));
+}
+
+#[tokio::test]
+async fn a_disabled_user_is_logged_out_and_cannot_log_in_again() {
+    // Arrange
+    let app = spawn_app().await;
+    let user = login_as(&app, "editor").await;
+    let editor_client = app.api_client.clone();
+    let owner_client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    owner_client
+        .post(format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    // Act - Part 1 - Disable the user
+    let response = owner_client
+        .post(format!(
+            "{}/admin/users/{}/disable",
+            &app.address, user.user_id
+        ))
+        .header("X-CSRF-Token", app.csrf_token_for(&owner_client).await)
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/users");
+
+    // Act - Part 2 - The existing session is over
+    let response = editor_client
+        .get(format!("{}/admin/dashboard", &app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/login");
+
+    // Act - Part 3 - Logging in fails
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &user.username,
+            "password": &user.password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page.contains("Authentication failed"));
+}
+
+#[tokio::test]
+async fn an_owner_cannot_disable_or_delete_themselves() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    app.post_users(
+        &format!("/{}/delete", app.test_user.user_id),
+        &serde_json::json!({}),
+    )
+    .await;
+
+    // Assert
+    let html_page = app.get_users_html().await;
+    assert!(html_page.contains("You cannot delete your own account."));
+}
+
+#[tokio::test]
+async fn a_deleted_user_is_removed() {
+    // Arrange
+    let app = spawn_app().await;
+    let user = TestUser::generate();
+    user.store_with_role(&app.db_pool, "viewer").await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_users(&format!("/{}/delete", user.user_id), &serde_json::json!({}))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/users");
+    let deleted = sqlx::query!("SELECT user_id FROM users WHERE user_id = $1", user.user_id)
+        .fetch_optional(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(deleted.is_none());
+}
+
+#[tokio::test]
+async fn an_invited_user_can_create_their_account_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Part 1 - Invite
+    let response = app
+        .post_users(
+            "/invite",
+            &serde_json::json!({
+                "email": "octavia@example.com",
+                "role": "viewer",
+            }),
+        )
+        .await;
+    assert_is_redirect_to(&response, "/admin/users");
+    app.post_logout().await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let invitation_link = app.get_confirmation_links(email_request).html;
+    let invitation_token = invitation_link
+        .query_pairs()
+        .find(|(k, _)| k == "invitation_token")
+        .unwrap()
+        .1
+        .into_owned();
+
+    // Act - Part 2 - Accept
+    let html_page = app
+        .api_client
+        .get(invitation_link.clone())
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("You have been invited as viewer"));
+    let accept = |username: &'static str| {
+        app.api_client
+            .post(format!("{}/invitations/accept", &app.address))
+            .form(&serde_json::json!({
+                "invitation_token": &invitation_token,
+                "username": username,
+                "password": "a-long-enough-password",
+                "password_check": "a-long-enough-password",
+            }))
+            .send()
+    };
+    let response = accept("octavia").await.unwrap();
+    assert_is_redirect_to(&response, "/login");
+    let user = sqlx::query!("SELECT role FROM users WHERE username = 'octavia'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(user.role, "viewer");
+
+    // Act - Part 3 - The invitation cannot be used again
+    let response = accept("octavia2").await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+}